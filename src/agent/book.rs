@@ -0,0 +1,354 @@
+use std::{collections::{BTreeMap, HashSet}, error::Error, fmt, str::FromStr};
+use crate::logic::{
+    action::{list_actions, Action},
+    game_record::GameRecord,
+    game_state::{GameState, Phase, Token},
+    position::{Board, PositionError},
+    rules::RuleSet,
+    symmetry::{canonicalize, Symmetry}
+};
+use super::{context::{SearchContext, SearchLimits}, search, AiPhase, SearchResult};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookMove {
+    pub action: Action,
+    pub weight: u32
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BookError {
+    InvalidPosition(PositionError),
+    NotSetPhase(String),
+    InvalidMove(String),
+    IllegalMove(String)
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookError::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+            BookError::NotSetPhase(position) => write!(f, "position '{}' is not in the set phase", position),
+            BookError::InvalidMove(book_move) => write!(f, "invalid move '{}'", book_move),
+            BookError::IllegalMove(book_move) => write!(f, "illegal move '{}'", book_move)
+        }
+    }
+}
+
+impl Error for BookError {}
+
+/*
+    Weighted moves for positions of the set phase. During the set phase a
+    position is given by the rules, the board and the step counter, which
+    also determines the player to move and the tokens in hand. Symmetric
+    positions share their moves: positions are stored as their canonical
+    board and the moves in the orientation of that board.
+
+    A book is written with one position per line, the position string
+    followed by the moves with their weights:
+        EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 d6:3 b6:1
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    positions: BTreeMap<(RuleSet, u16, u64), Vec<BookMove>>
+}
+
+fn get_key(board: Board, step_counter: u16, rules: RuleSet) -> ((RuleSet, u16, u64), Symmetry) {
+    let (canonical, symmetry) = canonicalize(board, rules);
+    ((rules, step_counter, canonical.to_raw()), symmetry)
+}
+
+fn get_game_state(board: Board, step_counter: u16, rules: RuleSet) -> GameState {
+    let mut game_state = GameState::new(rules);
+    game_state.set_board(board);
+    for _ in 0..step_counter {
+        game_state.increase_step_counter();
+        game_state.change_player();
+    }
+    game_state
+}
+
+fn action_key(action: Action) -> (usize, usize, usize, usize) {
+    (action.start_position.unwrap_or(24), action.end_position, action.beatable_position.unwrap_or(24), action.second_beatable_position.unwrap_or(24))
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        OpeningBook::default()
+    }
+
+    pub fn get_position_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /*
+        Adds the weight to the action in the position. If the canonical
+        board is symmetric itself, equivalent actions are stored as one,
+        the one with the smallest positions. Positions after the set
+        phase are ignored.
+    */
+    pub fn add(&mut self, game_state: &GameState, action: Action, weight: u32) {
+        if game_state.get_phase() != Phase::Set {
+            return;
+        }
+        let board = game_state.get_board();
+        let (key, symmetry) = get_key(board, game_state.get_step_counter(), game_state.get_rules());
+        let canonical = symmetry.transform_board(board);
+        let action = Symmetry::all_of(game_state.get_rules())
+            .filter(|other| other.transform_board(board) == canonical)
+            .map(|other| other.transform_action(action))
+            .min_by_key(|action| action_key(*action))
+            .unwrap_or(action);
+
+        let moves = self.positions.entry(key).or_default();
+        match moves.iter_mut().find(|book_move| book_move.action == action) {
+            Some(book_move) => book_move.weight = book_move.weight.saturating_add(weight),
+            None => moves.push(BookMove { action, weight })
+        }
+        moves.sort_by_key(|book_move| (u32::MAX - book_move.weight, action_key(book_move.action)));
+    }
+
+    /* the moves of the position in its own orientation, the one with the highest weight first */
+    pub fn get_moves(&self, board: Board, step_counter: u16, rules: RuleSet) -> Vec<BookMove> {
+        let (key, symmetry) = get_key(board, step_counter, rules);
+        let inverse = symmetry.inverse();
+        self.positions.get(&key)
+            .map(|moves| moves.iter()
+                .map(|book_move| BookMove { action: inverse.transform_action(book_move.action), weight: book_move.weight })
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /*
+        Without a random number the move with the highest weight is
+        chosen, with one every move with a probability proportional to
+        its weight.
+    */
+    pub fn choose(&self, board: Board, step_counter: u16, rules: RuleSet, random: Option<u64>) -> Option<Action> {
+        let moves = self.get_moves(board, step_counter, rules);
+        let total = moves.iter().map(|book_move| book_move.weight as u64).sum::<u64>();
+        let Some(random) = random.filter(|_| total > 0) else {
+            return moves.first().map(|book_move| book_move.action);
+        };
+
+        let mut remaining = random % total;
+        for book_move in moves.iter() {
+            if remaining < book_move.weight as u64 {
+                return Some(book_move.action);
+            }
+            remaining -= book_move.weight as u64;
+        }
+        None
+    }
+
+    /* adds every action of the set phase of the game with weight 1 */
+    pub fn add_game(&mut self, record: &GameRecord) {
+        let mut game_state = record.get_initial_state();
+        for action in record.get_actions() {
+            self.add(&game_state, action, 1);
+            game_state.apply_action(&action);
+        }
+    }
+
+    /*
+        Searches every position that is reached after less than the given
+        number of plies from the empty board of the rules and adds the best
+        action with weight 1. Symmetric positions are searched only once, so
+        the first plies only have a few positions, e.g. 4 after the first ply.
+    */
+    pub fn add_searches(&mut self, rules: RuleSet, plies: u8, limits: SearchLimits, mut on_result: impl FnMut(&GameState, &SearchResult)) {
+        let mut positions = vec![GameState::new(rules)];
+        let mut thread_pool = None;
+        for _ in 0..plies.min(rules.get_set_plies()) {
+            let mut next_positions = Vec::new();
+            let mut seen = HashSet::new();
+            for game_state in positions.iter() {
+                let player = game_state.get_player_turn();
+                let context = SearchContext::new(limits).with_thread_pool(thread_pool.take());
+                thread_pool = context.get_thread_pool();
+                let result = search(game_state.get_board(), player, AiPhase::from_game_state(game_state), &context, |_| {})
+                    .expect("no legal action");
+                on_result(game_state, &result);
+                self.add(game_state, result.action, 1);
+
+                for action in list_actions(game_state.get_board(), Token::parse_to_u8(player), Phase::Set, None, game_state.get_rules()) {
+                    let mut next = *game_state;
+                    next.apply_action(&action);
+                    if seen.insert(get_key(next.get_board(), next.get_step_counter(), rules).0) {
+                        next_positions.push(next);
+                    }
+                }
+            }
+            positions = next_positions;
+        }
+    }
+}
+
+impl fmt::Display for OpeningBook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for ((rules, step_counter, board), moves) in self.positions.iter() {
+            write!(f, "{}", get_game_state(Board::from_raw(*board), *step_counter, *rules))?;
+            for book_move in moves {
+                write!(f, " {}:{}", book_move.action, book_move.weight)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/*
+    The positions may be given in any orientation, they are canonicalized.
+    Positions of other rules than Nine Men's Morris have the rules as
+    seventh field like every position string.
+*/
+impl FromStr for OpeningBook {
+    type Err = BookError;
+
+    fn from_str(book: &str) -> Result<Self, Self::Err> {
+        let mut opening_book = OpeningBook::new();
+        for line in book.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let position_fields = if fields.get(6).is_some_and(|field| field.parse::<RuleSet>().is_ok()) { 7 } else { 6 };
+            let position = fields[..fields.len().min(position_fields)].join(" ");
+            let game_state: GameState = position.parse().map_err(BookError::InvalidPosition)?;
+            if game_state.get_phase() != Phase::Set {
+                return Err(BookError::NotSetPhase(position));
+            }
+
+            let player = Token::parse_to_u8(game_state.get_player_turn());
+            for book_move in fields.iter().skip(position_fields) {
+                let (action, weight) = book_move.split_once(':')
+                    .and_then(|(action, weight)| Some((action.parse::<Action>().ok()?, weight.parse::<u32>().ok()?)))
+                    .ok_or_else(|| BookError::InvalidMove(book_move.to_string()))?;
+                if !list_actions(game_state.get_board(), player, Phase::Set, None, game_state.get_rules()).any(|legal| legal == action) {
+                    return Err(BookError::IllegalMove(book_move.to_string()));
+                }
+                opening_book.add(&game_state, action, weight);
+            }
+        }
+        Ok(opening_book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::{
+        agent::{context::{SearchContext, SearchLimits}, search, AiPhase},
+        logic::{
+            action::Action,
+            game_record::GameRecord,
+            game_state::{GameState, Phase, Token},
+            position::PositionError,
+            r#move::apply_action,
+            rules::{RuleSet, Variant},
+            symmetry::{canonicalize, Symmetry}
+        }
+    };
+    use super::{BookError, BookMove, OpeningBook};
+
+    fn action(notation: &str) -> Action {
+        notation.parse().unwrap()
+    }
+
+    #[test]
+    fn test_add() {
+        let mut book = OpeningBook::new();
+        let start = GameState::default();
+        /* all four corners of the outer ring are the same move on the empty board */
+        book.add(&start, action("a7"), 2);
+        book.add(&start, action("g1"), 1);
+        book.add(&start, action("d6"), 1);
+        assert_eq!(book.get_position_count(), 1);
+
+        let moves = book.get_moves(start.get_board(), 0, RuleSet::default());
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].weight, 3);
+        assert_eq!(moves[1], BookMove { action: moves[1].action, weight: 1 });
+
+        /* moves are found in every orientation of the position, up to the symmetry of the position itself */
+        let mut game_state = start;
+        game_state.apply_action(&action("a7"));
+        book.add(&game_state, action("d7"), 1);
+        for symmetry in Symmetry::all() {
+            let board = symmetry.transform_board(game_state.get_board());
+            let expected = apply_action(board, &symmetry.transform_action(action("d7")), 0b10);
+            let moves = book.get_moves(board, 1, RuleSet::default());
+            assert_eq!(moves.len(), 1);
+            assert_eq!(canonicalize(apply_action(board, &moves[0].action, 0b10), RuleSet::default()).0, canonicalize(expected, RuleSet::default()).0);
+        }
+
+        /* positions of the move phase are not stored */
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE w 0 0 24 3".parse().unwrap();
+        book.add(&game_state, action("d2-d3"), 1);
+        assert_eq!(book.get_position_count(), 2);
+    }
+
+    #[test]
+    fn test_choose() {
+        let mut book = OpeningBook::new();
+        let start = GameState::default();
+        book.add(&start, action("a7"), 3);
+        book.add(&start, action("d6"), 1);
+        let board = start.get_board();
+        let best = book.choose(board, 0, RuleSet::default(), None).unwrap();
+
+        assert!(book.get_moves(board, 0, RuleSet::default())[0].action == best);
+        assert_eq!(book.choose(board, 0, RuleSet::default(), Some(0)), Some(best));
+        assert_eq!(book.choose(board, 0, RuleSet::default(), Some(2)), Some(best));
+        assert_ne!(book.choose(board, 0, RuleSet::default(), Some(3)), Some(best));
+        assert_eq!(book.choose(board, 0, RuleSet::default(), Some(7)), book.choose(board, 0, RuleSet::default(), Some(3)));
+        assert_eq!(book.choose(board, 2, RuleSet::default(), None), None);
+        /* the same board with other rules is another position */
+        assert_eq!(book.choose(board, 0, RuleSet::new(Variant::TwelveMensMorris), None), None);
+    }
+
+    #[test]
+    fn test_parse_book() {
+        let book: OpeningBook = "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 g7:3 d6:1\n\nWEEEEEEEEEEEEEEEEEEEEEEE b 8 9 1 0 d2:2\n".parse().unwrap();
+        assert_eq!(book.get_position_count(), 2);
+        assert_eq!(book.to_string().parse::<OpeningBook>(), Ok(book.clone()));
+        assert_eq!(book.to_string().lines().next().map(|line| line.split(' ').count()), Some(8));
+
+        assert_eq!(
+            "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 g7".parse::<OpeningBook>(),
+            Err(BookError::InvalidMove("g7".to_string()))
+        );
+        assert_eq!(
+            "WEEEEEEEEEEEEEEEEEEEEEEE b 8 9 1 0 d7:1".parse::<OpeningBook>(),
+            Err(BookError::IllegalMove("d7:1".to_string()))
+        );
+        assert_eq!(
+            "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0".parse::<OpeningBook>(),
+            Err(BookError::InvalidPosition(PositionError::MissingField("reversible plies")))
+        );
+        assert!(matches!("BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3 d7:1".parse::<OpeningBook>(), Err(BookError::NotSetPhase(_))));
+
+        let book: OpeningBook = "EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0 twelve d6:1".parse().unwrap();
+        assert_eq!(book.to_string(), "EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0 twelve d6:1\n");
+        assert_eq!(book.get_moves(GameState::default().get_board(), 0, RuleSet::new(Variant::TwelveMensMorris)).len(), 1);
+    }
+
+    #[test]
+    fn test_add_game_and_searches() {
+        let mut record = GameRecord::new(GameState::default());
+        for notation in ["d7", "d6", "a7"] {
+            record.apply_action(action(notation));
+        }
+        let mut book = OpeningBook::new();
+        book.add_game(&record);
+        assert_eq!(book.get_position_count(), 3);
+        assert_eq!(book.choose(GameState::default().get_board(), 0, RuleSet::default(), None), Some(action("d7")));
+
+        /* the search plays the book move without searching */
+        let context = SearchContext::new(SearchLimits::depth(4)).with_book(Arc::new(book.clone()), false);
+        let result = search(GameState::default().get_board(), Token::White, AiPhase::new(Phase::Set, 0), &context, |_| {}).unwrap();
+        assert_eq!((result.action, result.depth, result.nodes), (action("d7"), 0, 0));
+
+        let mut searched = 0;
+        let mut book = OpeningBook::new();
+        book.add_searches(RuleSet::default(), 2, SearchLimits::depth(1), |_, _| searched += 1);
+        assert_eq!(searched, 5);
+        assert_eq!(book.get_position_count(), 5);
+    }
+}
@@ -0,0 +1,316 @@
+use std::{sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, OnceLock}, thread};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use super::{
+    book::OpeningBook,
+    evaluation::EvaluationWeights,
+    move_ordering::MoveOrdering,
+    tablebase::Tablebase,
+    timer::time,
+    transposition_table::{TranspositionTable, DEFAULT_HASH_SIZE}
+};
+
+/*
+    Limits of one search. Limits that are None don't restrict the search,
+    max_time is given in seconds and max_depth in plies. An infinite search
+    ignores all limits and only ends when it is stopped from the outside.
+    The default limits are those of the normal difficulty, 4 plies and 3
+    seconds, so a default search always ends.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchLimits {
+    pub max_depth: Option<usize>,
+    pub max_time: Option<f64>,
+    pub max_nodes: Option<u64>,
+    pub infinite: bool
+}
+
+const UNLIMITED: SearchLimits = SearchLimits { max_depth: None, max_time: None, max_nodes: None, infinite: false };
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits { max_depth: Some(4), max_time: Some(3.0), ..UNLIMITED }
+    }
+}
+
+impl SearchLimits {
+    pub fn depth(max_depth: usize) -> Self {
+        SearchLimits { max_depth: Some(max_depth), ..UNLIMITED }
+    }
+
+    pub fn time(max_time: f64) -> Self {
+        SearchLimits { max_time: Some(max_time), ..UNLIMITED }
+    }
+
+    pub fn nodes(max_nodes: u64) -> Self {
+        SearchLimits { max_nodes: Some(max_nodes), ..UNLIMITED }
+    }
+
+    pub fn infinite() -> Self {
+        SearchLimits { infinite: true, ..UNLIMITED }
+    }
+
+    pub fn get_max_depth(&self) -> usize {
+        match self.max_depth {
+            Some(max_depth) if !self.infinite => max_depth,
+            _ => usize::MAX
+        }
+    }
+}
+
+/*
+    Shared state of one search. It is passed by reference through minimax,
+    so the search can be stopped from another thread and the visited nodes
+    of all threads are counted together. The transposition table can be
+    shared with later searches, e.g. for the following moves of a game.
+*/
+pub struct SearchContext {
+    start: f64,
+    limits: SearchLimits,
+    table: Arc<TranspositionTable>,
+    ordering: MoveOrdering,
+    weights: EvaluationWeights,
+    tablebase: Option<Arc<Tablebase>>,
+    book: Option<Arc<OpeningBook>>,
+    book_random: bool,
+    threads: usize,
+    thread_pool: OnceLock<Option<Arc<ThreadPool>>>,
+    stopped: AtomicBool,
+    nodes: AtomicU64
+}
+
+impl SearchContext {
+    pub fn new(limits: SearchLimits) -> Self {
+        SearchContext::with_table(limits, Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)))
+    }
+
+    pub fn with_table(limits: SearchLimits, table: Arc<TranspositionTable>) -> Self {
+        SearchContext {
+            start: time(),
+            limits,
+            table,
+            ordering: MoveOrdering::new(true),
+            weights: EvaluationWeights::default(),
+            tablebase: None,
+            book: None,
+            book_random: false,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            thread_pool: OnceLock::new(),
+            stopped: AtomicBool::new(false),
+            nodes: AtomicU64::new(0)
+        }
+    }
+
+    /*
+        The context of a helper thread of lazy SMP. It shares the table and
+        evaluates and probes the tablebase like this context, so the
+        entries it stores are the same the main search would store, but
+        it searches until it is stopped and has its own move ordering.
+    */
+    pub fn helper(&self) -> SearchContext {
+        let mut helper = SearchContext::with_table(SearchLimits::infinite(), self.get_shared_table())
+            .with_weights(self.weights)
+            .with_threads(1);
+        helper.ordering = MoveOrdering::new(self.ordering.is_enabled());
+        helper.tablebase = self.tablebase.clone();
+        helper
+    }
+
+    /* orders the moves by their static evaluation only, e.g. for comparisons */
+    pub fn without_ordering_heuristics(mut self) -> Self {
+        self.ordering = MoveOrdering::new(false);
+        self
+    }
+
+    /* the number of threads of lazy SMP, by default one per core */
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self.thread_pool = OnceLock::new();
+        self
+    }
+
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    /*
+        The threads of lazy SMP run on their own pool, so they don't wait
+        for other work. Building it takes a while, so searches one after
+        another can pass it on like the table. A pool with another number
+        of threads than the context is not used.
+    */
+    pub fn with_thread_pool(mut self, thread_pool: Option<Arc<ThreadPool>>) -> Self {
+        if let Some(thread_pool) = thread_pool.filter(|thread_pool| thread_pool.current_num_threads() == self.threads) {
+            self.thread_pool = OnceLock::from(Some(thread_pool));
+        }
+        self
+    }
+
+    /* the pool is built on the first call, None with a single thread */
+    pub fn get_thread_pool(&self) -> Option<Arc<ThreadPool>> {
+        self.thread_pool.get_or_init(|| {
+            (self.threads > 1)
+                .then(|| ThreadPoolBuilder::new().num_threads(self.threads).build().ok())
+                .flatten()
+                .map(Arc::new)
+        }).clone()
+    }
+
+    pub fn with_weights(mut self, weights: EvaluationWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn get_weights(&self) -> &EvaluationWeights {
+        &self.weights
+    }
+
+    /* endgames with a table are looked up instead of searched */
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    pub fn get_tablebase(&self) -> Option<&Tablebase> {
+        self.tablebase.as_deref()
+    }
+
+    /*
+        Positions of the set phase in the book are played from it without
+        a search. With random the book move is chosen by its weight,
+        otherwise the one with the highest weight is played.
+    */
+    pub fn with_book(mut self, book: Arc<OpeningBook>, random: bool) -> Self {
+        self.book = Some(book);
+        self.book_random = random;
+        self
+    }
+
+    pub fn get_book(&self) -> Option<&OpeningBook> {
+        self.book.as_deref()
+    }
+
+    pub fn is_book_random(&self) -> bool {
+        self.book_random
+    }
+
+    pub fn get_limits(&self) -> SearchLimits {
+        self.limits
+    }
+
+    pub fn get_table(&self) -> &TranspositionTable {
+        &self.table
+    }
+
+    pub fn get_shared_table(&self) -> Arc<TranspositionTable> {
+        Arc::clone(&self.table)
+    }
+
+    pub fn get_ordering(&self) -> &MoveOrdering {
+        &self.ordering
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        if self.stopped.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self.limits.infinite {
+            return false;
+        }
+
+        let time_exceeded = self.limits.max_time.is_some_and(|max_time| self.get_elapsed() > max_time);
+        let nodes_exceeded = self.limits.max_nodes.is_some_and(|max_nodes| self.get_nodes() >= max_nodes);
+        if time_exceeded || nodes_exceeded {
+            self.stop();
+            return true;
+        }
+        false
+    }
+
+    pub fn get_elapsed(&self) -> f64 {
+        time() - self.start
+    }
+
+    pub fn add_node(&self) {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_nodes(&self, nodes: u64) {
+        self.nodes.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::{SearchContext, SearchLimits};
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(SearchLimits::default().get_max_depth(), 4);
+        assert_eq!(SearchLimits::default().max_time, Some(3.0));
+        assert_eq!(SearchLimits::time(1.5).get_max_depth(), usize::MAX);
+        assert_eq!(SearchLimits::depth(4).get_max_depth(), 4);
+        assert_eq!(SearchLimits { infinite: true, ..SearchLimits::depth(4) }.get_max_depth(), usize::MAX);
+        assert_eq!(SearchLimits::time(1.5).max_time, Some(1.5));
+        assert_eq!(SearchLimits::nodes(100).max_nodes, Some(100));
+    }
+
+    #[test]
+    fn test_threads() {
+        assert!(SearchContext::new(SearchLimits::infinite()).get_threads() >= 1);
+        assert_eq!(SearchContext::new(SearchLimits::infinite()).with_threads(4).get_threads(), 4);
+        assert_eq!(SearchContext::new(SearchLimits::infinite()).with_threads(0).get_threads(), 1);
+    }
+
+    #[test]
+    fn test_thread_pool() {
+        assert!(SearchContext::new(SearchLimits::infinite()).with_threads(1).get_thread_pool().is_none());
+
+        let context = SearchContext::new(SearchLimits::infinite()).with_threads(2);
+        let thread_pool = context.get_thread_pool().unwrap();
+        assert_eq!(thread_pool.current_num_threads(), 2);
+        assert!(Arc::ptr_eq(&thread_pool, &context.get_thread_pool().unwrap()));
+
+        let reused = SearchContext::new(SearchLimits::infinite()).with_threads(2).with_thread_pool(Some(thread_pool.clone()));
+        assert!(Arc::ptr_eq(&thread_pool, &reused.get_thread_pool().unwrap()));
+        let rebuilt = SearchContext::new(SearchLimits::infinite()).with_threads(3).with_thread_pool(Some(thread_pool.clone()));
+        assert_eq!(rebuilt.get_thread_pool().unwrap().current_num_threads(), 3);
+    }
+
+    #[test]
+    fn test_stop() {
+        let context = SearchContext::new(SearchLimits::infinite());
+        assert!(!context.is_stopped());
+        context.stop();
+        assert!(context.is_stopped());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let context = SearchContext::new(SearchLimits::time(0.0));
+        while context.get_elapsed() <= 0.0 {}
+        assert!(context.is_stopped());
+
+        let context = SearchContext::new(SearchLimits { infinite: true, ..SearchLimits::time(0.0) });
+        while context.get_elapsed() <= 0.0 {}
+        assert!(!context.is_stopped());
+    }
+
+    #[test]
+    fn test_nodes() {
+        let context = SearchContext::new(SearchLimits::nodes(2));
+        context.add_node();
+        assert!(!context.is_stopped());
+        context.add_node();
+        assert_eq!(context.get_nodes(), 2);
+        assert!(context.is_stopped());
+    }
+}
@@ -0,0 +1,362 @@
+use std::{error::Error, fmt, str::FromStr};
+use crate::logic::{
+    game_state::Phase,
+    move_token_count::SearchBoard,
+    rules::Geometry
+};
+use super::AiPhase;
+
+/*
+    Weights of the terms of the evaluation in one phase of the game. Every
+    term is counted for white minus for black:
+        tokens          tokens on the board
+        tokens_in_hand  tokens that are still to be placed
+        mobility        moves of the tokens to an adjacent empty position
+        closed_mills    mills of three own tokens
+        open_mills      two own tokens of a mill next to an empty position
+        double_mills    tokens of a closed mill that can move into the empty
+                        position of an open mill, so every move closes a mill
+        blocked_tokens  tokens without an adjacent empty position, which
+                        usually is bad, so the weight is negative
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhaseWeights {
+    pub tokens: isize,
+    pub tokens_in_hand: isize,
+    pub mobility: isize,
+    pub closed_mills: isize,
+    pub open_mills: isize,
+    pub double_mills: isize,
+    pub blocked_tokens: isize
+}
+
+impl PhaseWeights {
+    pub const TERMS: usize = 7;
+
+    pub fn to_array(self) -> [isize; PhaseWeights::TERMS] {
+        [
+            self.tokens,
+            self.tokens_in_hand,
+            self.mobility,
+            self.closed_mills,
+            self.open_mills,
+            self.double_mills,
+            self.blocked_tokens
+        ]
+    }
+
+    pub fn from_array(weights: [isize; PhaseWeights::TERMS]) -> Self {
+        PhaseWeights {
+            tokens: weights[0],
+            tokens_in_hand: weights[1],
+            mobility: weights[2],
+            closed_mills: weights[3],
+            open_mills: weights[4],
+            double_mills: weights[5],
+            blocked_tokens: weights[6]
+        }
+    }
+}
+
+/*
+    The weights of the set phase, of the move phase and of the end of the
+    move phase, when at least one player has only three tokens left and
+    can fly. Without flying the weights of the move phase are used until
+    the end.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvaluationWeights {
+    pub set: PhaseWeights,
+    pub moving: PhaseWeights,
+    pub flying: PhaseWeights
+}
+
+impl Default for EvaluationWeights {
+    fn default() -> Self {
+        EvaluationWeights {
+            set: PhaseWeights {
+                tokens: 20,
+                tokens_in_hand: 20,
+                mobility: 1,
+                closed_mills: 4,
+                open_mills: 6,
+                double_mills: 8,
+                blocked_tokens: -1
+            },
+            moving: PhaseWeights {
+                tokens: 20,
+                tokens_in_hand: 0,
+                mobility: 2,
+                closed_mills: 6,
+                open_mills: 4,
+                double_mills: 12,
+                blocked_tokens: -2
+            },
+            flying: PhaseWeights {
+                tokens: 20,
+                tokens_in_hand: 0,
+                mobility: 0,
+                closed_mills: 4,
+                open_mills: 10,
+                double_mills: 0,
+                blocked_tokens: 0
+            }
+        }
+    }
+}
+
+impl EvaluationWeights {
+    pub const PHASES: [&'static str; 3] = ["set", "moving", "flying"];
+
+    pub fn to_array(self) -> [PhaseWeights; 3] {
+        [self.set, self.moving, self.flying]
+    }
+
+    pub fn from_array(weights: [PhaseWeights; 3]) -> Self {
+        EvaluationWeights {
+            set: weights[0],
+            moving: weights[1],
+            flying: weights[2]
+        }
+    }
+
+    pub fn get_phase_weights(&self, board: SearchBoard, phase: AiPhase) -> &PhaseWeights {
+        match get_phase_index(board, phase) {
+            0 => &self.set,
+            1 => &self.moving,
+            _ => &self.flying
+        }
+    }
+}
+
+/* the index of the weights used for the board in EvaluationWeights::to_array */
+pub fn get_phase_index(board: SearchBoard, phase: AiPhase) -> usize {
+    if phase.phase == Phase::Set {
+        0
+    } else if phase.rules.can_fly(board.get_white_token_count() as u8) || phase.rules.can_fly(board.get_black_token_count() as u8) {
+        2
+    } else {
+        1
+    }
+}
+
+/*
+    The weights are written as one line per phase with its name and the
+    weights in the order of PhaseWeights::to_array:
+        set 20 20 1 4 6 8 -1
+        moving 20 0 2 6 4 12 -2
+        flying 20 0 0 4 10 0 0
+*/
+impl fmt::Display for EvaluationWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, weights) in EvaluationWeights::PHASES.iter().zip(self.to_array()) {
+            write!(f, "{}", name)?;
+            for weight in weights.to_array() {
+                write!(f, " {}", weight)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseWeightsError {
+    InvalidLine(String),
+    MissingPhase(&'static str)
+}
+
+impl fmt::Display for ParseWeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseWeightsError::InvalidLine(line) => write!(f, "invalid line '{}'", line),
+            ParseWeightsError::MissingPhase(phase) => write!(f, "missing weights of the {} phase", phase)
+        }
+    }
+}
+
+impl Error for ParseWeightsError {}
+
+impl FromStr for EvaluationWeights {
+    type Err = ParseWeightsError;
+
+    fn from_str(weights: &str) -> Result<Self, Self::Err> {
+        let mut phases = [None; 3];
+        for line in weights.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid_line = || ParseWeightsError::InvalidLine(line.to_string());
+            let mut fields = line.split_whitespace();
+            let index = fields.next()
+                .and_then(|name| EvaluationWeights::PHASES.iter().position(|phase| *phase == name))
+                .ok_or_else(invalid_line)?;
+            let values = fields.map(str::parse).collect::<Result<Vec<isize>, _>>().map_err(|_| invalid_line())?;
+            phases[index] = Some(PhaseWeights::from_array(values.try_into().map_err(|_| invalid_line())?));
+        }
+
+        let mut weights = [PhaseWeights::from_array([0; PhaseWeights::TERMS]); 3];
+        for (index, phase) in phases.into_iter().enumerate() {
+            weights[index] = phase.ok_or(ParseWeightsError::MissingPhase(EvaluationWeights::PHASES[index]))?;
+        }
+        Ok(EvaluationWeights::from_array(weights))
+    }
+}
+
+/*
+    The evaluation works on the raw board with one bit per position, the
+    lower bit of its two bits. It is set for white tokens, the upper bit
+    for every token.
+*/
+const POSITION_BITS: u64 = 0x555555555555;
+
+fn position_bit(position: usize) -> u64 {
+    1 << ((23 - position) * 2)
+}
+
+fn get_neighbor_mask(geometry: &Geometry, position: usize) -> u64 {
+    geometry.get_neighbors(position).iter().fold(0, |mask, neighbor| mask | position_bit(*neighbor))
+}
+
+/*
+    Returns the terms of the evaluation of the board, white minus black,
+    in the order of PhaseWeights::to_array.
+*/
+pub fn get_terms(board: SearchBoard, phase: AiPhase) -> [isize; PhaseWeights::TERMS] {
+    let mut terms = [0; PhaseWeights::TERMS];
+    let geometry = phase.rules.get_geometry();
+    let raw = board.board().to_raw();
+    let occupied = (raw >> 1) & POSITION_BITS;
+    let white = raw & POSITION_BITS;
+    let black = occupied & !white;
+    let empty = !occupied & POSITION_BITS;
+
+    terms[0] = white.count_ones() as isize - black.count_ones() as isize;
+    if phase.phase == Phase::Set {
+        terms[1] = phase.get_tokens_in_hand(0b11) as isize - phase.get_tokens_in_hand(0b10) as isize;
+    }
+    terms[2] = board.get_white_move_count() as isize - board.get_black_move_count() as isize;
+
+    for (sign, tokens) in [(1, white), (-1, black)] {
+        let mut closed_mill_tokens = 0;
+        let mut open_mill_positions = 0;
+        for mask in geometry.get_mill_masks() {
+            let mask = mask & POSITION_BITS;
+            let own = (tokens & mask).count_ones();
+            if own == 3 {
+                terms[3] += sign;
+                closed_mill_tokens |= mask;
+            } else if own == 2 && empty & mask != 0 {
+                terms[4] += sign;
+                open_mill_positions |= empty & mask;
+            }
+        }
+
+        let mut remaining = tokens;
+        while remaining != 0 {
+            let shift = remaining.trailing_zeros();
+            let empty_neighbors = get_neighbor_mask(geometry, 23 - shift as usize / 2) & empty;
+            if empty_neighbors == 0 {
+                terms[6] += sign;
+            } else if closed_mill_tokens & (1 << shift) != 0 {
+                terms[5] += sign * (empty_neighbors & open_mill_positions).count_ones() as isize;
+            }
+            remaining &= remaining - 1;
+        }
+    }
+
+    terms
+}
+
+/*
+    The score of the board from the view of white. Positions that are won
+    get isize::MAX minus the step counter, lost positions isize::MIN plus
+    the step counter, so faster wins are better.
+*/
+pub fn evaluate_action(board: SearchBoard, phase: AiPhase, weights: &EvaluationWeights) -> isize {
+    if phase.phase == Phase::Move {
+        let black_token_count = board.get_black_token_count();
+        let white_token_count = board.get_white_token_count();
+        if (board.get_black_move_count() == 0 && !phase.rules.can_fly(black_token_count as u8)) || black_token_count == 2 {
+            return isize::MAX - phase.step_counter as isize
+        } else if (board.get_white_move_count() == 0 && !phase.rules.can_fly(white_token_count as u8)) || white_token_count == 2 {
+            return isize::MIN + phase.step_counter as isize
+        }
+    }
+
+    let weights = weights.get_phase_weights(board, phase).to_array();
+    get_terms(board, phase).iter()
+        .zip(weights)
+        .map(|(term, weight)| term * weight)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agent::AiPhase,
+        logic::{game_state::GameState, move_token_count::SearchBoard}
+    };
+    use super::{evaluate_action, get_terms, EvaluationWeights, ParseWeightsError, PhaseWeights};
+
+    fn terms(position: &str) -> [isize; PhaseWeights::TERMS] {
+        let game_state: GameState = position.parse().unwrap();
+        get_terms(SearchBoard::new(game_state.get_board(), game_state.get_rules()), AiPhase::from_game_state(&game_state))
+    }
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(terms("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0"), [0; 7]);
+        /* white has placed one token more, black has one more in hand */
+        assert_eq!(terms("WEEEEEEEEEEEEEEEEEEEEEEE b 8 9 1 0"), [1, -1, 3, 0, 0, 0, 0]);
+        /* both have an open mill, black can move to one more position */
+        assert_eq!(terms("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0"), [0, 0, -1, 0, 0, 0, 0]);
+        /*
+            white can move 0 to 8 and back, which closes 15 8 9 and 7 0 1,
+            black has 19 and 21 blocked
+        */
+        assert_eq!(terms("WWEEEEEWEWEEEEEWEEBBBBBE w 0 0 18 0")[3..], [0, -1, 1, -2]);
+    }
+
+    #[test]
+    fn test_phase_weights() {
+        let weights = EvaluationWeights::default();
+        let phase_weights = |position: &str| {
+            let game_state: GameState = position.parse().unwrap();
+            *weights.get_phase_weights(SearchBoard::new(game_state.get_board(), game_state.get_rules()), AiPhase::from_game_state(&game_state))
+        };
+        assert_eq!(phase_weights("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0"), weights.set);
+        assert_eq!(phase_weights("WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 18 0"), weights.moving);
+        assert_eq!(phase_weights("WWEWBEEEEEEEEEEEEEEEEBBB w 0 0 30 0"), weights.flying);
+        assert_eq!(PhaseWeights::from_array(weights.moving.to_array()), weights.moving);
+        assert_eq!(EvaluationWeights::from_array(weights.to_array()), weights);
+    }
+
+    #[test]
+    fn test_parse_weights() {
+        let weights = EvaluationWeights::default();
+        assert_eq!(weights.to_string().lines().next(), Some("set 20 20 1 4 6 8 -1"));
+        assert_eq!(weights.to_string().parse(), Ok(weights));
+        assert_eq!(
+            "flying 1 2 3 4 5 6 7\nset 20 20 1 4 6 8 -1\n\nmoving 20 0 2 6 4 12 -2".parse::<EvaluationWeights>().map(|weights| weights.flying.double_mills),
+            Ok(6)
+        );
+        assert_eq!("set 1 2 3".parse::<EvaluationWeights>(), Err(ParseWeightsError::InvalidLine("set 1 2 3".to_string())));
+        assert_eq!("mobility 1".parse::<EvaluationWeights>(), Err(ParseWeightsError::InvalidLine("mobility 1".to_string())));
+        assert_eq!("set 20 20 1 4 6 8 -1".parse::<EvaluationWeights>(), Err(ParseWeightsError::MissingPhase("moving")));
+    }
+
+    #[test]
+    fn test_evaluate_action() {
+        let weights = EvaluationWeights::default();
+        let evaluate = |position: &str| {
+            let game_state: GameState = position.parse().unwrap();
+            evaluate_action(SearchBoard::new(game_state.get_board(), game_state.get_rules()), AiPhase::from_game_state(&game_state), &weights)
+        };
+        assert_eq!(evaluate("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0"), 0);
+        assert_eq!(evaluate("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0"), -weights.set.mobility);
+        /* one token more on the board, one less in hand, a closed and an open mill */
+        assert_eq!(
+            evaluate("EWWWEEEEBBEEEEEEEEEEEEEE b 6 7 5 0"), 
+            -weights.set.mobility + weights.set.closed_mills - weights.set.open_mills
+        );
+        assert_eq!(evaluate("BWBWBWBWEEEEEEEEEEEEEEEE w 0 0 18 0"), isize::MIN + 18);
+    }
+}
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+use rayon::ThreadPool;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{sync::mpsc::{self, Receiver, TryRecvError}, thread};
+use crate::logic::{
+    forward_boards::forward_step_boards,
+    game_state::{GameState, Token},
+    move_token_count::SearchBoard
+};
+use super::{
+    context::{SearchContext, SearchLimits},
+    is_search_complete,
+    search_iteration,
+    timer::time,
+    transposition_table::TranspositionTable,
+    AiPhase,
+    SearchResult
+};
+#[cfg(not(target_arch = "wasm32"))]
+use super::search;
+
+/*
+    Iterative deepening that is executed in small steps, for platforms
+    without threads. Every step searches until the next iteration is
+    finished or the time slice is used up. An iteration that was
+    interrupted is started again in the next step, where the transposition
+    table already contains the subtrees that were searched before.
+*/
+pub struct IterativeSearch {
+    board: SearchBoard,
+    player: Token,
+    ai_phase: AiPhase,
+    limits: SearchLimits,
+    table: Arc<TranspositionTable>,
+    start: f64,
+    depth: usize,
+    nodes: u64,
+    stopped: bool,
+    has_legal_action: bool,
+    result: Option<SearchResult>
+}
+
+impl IterativeSearch {
+    pub fn new(game_state: GameState, limits: SearchLimits, table: Arc<TranspositionTable>) -> Self {
+        let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+        let player = game_state.get_player_turn();
+        let ai_phase = AiPhase::from_game_state(&game_state);
+        IterativeSearch {
+            board,
+            player,
+            ai_phase,
+            limits,
+            table,
+            start: time(),
+            depth: 1,
+            nodes: 0,
+            stopped: false,
+            has_legal_action: forward_step_boards(board, Token::parse_to_u8(player), ai_phase).next().is_some(),
+            result: None
+        }
+    }
+
+    pub fn step(&mut self, time_slice: f64) -> bool {
+        if self.is_finished() {
+            return true;
+        }
+
+        let remaining_time = self.limits.max_time
+            .filter(|_| !self.limits.infinite)
+            .map_or(f64::INFINITY, |max_time| max_time - self.get_elapsed());
+        let slice_limits = SearchLimits {
+            max_nodes: self.limits.max_nodes
+                .filter(|_| !self.limits.infinite)
+                .map(|max_nodes| max_nodes.saturating_sub(self.nodes)),
+            ..SearchLimits::time(time_slice.min(remaining_time))
+        };
+        let context = SearchContext::with_table(slice_limits, Arc::clone(&self.table)).with_threads(1);
+
+        let result = search_iteration(self.board, self.player, self.ai_phase, self.depth, self.result.as_ref(), &context);
+        self.nodes += context.get_nodes();
+        if let Some(mut result) = result {
+            result.nodes = self.nodes;
+            result.time = self.get_elapsed();
+            self.stopped |= is_search_complete(&result, self.limits);
+            self.result = Some(result);
+            self.depth += 1;
+        }
+        self.is_finished()
+    }
+
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /*
+        The search finishes with a limit or stop only after the first
+        iteration, so there is always an action if the player can move.
+    */
+    pub fn is_finished(&self) -> bool {
+        if !self.has_legal_action {
+            return true;
+        }
+        if self.result.is_none() {
+            return false;
+        }
+        if self.stopped {
+            return true;
+        }
+        if self.limits.infinite {
+            return false;
+        }
+
+        self.depth > self.limits.get_max_depth()
+            || self.limits.max_time.is_some_and(|max_time| self.get_elapsed() >= max_time)
+            || self.limits.max_nodes.is_some_and(|max_nodes| self.nodes >= max_nodes)
+    }
+
+    pub fn get_result(&self) -> Option<&SearchResult> {
+        self.result.as_ref()
+    }
+
+    pub fn get_elapsed(&self) -> f64 {
+        time() - self.start
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum Message {
+    Iteration(SearchResult),
+    Finished
+}
+
+/*
+    A search running in the background. On the desktop it runs on its own
+    thread, in the browser it is executed in time slices whenever the
+    handle is polled. Dropping the handle cancels the search.
+*/
+pub struct SearchHandle {
+    #[cfg(not(target_arch = "wasm32"))]
+    context: Arc<SearchContext>,
+    #[cfg(not(target_arch = "wasm32"))]
+    receiver: Receiver<Message>,
+    #[cfg(target_arch = "wasm32")]
+    search: IterativeSearch,
+    result: Option<SearchResult>,
+    finished: bool
+}
+
+/* time that a poll may use for the search in the browser */
+#[cfg(target_arch = "wasm32")]
+const TIME_SLICE: f64 = 0.05;
+
+impl SearchHandle {
+    /* the thread pool of an earlier search is used again, see SearchContext::with_thread_pool */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start(game_state: GameState, limits: SearchLimits, table: Arc<TranspositionTable>, thread_pool: Option<Arc<ThreadPool>>) -> Self {
+        let context = Arc::new(SearchContext::with_table(limits, table).with_thread_pool(thread_pool));
+        let search_context = Arc::clone(&context);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            search(
+                game_state.get_board(),
+                game_state.get_player_turn(),
+                AiPhase::from_game_state(&game_state),
+                &search_context,
+                |result| {
+                    let _ = sender.send(Message::Iteration(result.clone()));
+                }
+            );
+            let _ = sender.send(Message::Finished);
+        });
+
+        SearchHandle { context, receiver, result: None, finished: false }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn start(game_state: GameState, limits: SearchLimits, table: Arc<TranspositionTable>, _thread_pool: Option<Arc<ThreadPool>>) -> Self {
+        SearchHandle {
+            search: IterativeSearch::new(game_state, limits, table),
+            result: None,
+            finished: false
+        }
+    }
+
+    /*
+        Collects the results of the search and returns whether it is
+        finished. The result of the last finished iteration is available
+        with get_result in the meantime.
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll(&mut self) -> bool {
+        while !self.finished {
+            match self.receiver.try_recv() {
+                Ok(Message::Iteration(result)) => self.result = Some(result),
+                Ok(Message::Finished) | Err(TryRecvError::Disconnected) => self.finished = true,
+                Err(TryRecvError::Empty) => break
+            }
+        }
+        self.finished
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn poll(&mut self) -> bool {
+        if !self.finished {
+            self.finished = self.search.step(TIME_SLICE);
+            self.result = self.search.get_result().cloned();
+        }
+        self.finished
+    }
+
+    /* finishes the search as soon as possible with the best action so far */
+    pub fn stop(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.context.stop();
+        #[cfg(target_arch = "wasm32")]
+        self.search.stop();
+    }
+
+    pub fn cancel(self) {}
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn get_result(&self) -> Option<&SearchResult> {
+        self.result.as_ref()
+    }
+
+    pub fn take_result(&mut self) -> Option<SearchResult> {
+        self.result.take()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_thread_pool(&self) -> Option<Arc<ThreadPool>> {
+        self.context.get_thread_pool()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn get_thread_pool(&self) -> Option<Arc<ThreadPool>> {
+        None
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::{
+        agent::{calculate_next_move, context::SearchLimits, transposition_table::TranspositionTable, AiPhase},
+        logic::game_state::GameState
+    };
+    use super::{IterativeSearch, SearchHandle};
+
+    fn table() -> Arc<TranspositionTable> {
+        Arc::new(TranspositionTable::new(1))
+    }
+
+    #[test]
+    fn test_iterative_search_steps() {
+        let mut search = IterativeSearch::new(GameState::default(), SearchLimits::depth(3), table());
+        assert!(!search.is_finished());
+        assert_eq!(search.get_result(), None);
+
+        let mut steps = 0;
+        while !search.step(f64::INFINITY) {
+            steps += 1;
+        }
+        assert_eq!(steps, 2);
+        assert_eq!(search.get_result().unwrap().depth, 3);
+    }
+
+    #[test]
+    fn test_iterative_search_resumes_interrupted_iteration() {
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3".parse().unwrap();
+        let mut search = IterativeSearch::new(game_state, SearchLimits::depth(5), table());
+        let mut steps = 0;
+        while !search.step(0.001) {
+            steps += 1;
+        }
+        let result = search.get_result().unwrap();
+        let expected = calculate_next_move(
+            game_state.get_board(),
+            game_state.get_player_turn(),
+            AiPhase::from_game_state(&game_state),
+            SearchLimits::depth(5)
+        );
+
+        assert!(steps >= 4);
+        assert_eq!(result.depth, 5);
+        assert_eq!(result.score, expected.score);
+    }
+
+    #[test]
+    fn test_iterative_search_stop() {
+        let mut search = IterativeSearch::new(GameState::default(), SearchLimits::infinite(), table());
+        search.stop();
+        assert!(!search.is_finished());
+        assert!(search.step(f64::INFINITY));
+        assert_eq!(search.get_result().unwrap().depth, 1);
+    }
+
+    #[test]
+    fn test_iterative_search_without_actions() {
+        let game_state = "BWBWBWBWEEEEEEEEEEEEEEEE w 0 0 18 0".parse().unwrap();
+        let mut search = IterativeSearch::new(game_state, SearchLimits::infinite(), table());
+        assert!(search.is_finished());
+        assert!(search.step(f64::INFINITY));
+        assert_eq!(search.get_result(), None);
+    }
+
+    #[test]
+    fn test_search_handle() {
+        let mut handle = SearchHandle::start(GameState::default(), SearchLimits::depth(3), table(), None);
+        while !handle.poll() {}
+
+        assert!(handle.is_finished());
+        assert_eq!(handle.get_result().unwrap().depth, 3);
+        assert!(handle.take_result().is_some());
+        assert_eq!(handle.get_result(), None);
+    }
+
+    #[test]
+    fn test_search_handle_stop() {
+        let mut handle = SearchHandle::start(GameState::default(), SearchLimits::infinite(), table(), None);
+        handle.stop();
+        while !handle.poll() {}
+        assert!(handle.get_result().is_some());
+
+        let handle = SearchHandle::start(GameState::default(), SearchLimits::infinite(), table(), None);
+        handle.cancel();
+    }
+}
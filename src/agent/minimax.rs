@@ -1,107 +1,316 @@
-use ggez::timer;
 use itertools::Itertools;
-use crate::logic::move_token_count::{
-    extract_black_move_count_from_board, 
-    extract_black_token_count_from_board, 
-    extract_white_move_count_from_board, extract_white_token_count_from_board
-};
+use crate::logic::move_token_count::SearchBoard;
 use crate::logic::forward_boards::forward_step_boards;
 use crate::logic::game_state::Phase;
 use crate::logic::position::negate_token;
-use super::AiPhase;
+use crate::logic::rules::RuleSet;
+use super::{
+    context::SearchContext,
+    evaluation::{evaluate_action, EvaluationWeights},
+    tablebase::TablebaseValue,
+    transposition_table::{get_key, Bound, TableEntry},
+    AiPhase
+};
 
-pub fn minimax(board: u64, depth: usize, mut alpha: isize, mut beta: isize, maximizing_player: u8, phase: AiPhase, time: f64) -> Option<isize> {
-    if timer::time() - time > 3.0 {
-        return None;
+/*
+    Principal variation search: the first child, which is expected to be
+    the best, is searched with the full window. All other children are
+    only tested with a null window whether they are better, and searched
+    again with the full window if they are. Returns None if the search was
+    stopped. The principal variation, i.e. the boards of the best line for
+    both players, is written into pv.
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn minimax(board: SearchBoard, depth: usize, mut alpha: isize, mut beta: isize, maximizing_player: u8, phase: AiPhase, context: &SearchContext, pv: &mut Vec<SearchBoard>) -> Option<isize> {
+    context.add_node();
+    pv.clear();
+
+    let black_token_count = board.get_black_token_count();
+    let white_token_count = board.get_white_token_count();
+    if phase.phase == Phase::Set {
+        if phase.is_out_of_tokens(board, 0b10) {
+            return Some(isize::MAX - phase.step_counter as isize)
+        } else if phase.is_out_of_tokens(board, 0b11) {
+            return Some(isize::MIN + phase.step_counter as isize)
+        }
     }
-    
-    let black_token_count = extract_black_token_count_from_board(board);
-    let white_token_count = extract_white_token_count_from_board(board);
     if phase.phase == Phase::Move {
-        if (extract_black_move_count_from_board(board) == 0 && black_token_count > 3) || black_token_count == 2 {
+        let black_blocked = board.get_black_move_count() == 0 && !phase.rules.can_fly(black_token_count as u8);
+        let white_blocked = board.get_white_move_count() == 0 && !phase.rules.can_fly(white_token_count as u8);
+        /* like GameState::get_draw_reason only the player to move draws when blocked */
+        let player_blocked = if maximizing_player == 0b11 { white_blocked } else { black_blocked };
+        if player_blocked && black_token_count > 2 && white_token_count > 2 && phase.rules.is_blocked_draw(phase.step_counter) {
+            return Some(0)
+        } else if black_blocked || black_token_count == 2 {
             return Some(isize::MAX - phase.step_counter as isize)
-        } else if (extract_white_move_count_from_board(board) == 0 && white_token_count > 3) || white_token_count == 2 {
+        } else if white_blocked || white_token_count == 2 {
             return Some(isize::MIN + phase.step_counter as isize)
         }
+
+        /*
+            The table doesn't know the reversible plies, so positions close
+            to the draw share their scores with the same positions further
+            away from it, which the search accepts.
+        */
+        if phase.rules.is_no_capture_draw(phase.reversible_plies) {
+            return Some(0)
+        }
+    }
+
+    /* the tablebase is generated with the standard rules */
+    if phase.phase == Phase::Move && phase.rules == RuleSet::default() {
+        if let Some(value) = context.get_tablebase().and_then(|tablebase| tablebase.probe(board.board(), maximizing_player)) {
+            return Some(get_tablebase_score(value, maximizing_player, phase));
+        }
     }
     
     if depth == 0 {
-        return Some(evaluate_action(board, phase));
+        return Some(evaluate_action(board, phase, context.get_weights()));
     }
 
-    let forward_step_boards = forward_step_boards(&board, maximizing_player, phase)
-        .sorted_by(|board1, board2| {
-            let board1_eval = evaluate_action(*board1, phase);
-            let board2_eval = evaluate_action(*board2, phase);
-            if maximizing_player == 0b11 {
-                board2_eval.cmp(&board1_eval)
-            } else {
-                board1_eval.cmp(&board2_eval)
-            }
-    });
+    if context.is_stopped() {
+        return None;
+    }
+
+    let key = get_key(board, maximizing_player, phase);
+    let table_entry = context.get_table().probe(key, phase.step_counter);
+    if let Some(entry) = table_entry.filter(|entry| entry.depth >= depth) {
+        match entry.bound {
+            Bound::Exact => return Some(entry.score),
+            Bound::Lower if entry.score >= beta => return Some(entry.score),
+            Bound::Upper if entry.score <= alpha => return Some(entry.score),
+            _ => ()
+        }
+    }
+    let (original_alpha, original_beta) = (alpha, beta);
+
+    let table_move = table_entry.and_then(|entry| entry.best_move);
+    let forward_boards = context.get_ordering().order(board, maximizing_player, phase, table_move, context.get_weights());
     
-    if maximizing_player == 0b11 {
+    let mut best_move = None;
+    let best_eval = if maximizing_player == 0b11 {
         let mut max_eval = isize::MIN + phase.step_counter as isize;
+        let mut child_pv = Vec::new();
 
-        for forward_board in forward_step_boards {
-            let eval = minimax(
+        for (child, (index, forward_board)) in forward_boards.into_iter().enumerate() {
+            let mut search_child = |alpha, beta| minimax(
                 forward_board, 
                 depth - 1, 
                 alpha, 
                 beta, 
                 negate_token(maximizing_player), 
-                phase.increased(), 
-                time
+                phase.after(board, forward_board), 
+                context,
+                &mut child_pv
             );
+            let eval = if child == 0 {
+                search_child(alpha, beta)?
+            } else {
+                let eval = search_child(alpha, alpha.saturating_add(1))?;
+                if eval > alpha && eval < beta {
+                    search_child(alpha, beta)?
+                } else {
+                    eval
+                }
+            };
            
-            max_eval = std::cmp::max(max_eval, eval?);
+            if eval > max_eval {
+                max_eval = eval;
+                best_move = Some(index);
+                update_pv(pv, forward_board, &child_pv);
+            }
             
-            alpha = std::cmp::max(alpha, eval.unwrap());
+            alpha = std::cmp::max(alpha, eval);
             if beta <= alpha {
+                context.get_ordering().update(board, forward_board, maximizing_player, phase, depth);
                 break;
             }
         }
-        Some(max_eval)
+        max_eval
     } else {
         let mut min_eval = isize::MAX - phase.step_counter as isize;
-        for forward_board in forward_step_boards {
-            let eval = minimax(
+        let mut child_pv = Vec::new();
+
+        for (child, (index, forward_board)) in forward_boards.into_iter().enumerate() {
+            let mut search_child = |alpha, beta| minimax(
                 forward_board, 
                 depth - 1, 
                 alpha, 
                 beta, 
                 negate_token(maximizing_player), 
-                phase.increased(), 
-                time
+                phase.after(board, forward_board), 
+                context,
+                &mut child_pv
             );
+            let eval = if child == 0 {
+                search_child(alpha, beta)?
+            } else {
+                let eval = search_child(beta.saturating_sub(1), beta)?;
+                if eval > alpha && eval < beta {
+                    search_child(alpha, beta)?
+                } else {
+                    eval
+                }
+            };
             
-            min_eval = std::cmp::min(min_eval, eval?);
+            if eval < min_eval {
+                min_eval = eval;
+                best_move = Some(index);
+                update_pv(pv, forward_board, &child_pv);
+            }
             
-            beta = std::cmp::min(beta, eval.unwrap());
+            beta = std::cmp::min(beta, eval);
             if beta <= alpha {
+                context.get_ordering().update(board, forward_board, maximizing_player, phase, depth);
                 break;
             }
         }
-        Some(min_eval)
+        min_eval
+    };
+
+    /* scores are always from the view of white, so the bounds are as well */
+    let bound = if best_eval <= original_alpha {
+        Bound::Upper
+    } else if best_eval >= original_beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    context.get_table().store(key, phase.step_counter, TableEntry { score: best_eval, depth, bound, best_move });
+    Some(best_eval)
+}
+
+/*
+    All forward boards together with their index in the order of
+    forward_step_boards, sorted by the static evaluation, best first for the
+    player to move.
+*/
+pub fn sorted_forward_boards(board: SearchBoard, player: u8, phase: AiPhase, weights: &EvaluationWeights) -> Vec<(usize, SearchBoard)> {
+    forward_step_boards(board, player, phase)
+        .enumerate()
+        .map(|(index, forward_board)| (evaluate_action(forward_board, phase, weights), index, forward_board))
+        .sorted_by(|(board1_eval, _, _), (board2_eval, _, _)| {
+            if player == 0b11 {
+                board2_eval.cmp(board1_eval)
+            } else {
+                board1_eval.cmp(board2_eval)
+            }
+        })
+        .map(|(_, index, forward_board)| (index, forward_board))
+        .collect()
+}
+
+/*
+    Converts the value of the tablebase for the player to move into a score
+    from the view of white, with the step counter at the end of the game
+    like the scores of won and lost positions of the search.
+*/
+fn get_tablebase_score(value: TablebaseValue, player: u8, phase: AiPhase) -> isize {
+    let (player_wins, plies) = match value {
+        TablebaseValue::Win(plies) => (true, plies),
+        TablebaseValue::Loss(plies) => (false, plies),
+        TablebaseValue::Draw => return 0
+    };
+    let end_step = phase.step_counter as isize + plies as isize;
+    if player_wins == (player == 0b11) {
+        isize::MAX - end_step
+    } else {
+        isize::MIN + end_step
     }
 }
 
-fn evaluate_action(positions: u64, phase: AiPhase) -> isize {
-    let mut score: isize = 0;
-    let black_move_count = extract_black_move_count_from_board(positions);
-    let white_move_count = extract_white_move_count_from_board(positions);
-    let black_token_count = extract_black_token_count_from_board(positions);
-    let white_token_count = extract_white_token_count_from_board(positions);
+fn update_pv(pv: &mut Vec<SearchBoard>, forward_board: SearchBoard, child_pv: &[SearchBoard]) {
+    pv.clear();
+    pv.push(forward_board);
+    pv.extend_from_slice(child_pv);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agent::{context::{SearchContext, SearchLimits}, AiPhase},
+        logic::{forward_boards::forward_step_boards, game_state::{GameState, Phase, Token}, move_token_count::SearchBoard, position::negate_token, rules::BlockedOutcome}
+    };
+    use crate::agent::evaluation::{evaluate_action, EvaluationWeights};
+    use super::minimax;
 
-    if phase.phase == Phase::Move {
-        if (black_move_count == 0 && black_token_count > 3) || black_token_count == 2 {
-            return isize::MAX - phase.step_counter as isize
-        } else if (white_move_count == 0 && white_token_count > 3) || white_token_count == 2 {
-            return isize::MIN + phase.step_counter as isize
+    fn plain_minimax(board: SearchBoard, depth: usize, player: u8, phase: AiPhase) -> isize {
+        let eval = evaluate_action(board, phase, &EvaluationWeights::default());
+        if depth == 0 || (phase.phase == Phase::Move && (eval == isize::MAX - phase.step_counter as isize || eval == isize::MIN + phase.step_counter as isize)) {
+            return eval;
+        }
+        let evals = forward_step_boards(board, player, phase)
+            .map(|forward_board| plain_minimax(forward_board, depth - 1, negate_token(player), phase.increased()));
+        if player == 0b11 {
+            evals.max().unwrap_or(isize::MIN + phase.step_counter as isize)
+        } else {
+            evals.min().unwrap_or(isize::MAX - phase.step_counter as isize)
         }
     }
-    
-    score += (white_token_count as isize - black_token_count as isize) * 20;
-    score += white_move_count as isize - black_move_count as isize;
-    score
+
+    #[test]
+    fn test_principal_variation_search_finds_minimax_score() {
+        let positions = [
+            ("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0", 4),
+            ("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0", 4),
+            ("BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3", 4),
+            ("EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0", 3)
+        ];
+        for (position, depth) in positions {
+            let game_state: GameState = position.parse().unwrap();
+            let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+            let player = Token::parse_to_u8(game_state.get_player_turn());
+            let phase = AiPhase::from_game_state(&game_state);
+            let context = SearchContext::new(SearchLimits::infinite());
+
+            let score = minimax(board, depth, isize::MIN, isize::MAX, player, phase, &context, &mut Vec::new());
+            assert_eq!(score, Some(plain_minimax(board, depth, player, phase)), "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_draws_by_rules() {
+        /* white closes a mill with the next move, which wins against the two tokens of black */
+        let game_state = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 117 0 nine+draw50".parse::<GameState>().unwrap();
+        let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+        let search = |phase: AiPhase| minimax(board, 3, isize::MIN, isize::MAX, 0b11, phase, &SearchContext::new(SearchLimits::infinite()), &mut Vec::new());
+
+        let phase = AiPhase::from_game_state(&game_state);
+        assert!(search(phase).is_some_and(|score| score > isize::MAX - 200));
+        /* the capture of the last move before the draw resets the plies */
+        assert!(search(AiPhase { reversible_plies: 99, ..phase }).is_some_and(|score| score > isize::MAX - 200));
+        assert_eq!(search(AiPhase { reversible_plies: 100, ..phase }), Some(0));
+
+        let twelve = "BWBWBWBWWBWBWBWBBWBWBWBW w 0 0 24 0 twelve".parse::<GameState>().unwrap();
+        let board = SearchBoard::new(twelve.get_board(), twelve.get_rules());
+        let phase = AiPhase::from_game_state(&twelve);
+        let context = SearchContext::new(SearchLimits::infinite());
+        assert_ne!(minimax(board, 1, isize::MIN, isize::MAX, 0b11, phase, &context, &mut Vec::new()), Some(0));
+        let phase = phase.with_rules(twelve.get_rules().with_blocked_outcome(BlockedOutcome::Draw));
+        assert_eq!(minimax(board, 1, isize::MIN, isize::MAX, 0b11, phase, &context, &mut Vec::new()), Some(0));
+
+        /* the corners of black are blocked, but white to move isn't, so the game goes on */
+        let positions = [
+            ("WBWBWEWBEEEEEEEEEEEEEEEE w 0 0 18 0 nine+noflying+blockeddraw", false),
+            ("WBWBWEWBEEEEEEEEEEEEEEEE b 0 0 18 0 nine+noflying+blockeddraw", true)
+        ];
+        for (position, draw) in positions {
+            let game_state = position.parse::<GameState>().unwrap();
+            let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+            let player = Token::parse_to_u8(game_state.get_player_turn());
+            let score = minimax(board, 0, isize::MIN, isize::MAX, player, AiPhase::from_game_state(&game_state), &context, &mut Vec::new());
+            assert_eq!(game_state.get_draw_reason().is_some(), draw, "{}", position);
+            assert_eq!(score == Some(0), draw, "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_three_mens_mill_in_set_phase() {
+        /* the third placement of white closes a mill, the capture leaves black with two tokens */
+        let game_state = "WEEEEEEWBEEEEEEBEEEEEEEE w 1 1 4 0 three".parse::<GameState>().unwrap();
+        let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+        let phase = AiPhase::from_game_state(&game_state);
+        let context = SearchContext::new(SearchLimits::infinite());
+        assert_eq!(minimax(board, 1, isize::MIN, isize::MAX, 0b11, phase, &context, &mut Vec::new()), Some(isize::MAX - 5));
+    }
 }
@@ -1,99 +1,636 @@
-use ggez::timer;
-use minimax::minimax;
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use context::{SearchContext, SearchLimits};
+use minimax::{minimax, sorted_forward_boards};
+use transposition_table::{get_key, WIN_THRESHOLD};
+use std::fmt;
 use crate::logic::{
     action::{get_action_from_board, Action}, 
     forward_boards::forward_step_boards, 
-    game_state::{Phase, Token}, 
-    move_token_count::{insert_number_of_possible_moves_to_board, insert_token_count_to_board}, 
-    position::negate_token
+    game_state::{GameState, Phase, Token}, 
+    move_token_count::SearchBoard, 
+    position::{negate_token, Board},
+    rules::RuleSet
 };
 
+pub mod book;
+pub mod context;
+pub mod evaluation;
+pub mod handle;
 pub mod minimax;
+pub mod move_ordering;
+pub mod tablebase;
+pub mod timer;
+pub mod tournament;
+pub mod transposition_table;
+pub mod tuning;
 
+/*
+    The phase of a position in the search together with the rules of the
+    game and the plies since the last placement or capture, which decide
+    the draw without captures of the rules.
+*/
 #[derive(Clone, Copy)]
 pub struct AiPhase {
     pub phase: Phase,
-    pub step_counter: u8
+    pub step_counter: u16,
+    pub reversible_plies: u16,
+    pub rules: RuleSet
 }
 
 impl AiPhase {
-    pub fn new(phase: Phase, step_counter: u8) -> Self {
+    pub fn new(phase: Phase, step_counter: u16) -> Self {
         AiPhase {
             phase,
-            step_counter
+            step_counter,
+            reversible_plies: 0,
+            rules: RuleSet::default()
         }
     }
+
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn from_game_state(game_state: &GameState) -> Self {
+        let mut phase = AiPhase::new(game_state.get_phase(), game_state.get_step_counter()).with_rules(game_state.get_rules());
+        phase.reversible_plies = game_state.get_reversible_plies();
+        phase
+    }
+
+    /* the tokens the player with the token type still has to place */
+    pub fn get_tokens_in_hand(&self, token_type: u8) -> u8 {
+        let step_counter = self.step_counter.min(self.rules.get_set_plies() as u16) as u8;
+        let placed_tokens = if token_type == 0b11 { step_counter.div_ceil(2) } else { step_counter / 2 };
+        self.rules.get_tokens_per_player() - placed_tokens
+    }
+
+    /* whether the player can't have three tokens on the board anymore, see GameState::is_game_over */
+    pub fn is_out_of_tokens(&self, board: SearchBoard, token_type: u8) -> bool {
+        board.board().get_number_of_tokens(token_type) + self.get_tokens_in_hand(token_type) < 3
+    }
+
     pub fn increased(&self) -> Self {
-        let mut new_phase = AiPhase::new(self.phase, self.step_counter + 1);
-        if new_phase.phase == Phase::Set && new_phase.step_counter >= 18 {
+        let mut new_phase = AiPhase::new(self.phase, self.step_counter.saturating_add(1)).with_rules(self.rules);
+        if new_phase.phase == Phase::Set && new_phase.step_counter >= self.rules.get_set_plies() as u16 {
             new_phase.phase = Phase::Move;
         }
         new_phase
     }
+
+    /*
+        The phase after the action from board to forward_board. The
+        reversible plies are only counted if the rules draw without
+        captures, the counters of the boards can saturate, so the tokens
+        are counted on the boards.
+    */
+    pub fn after(&self, board: SearchBoard, forward_board: SearchBoard) -> Self {
+        let mut new_phase = self.increased();
+        if self.phase == Phase::Move && self.rules.get_no_capture_draw().is_some() {
+            let count_tokens = |board: Board| board.get_number_of_tokens(0b11) + board.get_number_of_tokens(0b10);
+            if count_tokens(board.board()) == count_tokens(forward_board.board()) {
+                new_phase.reversible_plies = self.reversible_plies.saturating_add(1);
+            }
+        }
+        new_phase
+    }
+}
+
+/* xorshift64*, good enough to vary self-play games and book moves */
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Self {
+        Random(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    pub(crate) fn next_index(&mut self, len: usize) -> usize {
+        (self.next_u64() >> 32) as usize % len
+    }
+}
+
+/*
+    The result of a finished iteration of the search. The score is given
+    from the view of the player to move. If the search found the end of the
+    game, mate contains the number of moves of the player to move until
+    then, which is negative if the player to move loses.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub action: Action,
+    pub score: isize,
+    pub mate: Option<isize>,
+    pub depth: usize,
+    pub nodes: u64,
+    pub time: f64,
+    pub pv: Vec<Action>
+}
+
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mate {
+            Some(mate) if mate > 0 => write!(f, "wins in {}", mate)?,
+            Some(mate) => write!(f, "loses in {}", -mate)?,
+            None => write!(f, "score {}", self.score)?
+        }
+        write!(f, ", depth {}, {} nodes, {:.2}s, pv", self.depth, self.nodes, self.time)?;
+        for action in self.pv.iter() {
+            write!(f, " {}", action)?;
+        }
+        Ok(())
+    }
 }
 
-#[allow(unused_assignments, unused_variables, clippy::if_same_then_else)]
-pub fn calculate_next_move(mut board: u64, player: Token, ai_phase: AiPhase, max_depth: usize) -> Action {
-    board = insert_token_count_to_board(board);
-    board = insert_number_of_possible_moves_to_board(board);
+/*
+    Converts a score of minimax, which is always from the view of white,
+    to the view of the player to move. Won positions are scored with
+    isize::MAX minus the step counter at the end of the game.
+*/
+fn get_relative_score(score: isize, player: Token, step_counter: u16) -> (isize, Option<isize>) {
+    let (end_step, winner) = if score > isize::MAX - WIN_THRESHOLD {
+        (isize::MAX - score, Token::White)
+    } else if score < isize::MIN + WIN_THRESHOLD {
+        (score - isize::MIN, Token::Black)
+    } else if player == Token::Black {
+        return (-score, None);
+    } else {
+        return (score, None);
+    };
+
+    let moves = (end_step - step_counter as isize + 1) / 2;
+    let relative_score = if player == Token::Black { score.saturating_neg() } else { score };
+    (relative_score, Some(if winner == player { moves } else { -moves }))
+}
+
+pub fn calculate_next_move(board: Board, player: Token, ai_phase: AiPhase, limits: SearchLimits) -> SearchResult {
+    search(board, player, ai_phase, &SearchContext::new(limits), |_| {})
+        .expect("no legal action")
+}
+
+/*
+    Iterative deepening from 1 ply up to the depth limit of the context.
+    Every finished iteration is reported with on_result. The first
+    iteration always completes, so a result is returned even if the search
+    is stopped right away. Returns None only if the player has no legal
+    action. Positions in the opening book of the context are not searched,
+    the book move is returned as a result of depth 0.
+*/
+pub fn search(
+    board: Board, 
+    player: Token, 
+    ai_phase: AiPhase, 
+    context: &SearchContext, 
+    mut on_result: impl FnMut(&SearchResult)
+) -> Option<SearchResult> {
+    let board = SearchBoard::new(board, ai_phase.rules);
+    forward_step_boards(board, Token::parse_to_u8(player), ai_phase).next()?;
+    if let Some(result) = get_book_result(board.board(), ai_phase, context) {
+        on_result(&result);
+        return Some(result);
+    }
+    let mut best_result = None;
 
-    let now = timer::time();
-    
+    let pool = context.get_thread_pool();
+    for depth in 1..=context.get_limits().get_max_depth().max(1) {
+        let iteration = || search_iteration(board, player, ai_phase, depth, best_result.as_ref(), context);
+        let result = match pool.as_ref() {
+            Some(pool) => pool.install(iteration),
+            None => iteration()
+        };
+        let Some(result) = result else {
+            break;
+        };
+        on_result(&result);
+        let is_complete = is_search_complete(&result, context.get_limits());
+        best_result = Some(result);
+        if is_complete {
+            break;
+        }
+    }
+
+    best_result
+}
+
+fn get_book_result(board: Board, ai_phase: AiPhase, context: &SearchContext) -> Option<SearchResult> {
+    if ai_phase.phase != Phase::Set {
+        return None;
+    }
+    let random = context.is_book_random().then(|| Random::new(timer::time().to_bits()).next_u64());
+    let action = context.get_book()?.choose(board, ai_phase.step_counter, ai_phase.rules, random)?;
+    Some(SearchResult { action, score: 0, mate: None, depth: 0, nodes: 0, time: context.get_elapsed(), pv: vec![action] })
+}
+
+/*
+    Once the end of the game is found, deeper iterations can't find a
+    faster win or avoid the loss, so only infinite searches go on.
+*/
+fn is_search_complete(result: &SearchResult, limits: SearchLimits) -> bool {
+    result.mate.is_some() && !limits.infinite
+}
+
+/* half the width of the aspiration window around the previous score */
+const ASPIRATION_WINDOW: isize = 10;
+
+/*
+    Searches all actions of the player with the given depth and returns
+    None if the search was stopped. The player needs at least one legal
+    action. Iterations of depth 1 always complete.
+
+    The result of the previous iteration seeds the search: its action is
+    searched first and its score is expected to change only a little, so
+    the search starts with a narrow aspiration window around it. If the
+    score falls outside the window, the window is widened and the
+    iteration repeated.
+
+    With more than one thread the iteration uses lazy SMP: helper threads
+    search the same position at the same or the next depth with the
+    actions in a different order, until the main search is finished. Their
+    results are not used, but they fill the shared transposition table, so
+    the main search finds many positions already searched.
+*/
+fn search_iteration(
+    board: SearchBoard, 
+    player: Token, 
+    ai_phase: AiPhase, 
+    depth: usize, 
+    previous: Option<&SearchResult>, 
+    context: &SearchContext
+) -> Option<SearchResult> {
     let player_parsed = Token::parse_to_u8(player);
-    let mut depth = 0;
-    let mut best_action_total = None;
-    let mut best_score_total = match player {
-        Token::White => isize::MIN,
-        Token::Black => isize::MAX,
-        Token::None => unreachable!()
+    let mut forward_boards = sorted_forward_boards(board, player_parsed, ai_phase, context.get_weights())
+        .into_iter()
+        .map(|(_, forward_board)| forward_board)
+        .collect::<Vec<SearchBoard>>();
+    if let Some(previous) = previous {
+        let previous_board = forward_boards.iter().position(|forward_board| {
+            get_action_from_board(board.board(), forward_board.board(), player_parsed) == previous.action
+        });
+        if let Some(position) = previous_board {
+            let forward_board = forward_boards.remove(position);
+            forward_boards.insert(0, forward_board);
+        }
+    }
+
+    let helpers = (1..context.get_threads())
+        .map(|_| context.helper())
+        .collect::<Vec<SearchContext>>();
+    let (best_score, forward_board, pv) = rayon::scope(|scope| {
+        for (index, helper) in helpers.iter().enumerate() {
+            let mut forward_boards = forward_boards.clone();
+            let rotation = (index + 1) % forward_boards.len();
+            forward_boards.rotate_left(rotation);
+            let depth = depth + (index + 1) % 2;
+            scope.spawn(move |_| {
+                search_root(board, &forward_boards, player_parsed, ai_phase, depth, isize::MIN, isize::MAX, helper);
+            });
+        }
+
+        let result = search_aspiration(board, &forward_boards, player, ai_phase, depth, previous, context);
+        for helper in helpers.iter() {
+            helper.stop();
+        }
+        result
+    })?;
+    for helper in helpers.iter() {
+        context.add_nodes(helper.get_nodes());
+    }
+
+    let mut pv = pv;
+    extend_pv(forward_board, &mut pv, negate_token(player_parsed), ai_phase.after(board, forward_board), depth - 1, context);
+    let pv = pv_to_actions(board, forward_board, &pv, player_parsed);
+    let (score, mate) = get_relative_score(best_score, player, ai_phase.step_counter);
+    Some(SearchResult {
+        action: pv[0],
+        score,
+        mate,
+        depth,
+        nodes: context.get_nodes(),
+        time: context.get_elapsed(),
+        pv
+    })
+}
+
+fn search_aspiration(
+    board: SearchBoard,
+    forward_boards: &[SearchBoard],
+    player: Token,
+    ai_phase: AiPhase,
+    depth: usize,
+    previous: Option<&SearchResult>,
+    context: &SearchContext
+) -> Option<(isize, SearchBoard, Vec<SearchBoard>)> {
+    let mut delta = ASPIRATION_WINDOW;
+    let (mut alpha, mut beta) = match previous.filter(|previous| previous.mate.is_none()) {
+        Some(previous) => {
+            let score = if player == Token::Black { -previous.score } else { previous.score };
+            (score - delta, score + delta)
+        },
+        None => (isize::MIN, isize::MAX)
     };
-    let mut actions_with_scores: Vec<(u64, Option<isize>)> = Vec::with_capacity(500);
 
-    'outer_loop: loop {
-        if depth > max_depth {
-            break 'outer_loop;
+    loop {
+        let (score, forward_board, pv) = search_root(board, forward_boards, Token::parse_to_u8(player), ai_phase, depth, alpha, beta, context)?;
+        delta = delta.saturating_mul(4);
+        if score <= alpha && alpha != isize::MIN {
+            alpha = score.saturating_sub(delta);
+        } else if score >= beta && beta != isize::MAX {
+            beta = score.saturating_add(delta);
+        } else {
+            return Some((score, forward_board, pv));
         }
+    }
+}
+
+/*
+    Principal variation search at the root. The first action is searched
+    with the whole window, all others with a null window, which only
+    tells whether they are better. Those that are better are searched
+    again with the whole window. Returns the best score, which is a
+    bound if it's outside the window, the forward board of the best
+    action and the principal variation after it.
+*/
+#[allow(clippy::too_many_arguments)]
+fn search_root(
+    board: SearchBoard,
+    forward_boards: &[SearchBoard],
+    player: u8,
+    ai_phase: AiPhase,
+    depth: usize,
+    alpha: isize,
+    beta: isize,
+    context: &SearchContext
+) -> Option<(isize, SearchBoard, Vec<SearchBoard>)> {
+    let maximizing = player == 0b11;
+    let search_child = |forward_board: SearchBoard, alpha: isize, beta: isize| {
+        let mut pv = Vec::new();
+        minimax(forward_board, depth - 1, alpha, beta, negate_token(player), ai_phase.after(board, forward_board), context, &mut pv)
+            .map(|score| (score, pv))
+    };
+    let is_better = |score: isize, best_score: isize| if maximizing { score > best_score } else { score < best_score };
+    let is_cutoff = |score: isize| if maximizing { score >= beta } else { score <= alpha };
 
-        let mut best_action = None;
-        let mut best_score = match player {
-            Token::White => isize::MIN,
-            Token::Black => isize::MAX,
-            Token::None => unreachable!()
+    let (first_board, forward_boards) = forward_boards.split_first()?;
+    let (mut best_score, mut best_pv) = search_child(*first_board, alpha, beta)?;
+    let mut best_board = *first_board;
+    if is_cutoff(best_score) {
+        return Some((best_score, best_board, best_pv));
+    }
+
+    for forward_board in forward_boards.iter().copied() {
+        let (null_alpha, null_beta) = if maximizing {
+            let bound = best_score.max(alpha);
+            (bound, bound.saturating_add(1))
+        } else {
+            let bound = best_score.min(beta);
+            (bound.saturating_sub(1), bound)
         };
+        let (score, _) = search_child(forward_board, null_alpha, null_beta)?;
+        if !is_better(score, if maximizing { null_alpha } else { null_beta }) {
+            continue;
+        }
 
-        actions_with_scores = forward_step_boards(&board, player_parsed, ai_phase)
-            .par_bridge()
-            .map(|forward_board| {
-                (forward_board, minimax(
-                    forward_board, 
-                    depth, 
-                    isize::MIN, 
-                    isize::MAX, 
-                    negate_token(player_parsed), 
-                    ai_phase.clone().increased(), 
-                    now
-                ))
-            }).collect();
-            
-        for (forward_board, score) in actions_with_scores.into_iter() {
-            if score.is_none() {
-                break 'outer_loop;
+        let (score, pv) = if maximizing {
+            search_child(forward_board, null_alpha, beta)?
+        } else {
+            search_child(forward_board, alpha, null_beta)?
+        };
+        if is_better(score, best_score) {
+            best_score = score;
+            best_board = forward_board;
+            best_pv = pv;
+            if is_cutoff(best_score) {
+                break;
             }
+        }
+    }
+
+    Some((best_score, best_board, best_pv))
+}
+
+/*
+    Positions that were found in the transposition table end the principal
+    variation of minimax early, so it is continued with the best moves
+    stored in the table.
+*/
+fn extend_pv(forward_board: SearchBoard, pv: &mut Vec<SearchBoard>, player: u8, ai_phase: AiPhase, depth: usize, context: &SearchContext) {
+    let (mut board, mut player, mut ai_phase) = (forward_board, player, ai_phase);
+    for next in pv.iter() {
+        (board, player, ai_phase) = (*next, negate_token(player), ai_phase.after(board, *next));
+    }
+
+    while pv.len() < depth {
+        let Some(best_move) = context.get_table()
+            .probe(get_key(board, player, ai_phase), ai_phase.step_counter)
+            .and_then(|entry| entry.best_move) else {
+            break;
+        };
+        let Some(next) = forward_step_boards(board, player, ai_phase).nth(best_move) else {
+            break;
+        };
+        pv.push(next);
+        (board, player, ai_phase) = (next, negate_token(player), ai_phase.after(board, next));
+    }
+}
+
+fn pv_to_actions(board: SearchBoard, forward_board: SearchBoard, pv: &[SearchBoard], player: u8) -> Vec<Action> {
+    let mut actions = Vec::with_capacity(pv.len() + 1);
+    let mut previous = board.board();
+    let mut token = player;
+    for next in std::iter::once(forward_board).chain(pv.iter().copied()) {
+        actions.push(get_action_from_board(previous, next.board(), token));
+        previous = next.board();
+        token = negate_token(token);
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::game_state::{GameState, Token};
+    use std::sync::Arc;
+    use crate::logic::action::{list_actions, Action};
+    use crate::logic::move_token_count::SearchBoard;
+    use super::{
+        context::{SearchContext, SearchLimits},
+        evaluation::{EvaluationWeights, PhaseWeights},
+        get_relative_score,
+        search,
+        search_iteration,
+        transposition_table::TranspositionTable,
+        AiPhase,
+        SearchResult
+    };
+
+    fn legal_actions(game_state: &GameState) -> Vec<Action> {
+        list_actions(game_state.get_board(), Token::parse_to_u8(game_state.get_player_turn()), game_state.get_phase(), None, game_state.get_rules()).collect()
+    }
 
-            if player == Token::White && score.unwrap() >= best_score {
-                best_action = Some(get_action_from_board(board, forward_board, player_parsed));
-                best_score = score.unwrap();
-            } else if player == Token::Black && score.unwrap() <= best_score {
-                best_action = Some(get_action_from_board(board, forward_board, player_parsed));
-                best_score = score.unwrap();
+    #[test]
+    fn test_search_reports_every_iteration() {
+        let mut depths = Vec::new();
+        let result = search(
+            GameState::default().get_board(),
+            Token::White,
+            AiPhase::from_game_state(&GameState::default()),
+            &SearchContext::new(SearchLimits::depth(3)),
+            |result| depths.push(result.depth)
+        ).unwrap();
+
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.depth, 3);
+    }
+
+    #[test]
+    fn test_search_ends_with_mate() {
+        let game_state: GameState = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0".parse().unwrap();
+        let mut depths = Vec::new();
+        let result = search(
+            game_state.get_board(),
+            Token::White,
+            AiPhase::from_game_state(&game_state),
+            &SearchContext::new(SearchLimits::depth(3)),
+            |result| {
+                assert!(result.pv.first().unwrap().beatable_position.is_some());
+                depths.push(result.depth)
             }
+        ).unwrap();
+
+        assert_eq!(depths, vec![1]);
+        assert_eq!(result.action.start_position, Some(16));
+        assert_eq!(result.action.end_position, 3);
+        assert_eq!(result.mate, Some(1));
+
+        /* the step counter goes past 255 plies in long games */
+        let game_state: GameState = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 1000 0".parse().unwrap();
+        let context = SearchContext::new(SearchLimits::depth(3));
+        let result = search(game_state.get_board(), Token::White, AiPhase::from_game_state(&game_state), &context, |_| {}).unwrap();
+        assert_eq!(result.action.end_position, 3);
+        assert_eq!(result.mate, Some(1));
+        assert_eq!(AiPhase::from_game_state(&game_state).increased().step_counter, 1001);
+    }
+
+    #[test]
+    fn test_aspiration_window_fails() {
+        let game_state: GameState = "WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0".parse().unwrap();
+        let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+        let ai_phase = AiPhase::from_game_state(&game_state);
+        let expected = search_iteration(board, Token::White, ai_phase, 4, None, &SearchContext::new(SearchLimits::infinite())).unwrap();
+
+        for previous_score in [-500, 500] {
+            let previous = SearchResult { score: previous_score, ..expected.clone() };
+            let result = search_iteration(board, Token::White, ai_phase, 4, Some(&previous), &SearchContext::new(SearchLimits::infinite())).unwrap();
+            assert_eq!(result.score, expected.score);
+        }
+    }
+
+    #[test]
+    fn test_lazy_smp() {
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3".parse().unwrap();
+        let single = SearchContext::new(SearchLimits::depth(5)).with_threads(1);
+        let expected = search(game_state.get_board(), Token::Black, AiPhase::from_game_state(&game_state), &single, |_| {}).unwrap();
+
+        let context = SearchContext::new(SearchLimits::depth(5)).with_threads(4);
+        let mut depths = Vec::new();
+        let result = search(game_state.get_board(), Token::Black, AiPhase::from_game_state(&game_state), &context, |result| depths.push(result.depth)).unwrap();
+        assert_eq!(depths, vec![1, 2, 3, 4, 5]);
+        assert_eq!(result.mate, expected.mate);
+        assert!(legal_actions(&game_state).contains(&result.action));
+    }
+
+    #[test]
+    fn test_lazy_smp_with_weights() {
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3".parse().unwrap();
+        let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+        let ai_phase = AiPhase::from_game_state(&game_state);
+        let weights = EvaluationWeights::from_array(EvaluationWeights::default().to_array()
+            .map(|phase_weights| PhaseWeights::from_array(phase_weights.to_array().map(|weight| weight * 7))));
+        let context_with_threads = |threads| SearchContext::new(SearchLimits::depth(4)).with_weights(weights).with_threads(threads);
+        let expected = search(game_state.get_board(), Token::Black, ai_phase, &context_with_threads(1), |_| {}).unwrap();
+
+        /* a helper stores the scores the main search would store, so the main search finds its own score in the table */
+        let context = context_with_threads(1);
+        search_iteration(board, Token::Black, ai_phase, 4, None, &context.helper()).unwrap();
+        let result = search(game_state.get_board(), Token::Black, ai_phase, &context, |_| {}).unwrap();
+        assert_eq!(result.score, expected.score);
+
+        /* helpers search up to one ply deeper, so the score can change, but it is still made of the scaled evaluations */
+        for threads in [2, 4] {
+            let result = search(game_state.get_board(), Token::Black, ai_phase, &context_with_threads(threads), |_| {}).unwrap();
+            assert_eq!(result.score % 7, 0);
+        }
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let context = SearchContext::new(SearchLimits::nodes(1000));
+        let mut depths = Vec::new();
+        search(GameState::default().get_board(), Token::White, AiPhase::from_game_state(&GameState::default()), &context, |result| depths.push(result.depth));
+
+        assert!(!depths.is_empty());
+        assert!(context.get_nodes() < 2000);
+    }
+
+    #[test]
+    fn test_stopped_search_returns_action() {
+        let context = SearchContext::new(SearchLimits::infinite());
+        context.stop();
+        let result = search(GameState::default().get_board(), Token::White, AiPhase::from_game_state(&GameState::default()), &context, |_| {}).unwrap();
+        assert!(result.action.start_position.is_none());
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.mate, None);
+    }
+
+    #[test]
+    fn test_transposition_table_is_reused() {
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3".parse().unwrap();
+        let table = Arc::new(TranspositionTable::new(1));
+        let mut scores = Vec::new();
+        let mut nodes = Vec::new();
+        let mut pv_lengths = Vec::new();
+        for _ in 0..2 {
+            let context = SearchContext::with_table(SearchLimits::depth(5), Arc::clone(&table)).with_threads(1);
+            let result = search(game_state.get_board(), Token::Black, AiPhase::from_game_state(&game_state), &context, |_| {}).unwrap();
+            scores.push(result.score);
+            pv_lengths.push(result.pv.len());
+            nodes.push(context.get_nodes());
         }
 
-        best_action_total = best_action;
-        best_score_total = best_score;
-        depth += 1;
+        assert_eq!(scores[0], scores[1]);
+        assert!(nodes[1] < nodes[0]);
+        /* the second search finds the root actions in the table, the principal variation is continued from it */
+        assert_eq!(pv_lengths, vec![5, 5]);
     }
 
-    best_action_total.unwrap()
+    #[test]
+    fn test_relative_score() {
+        assert_eq!(get_relative_score(25, Token::White, 10), (25, None));
+        assert_eq!(get_relative_score(25, Token::Black, 10), (-25, None));
+        assert_eq!(get_relative_score(isize::MAX - 21, Token::White, 20), (isize::MAX - 21, Some(1)));
+        assert_eq!(get_relative_score(isize::MAX - 23, Token::White, 20), (isize::MAX - 23, Some(2)));
+        assert_eq!(get_relative_score(isize::MAX - 22, Token::Black, 20), (-(isize::MAX - 22), Some(-1)));
+        assert_eq!(get_relative_score(isize::MIN + 21, Token::Black, 20), (isize::MAX - 20, Some(1)));
+        assert_eq!(get_relative_score(isize::MAX - 1001, Token::White, 1000), (isize::MAX - 1001, Some(1)));
+    }
+
+    #[test]
+    fn test_display_result() {
+        let mut result = SearchResult {
+            action: Action::new(None, 5, None),
+            score: -3,
+            mate: None,
+            depth: 2,
+            nodes: 120,
+            time: 0.0125,
+            pv: vec![Action::new(None, 5, None), Action::new(None, 0, None)]
+        };
+        assert_eq!(result.to_string(), "score -3, depth 2, 120 nodes, 0.01s, pv a1 d7");
+        result.mate = Some(-2);
+        assert_eq!(result.to_string(), "loses in 2, depth 2, 120 nodes, 0.01s, pv a1 d7");
+    }
 }
@@ -0,0 +1,232 @@
+use std::{cmp::Reverse, sync::atomic::{AtomicU32, Ordering}};
+use crate::logic::{forward_boards::forward_step_boards, move_token_count::SearchBoard};
+use super::{
+    evaluation::EvaluationWeights,
+    minimax::sorted_forward_boards,
+    AiPhase
+};
+
+/* a search only spans a few plies, so the step counters of its positions don't share a slot */
+const KILLER_SLOTS: usize = 256;
+/* tokens that are set come from position 24 */
+const FROM_POSITIONS: usize = 25;
+
+/*
+    A move is identified by its start and end position, the captured
+    token is ignored. Set moves start at position 24.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    from: usize,
+    to: usize,
+    capture: bool
+}
+
+impl Move {
+    pub fn from_boards(board: SearchBoard, forward_board: SearchBoard, player: u8) -> Self {
+        let before = board.board().to_raw();
+        let after = forward_board.board().to_raw();
+        let mut changed = before ^ after;
+        let mut from = FROM_POSITIONS - 1;
+        let mut to = 0;
+        let mut capture = false;
+
+        while changed != 0 {
+            let shift = changed.trailing_zeros() & !1;
+            let position = 23 - (shift / 2) as usize;
+            if (before >> shift) & 0b11 == 0 {
+                to = position;
+            } else if (before >> shift) & 0b11 == player as u64 {
+                from = position;
+            } else {
+                capture = true;
+            }
+            changed &= !(0b11 << shift);
+        }
+        Move { from, to, capture }
+    }
+
+    fn code(self) -> u32 {
+        (self.from * 24 + self.to) as u32 + 1
+    }
+}
+
+/*
+    Move ordering heuristics shared by all threads of a search. Killer
+    moves are the last two moves that caused a beta cutoff after the same
+    number of steps, so they are often good in sibling positions as well.
+    The history table counts for every player, start and end position how
+    much the move caused cutoffs, weighted by the remaining depth.
+
+    Moves are tried in this order: the move of the transposition table,
+    captures, the two killer moves and then all other moves by their
+    history. The forward boards aren't evaluated, which would cost more
+    than the cutoffs it finds in interior nodes, only the root sorts its
+    moves by the static evaluation. If the heuristics are disabled, the
+    moves are only sorted by their static evaluation.
+*/
+pub struct MoveOrdering {
+    enabled: bool,
+    killers: Vec<[AtomicU32; 2]>,
+    history: Vec<AtomicU32>
+}
+
+impl MoveOrdering {
+    pub fn new(enabled: bool) -> Self {
+        MoveOrdering {
+            enabled,
+            killers: (0..KILLER_SLOTS).map(|_| [AtomicU32::new(0), AtomicU32::new(0)]).collect(),
+            history: (0..2 * FROM_POSITIONS * 24).map(|_| AtomicU32::new(0)).collect()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn clear(&self) {
+        for killers in self.killers.iter() {
+            killers[0].store(0, Ordering::Relaxed);
+            killers[1].store(0, Ordering::Relaxed);
+        }
+        for history in self.history.iter() {
+            history.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /*
+        Returns the forward boards with their index in the order of
+        forward_step_boards, in the order they should be searched.
+    */
+    pub fn order(
+        &self, 
+        board: SearchBoard, 
+        player: u8, 
+        phase: AiPhase, 
+        table_move: Option<usize>, 
+        weights: &EvaluationWeights
+    ) -> Vec<(usize, SearchBoard)> {
+        let mut forward_boards = if self.enabled {
+            let killers = &self.killers[phase.step_counter as usize % KILLER_SLOTS];
+            let killers = [killers[0].load(Ordering::Relaxed), killers[1].load(Ordering::Relaxed)];
+            let mut forward_boards = forward_step_boards(board, player, phase)
+                .enumerate()
+                .map(|(index, forward_board)| {
+                    let next_move = Move::from_boards(board, forward_board, player);
+                    let killer = if next_move.code() == killers[0] {
+                        2
+                    } else if next_move.code() == killers[1] {
+                        1
+                    } else {
+                        0
+                    };
+                    ((next_move.capture, killer, self.get_history(player, next_move)), index, forward_board)
+                })
+                .collect::<Vec<((bool, u8, u32), usize, SearchBoard)>>();
+            forward_boards.sort_by_key(|(score, _, _)| Reverse(*score));
+            forward_boards.into_iter().map(|(_, index, forward_board)| (index, forward_board)).collect()
+        } else {
+            sorted_forward_boards(board, player, phase, weights)
+        };
+
+        if let Some(position) = forward_boards.iter().position(|(index, _)| Some(*index) == table_move) {
+            let table_board = forward_boards.remove(position);
+            forward_boards.insert(0, table_board);
+        }
+        forward_boards
+    }
+
+    /* called for the move that caused a beta cutoff */
+    pub fn update(&self, board: SearchBoard, forward_board: SearchBoard, player: u8, phase: AiPhase, depth: usize) {
+        let cutoff_move = Move::from_boards(board, forward_board, player);
+        if !self.enabled || cutoff_move.capture {
+            return;
+        }
+
+        let killers = &self.killers[phase.step_counter as usize % KILLER_SLOTS];
+        let code = cutoff_move.code();
+        if killers[0].load(Ordering::Relaxed) != code {
+            killers[1].store(killers[0].load(Ordering::Relaxed), Ordering::Relaxed);
+            killers[0].store(code, Ordering::Relaxed);
+        }
+
+        let bonus = (depth * depth).min(u32::MAX as usize) as u32;
+        let _ = self.history[history_index(player, cutoff_move)]
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |history| Some(history.saturating_add(bonus)));
+    }
+
+    fn get_history(&self, player: u8, next_move: Move) -> u32 {
+        self.history[history_index(player, next_move)].load(Ordering::Relaxed)
+    }
+}
+
+fn history_index(player: u8, next_move: Move) -> usize {
+    ((player == 0b11) as usize * FROM_POSITIONS + next_move.from) * 24 + next_move.to
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agent::AiPhase,
+        logic::{forward_boards::forward_step_boards, game_state::{GameState, Phase}, move_token_count::SearchBoard}
+    };
+    use crate::agent::{evaluation::EvaluationWeights, minimax::sorted_forward_boards};
+    use super::{Move, MoveOrdering};
+
+    fn boards(position: &str) -> (SearchBoard, AiPhase, Vec<SearchBoard>) {
+        let game_state: GameState = position.parse().unwrap();
+        let board = SearchBoard::new(game_state.get_board(), game_state.get_rules());
+        let phase = AiPhase::from_game_state(&game_state);
+        (board, phase, forward_step_boards(board, 0b11, phase).collect())
+    }
+
+    #[test]
+    fn test_move_from_boards() {
+        let (board, _, forward_boards) = boards("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0");
+        assert!(forward_boards.iter().any(|forward_board| Move::from_boards(board, *forward_board, 0b11) == Move { from: 24, to: 7, capture: true }));
+        assert!(forward_boards.iter().any(|forward_board| Move::from_boards(board, *forward_board, 0b11) == Move { from: 24, to: 5, capture: false }));
+
+        let (board, _, forward_boards) = boards("BWWEWBBBBEEWWWEEBEEEEEEE w 0 0 24 3");
+        assert!(forward_boards.iter().any(|forward_board| Move::from_boards(board, *forward_board, 0b11) == Move { from: 4, to: 3, capture: true }));
+        assert!(forward_boards.iter().any(|forward_board| Move::from_boards(board, *forward_board, 0b11) == Move { from: 2, to: 10, capture: false }));
+    }
+
+    #[test]
+    fn test_order() {
+        let (board, phase, forward_boards) = boards("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0");
+        let ordering = MoveOrdering::new(true);
+        let weights = EvaluationWeights::default();
+        let quiet = |index: usize| !Move::from_boards(board, forward_boards[index], 0b11).capture;
+        let quiet_moves = (0..forward_boards.len()).filter(|index| quiet(*index)).collect::<Vec<usize>>();
+        let (first, second, third) = (quiet_moves[0], quiet_moves[1], quiet_moves[2]);
+
+        let position = |order: &[(usize, SearchBoard)], index: usize| order.iter().position(|(order_index, _)| *order_index == index).unwrap();
+        let order = ordering.order(board, 0b11, phase, None, &weights);
+        assert_eq!(order.len(), forward_boards.len());
+        assert!(!quiet(order[0].0));
+        assert!(position(&order, first) < position(&order, second));
+        assert!(position(&order, second) < position(&order, third));
+
+        /* the killer moves come after the captures and before all other moves */
+        ordering.update(board, forward_boards[second], 0b11, phase, 4);
+        ordering.update(board, forward_boards[third], 0b11, phase, 2);
+        let order = ordering.order(board, 0b11, phase, Some(quiet_moves[3]), &weights);
+        assert_eq!(order[0].0, quiet_moves[3]);
+        assert!(!quiet(order[1].0));
+        assert!(position(&order, third) < position(&order, second));
+        assert!(position(&order, second) < position(&order, first));
+        assert!(quiet_moves[4..].iter().all(|index| position(&order, second) < position(&order, *index)));
+
+        /* history is shared between different numbers of steps, killers are not */
+        let order = ordering.order(board, 0b11, AiPhase::new(Phase::Set, 6), None, &weights);
+        assert!(position(&order, second) < position(&order, third));
+        assert!(position(&order, third) < position(&order, first));
+
+        ordering.clear();
+        assert_eq!(ordering.order(board, 0b11, phase, None, &weights), MoveOrdering::new(true).order(board, 0b11, phase, None, &weights));
+
+        let disabled = MoveOrdering::new(false);
+        disabled.update(board, forward_boards[quiet_moves[3]], 0b11, phase, 4);
+        assert_eq!(disabled.order(board, 0b11, phase, None, &weights), sorted_forward_boards(board, 0b11, phase, &weights));
+    }
+}
@@ -0,0 +1,654 @@
+use std::{fs, io, iter, path::Path};
+use rayon::prelude::*;
+use crate::logic::{mill_detection::MILLS, position::Board, r#move::NEIGHBORS};
+
+pub const MIN_TOKENS: usize = 3;
+pub const MAX_TOKENS: usize = 9;
+
+const ALL_POSITIONS: u32 = (1 << 24) - 1;
+const MAGIC: &[u8; 4] = b"MTB1";
+const NO_WIN: u8 = u8::MAX;
+
+/*
+    The value of a position for the player to move with the number of
+    plies until the end of the game if both players play perfectly, i.e.
+    the winner as fast and the loser as slow as possible.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TablebaseValue {
+    Win(u8),
+    Loss(u8),
+    Draw
+}
+
+impl TablebaseValue {
+    /* a value is stored as the number of plies plus one, or 0 for a draw */
+    fn decode(value: u8) -> Self {
+        match value {
+            0 => TablebaseValue::Draw,
+            value if (value - 1) % 2 == 1 => TablebaseValue::Win(value - 1),
+            value => TablebaseValue::Loss(value - 1)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TableStatistics {
+    pub positions: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub longest_win: u8
+}
+
+const BINOMIALS: [[usize; 25]; 25] = get_binomials();
+const MILL_MASKS: [u32; 16] = get_mill_masks();
+const POSITION_MILL_MASKS: [[u32; 2]; 24] = get_position_mill_masks();
+const NEIGHBOR_MASKS: [u32; 24] = get_neighbor_masks();
+
+const fn get_binomials() -> [[usize; 25]; 25] {
+    let mut binomials = [[0; 25]; 25];
+    let mut n = 0;
+    while n < 25 {
+        binomials[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            binomials[n][k] = binomials[n - 1][k - 1] + binomials[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    binomials
+}
+
+const fn get_mill_masks() -> [u32; 16] {
+    let mut masks = [0; 16];
+    let mut index = 0;
+    while index < 16 {
+        masks[index] = (1 << MILLS[index][0]) | (1 << MILLS[index][1]) | (1 << MILLS[index][2]);
+        index += 1;
+    }
+    masks
+}
+
+const fn get_position_mill_masks() -> [[u32; 2]; 24] {
+    let masks = get_mill_masks();
+    let mut position_masks = [[0; 2]; 24];
+    let mut index = 0;
+    while index < 16 {
+        let mut position = 0;
+        while position < 24 {
+            if masks[index] & (1 << position) != 0 {
+                let slot = if position_masks[position][0] == 0 { 0 } else { 1 };
+                position_masks[position][slot] = masks[index];
+            }
+            position += 1;
+        }
+        index += 1;
+    }
+    position_masks
+}
+
+const fn get_neighbor_masks() -> [u32; 24] {
+    let mut masks = [0; 24];
+    let mut position = 0;
+    while position < 24 {
+        let mut index = 0;
+        while index < 4 {
+            if NEIGHBORS[position][index] != 24 {
+                masks[position] |= 1 << NEIGHBORS[position][index];
+            }
+            index += 1;
+        }
+        position += 1;
+    }
+    masks
+}
+
+/*
+    Inside the tablebase a position is stored as two masks with one bit
+    per position, the tokens of the player to move and the tokens of the
+    other player. Won and lost positions don't depend on the colour.
+*/
+fn positions(mut tokens: u32) -> impl Iterator<Item = usize> {
+    iter::from_fn(move || {
+        if tokens == 0 {
+            return None;
+        }
+        let position = tokens.trailing_zeros() as usize;
+        tokens &= tokens - 1;
+        Some(position)
+    })
+}
+
+fn get_mill_tokens(tokens: u32) -> u32 {
+    MILL_MASKS.iter()
+        .filter(|mask| tokens & **mask == **mask)
+        .fold(0, |mill_tokens, mask| mill_tokens | mask)
+}
+
+fn get_tokens(board: Board, player: u8) -> (u32, u32) {
+    let (mut own, mut other) = (0, 0);
+    for (position, token) in board.token_iter().enumerate() {
+        if token == player {
+            own |= 1 << position;
+        } else if token != 0b00 {
+            other |= 1 << position;
+        }
+    }
+    (own, other)
+}
+
+struct Successor {
+    own: u32,
+    other: u32,
+    capture: bool
+}
+
+/*
+    Calls on_successor with all positions after the actions of the player
+    to move, from the view of the other player, who moves next.
+*/
+fn for_each_successor(own: u32, other: u32, mut on_successor: impl FnMut(Successor)) {
+    let empty = ALL_POSITIONS & !(own | other);
+    let flying = own.count_ones() == 3;
+    let other_mill_tokens = get_mill_tokens(other);
+    let capturable = if other & !other_mill_tokens == 0 { other } else { other & !other_mill_tokens };
+
+    for start in positions(own) {
+        let targets = if flying { empty } else { NEIGHBOR_MASKS[start] & empty };
+        for end in positions(targets) {
+            let moved = (own & !(1 << start)) | (1 << end);
+            if POSITION_MILL_MASKS[end].iter().any(|mask| moved & mask == *mask) {
+                for captured in positions(capturable) {
+                    on_successor(Successor { own: other & !(1 << captured), other: moved, capture: true });
+                }
+            } else {
+                on_successor(Successor { own: other, other: moved, capture: false });
+            }
+        }
+    }
+}
+
+/*
+    Calls on_predecessor with all positions from which the other player
+    reached the position with a move that didn't close a mill, again from
+    the view of the player to move in them.
+*/
+fn for_each_predecessor(own: u32, other: u32, mut on_predecessor: impl FnMut(u32, u32)) {
+    let empty = ALL_POSITIONS & !(own | other);
+    let flying = other.count_ones() == 3;
+
+    for end in positions(other & !get_mill_tokens(other)) {
+        let starts = if flying { empty } else { NEIGHBOR_MASKS[end] & empty };
+        for start in positions(starts) {
+            on_predecessor((other & !(1 << end)) | (1 << start), own);
+        }
+    }
+}
+
+/*
+    Positions are indexed with the combinatorial number system: the rank
+    of the tokens of the player to move among all 24 positions times the
+    number of ways to place the other tokens on the remaining positions
+    plus the rank of the other tokens among them. So every index stands
+    for a different position and no index is unused.
+*/
+fn get_table_size(own_count: usize, other_count: usize) -> usize {
+    BINOMIALS[24][own_count] * BINOMIALS[24 - own_count][other_count]
+}
+
+fn get_rank(tokens: u32) -> usize {
+    positions(tokens).enumerate().map(|(index, position)| BINOMIALS[position][index + 1]).sum()
+}
+
+fn from_rank(mut rank: usize, count: usize) -> u32 {
+    let mut tokens = 0;
+    for index in (1..=count).rev() {
+        let mut position = index - 1;
+        while BINOMIALS[position + 1][index] <= rank {
+            position += 1;
+        }
+        rank -= BINOMIALS[position][index];
+        tokens |= 1 << position;
+    }
+    tokens
+}
+
+/* numbers the positions that aren't occupied by own from 0 */
+fn compress(tokens: u32, own: u32) -> u32 {
+    positions(tokens).fold(0, |compressed, position| compressed | 1 << (position - (own & ((1 << position) - 1)).count_ones() as usize))
+}
+
+fn expand(compressed: u32, own: u32) -> u32 {
+    positions(ALL_POSITIONS & !own)
+        .enumerate()
+        .filter(|(index, _)| compressed & (1 << index) != 0)
+        .fold(0, |tokens, (_, position)| tokens | 1 << position)
+}
+
+fn get_index(own: u32, other: u32) -> usize {
+    let other_size = BINOMIALS[24 - own.count_ones() as usize][other.count_ones() as usize];
+    get_rank(own) * other_size + get_rank(compress(other, own))
+}
+
+fn get_position(index: usize, own_count: usize, other_count: usize) -> (u32, u32) {
+    let other_size = BINOMIALS[24 - own_count][other_count];
+    let own = from_rank(index / other_size, own_count);
+    (own, expand(from_rank(index % other_size, other_count), own))
+}
+
+/*
+    Endgame tablebases for the move phase. A table stores the value of
+    every position with the given numbers of tokens of the player to move
+    and of the other player, so the table 4v3 holds the positions in which
+    the player to move has 4 tokens and the other player 3.
+*/
+#[derive(Default)]
+pub struct Tablebase {
+    tables: Vec<Option<Vec<u8>>>
+}
+
+fn get_slot(own_count: usize, other_count: usize) -> usize {
+    own_count * (MAX_TOKENS + 1) + other_count
+}
+
+fn get_file_name(own_count: usize, other_count: usize) -> String {
+    format!("{}v{}.mtb", own_count, other_count)
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Tablebase { tables: vec![None; (MAX_TOKENS + 1) * (MAX_TOKENS + 1)] }
+    }
+
+    pub fn contains(&self, own_count: usize, other_count: usize) -> bool {
+        self.get_table(own_count, other_count).is_some()
+    }
+
+    fn get_table(&self, own_count: usize, other_count: usize) -> Option<&Vec<u8>> {
+        if own_count > MAX_TOKENS || other_count > MAX_TOKENS {
+            return None;
+        }
+        self.tables.get(get_slot(own_count, other_count))?.as_ref()
+    }
+
+    /* the signatures of all tables, as numbers of tokens of the player to move and the other player */
+    pub fn get_signatures(&self) -> Vec<(usize, usize)> {
+        (MIN_TOKENS..=MAX_TOKENS)
+            .flat_map(|own_count| (MIN_TOKENS..=MAX_TOKENS).map(move |other_count| (own_count, other_count)))
+            .filter(|(own_count, other_count)| self.contains(*own_count, *other_count))
+            .collect()
+    }
+
+    /*
+        The value of the position for the player, who has to move, or None
+        if there is no table for its number of tokens. The position must
+        be in the move phase, i.e. no tokens may be left to place.
+    */
+    pub fn probe(&self, board: Board, player: u8) -> Option<TablebaseValue> {
+        let (own, other) = get_tokens(board, player);
+        let table = self.get_table(own.count_ones() as usize, other.count_ones() as usize)?;
+        Some(TablebaseValue::decode(table[get_index(own, other)]))
+    }
+
+    /* positions with less than three tokens of the player to move are lost */
+    fn probe_tokens(&self, own: u32, other: u32) -> TablebaseValue {
+        if own.count_ones() < MIN_TOKENS as u32 {
+            return TablebaseValue::Loss(0);
+        }
+        let table = self.get_table(own.count_ones() as usize, other.count_ones() as usize)
+            .expect("missing table of a capture");
+        TablebaseValue::decode(table[get_index(own, other)])
+    }
+
+    pub fn get_statistics(&self, own_count: usize, other_count: usize) -> Option<TableStatistics> {
+        let table = self.get_table(own_count, other_count)?;
+        let mut statistics = TableStatistics { positions: table.len(), ..Default::default() };
+        for value in table.iter() {
+            match TablebaseValue::decode(*value) {
+                TablebaseValue::Win(plies) => {
+                    statistics.wins += 1;
+                    statistics.longest_win = statistics.longest_win.max(plies);
+                },
+                TablebaseValue::Loss(_) => statistics.losses += 1,
+                TablebaseValue::Draw => statistics.draws += 1
+            }
+        }
+        Some(statistics)
+    }
+
+    /*
+        Generates the table and the one with the numbers of tokens swapped,
+        since the positions of both follow each other, together with all
+        tables that are reached by captures.
+    */
+    pub fn generate(&mut self, own_count: usize, other_count: usize) {
+        assert!((MIN_TOKENS..=MAX_TOKENS).contains(&own_count) && (MIN_TOKENS..=MAX_TOKENS).contains(&other_count));
+        if self.contains(own_count, other_count) {
+            return;
+        }
+        if other_count > MIN_TOKENS {
+            self.generate(other_count - 1, own_count);
+        }
+        if own_count > MIN_TOKENS {
+            self.generate(own_count - 1, other_count);
+        }
+        self.generate_pair(own_count, other_count);
+    }
+
+    /*
+        Retrograde analysis: first every position counts its moves that
+        don't capture, which lead to positions of the same two tables, and
+        looks up the positions after captures in the smaller tables. Then
+        the positions are resolved by the number of plies until the end of
+        the game. A position is won in n plies if a move leads to a
+        position that is lost in n - 1 plies, which is found by going
+        backwards from the lost position. A position is lost in n plies
+        once all of its moves lead to won positions, the slowest of them in
+        n - 1 plies. Positions that are never resolved are draws.
+    */
+    fn generate_pair(&mut self, own_count: usize, other_count: usize) {
+        let mut signatures = vec![(own_count, other_count)];
+        if own_count != other_count {
+            signatures.push((other_count, own_count));
+        }
+        let second_offset = get_table_size(own_count, other_count);
+        let offset_of = |own: u32| if own.count_ones() as usize == own_count { 0 } else { second_offset };
+        let total_size = signatures.iter().map(|(own, other)| get_table_size(*own, *other)).sum();
+
+        /*
+            counts holds the moves to positions that aren't known to be won
+            for the other player, best the fastest win and worst the slowest
+            loss found so far, both in plies
+        */
+        let mut values = vec![0u8; total_size];
+        let mut counts = vec![0u8; total_size];
+        let mut best = vec![NO_WIN; total_size];
+        let mut worst = vec![0u8; total_size];
+
+        for (own_count, other_count) in signatures.iter().copied() {
+            let offset = if own_count == signatures[0].0 { 0 } else { second_offset };
+            let size = get_table_size(own_count, other_count);
+            counts[offset..offset + size].par_iter_mut()
+                .zip(best[offset..offset + size].par_iter_mut())
+                .zip(worst[offset..offset + size].par_iter_mut())
+                .enumerate()
+                .for_each(|(index, ((count, best), worst))| {
+                    let (own, other) = get_position(index, own_count, other_count);
+                    for_each_successor(own, other, |successor| {
+                        if !successor.capture {
+                            *count += 1;
+                            return;
+                        }
+                        match self.probe_tokens(successor.own, successor.other) {
+                            TablebaseValue::Loss(plies) => *best = (*best).min(plies.saturating_add(1)),
+                            TablebaseValue::Win(plies) => *worst = (*worst).max(plies.saturating_add(1)),
+                            TablebaseValue::Draw => *count += 1
+                        }
+                    });
+                });
+        }
+
+        let mut last_level = (0..total_size)
+            .map(|position| if best[position] != NO_WIN { best[position] } else if counts[position] == 0 { worst[position] } else { 0 })
+            .max()
+            .unwrap_or(0);
+
+        let mut level = 0;
+        while level <= last_level && level < NO_WIN - 1 {
+            for position in 0..total_size {
+                if values[position] != 0 {
+                    continue;
+                }
+                let won = best[position] == level;
+                let lost = best[position] == NO_WIN && counts[position] == 0 && worst[position] == level;
+                if !won && !lost {
+                    continue;
+                }
+                values[position] = level + 1;
+
+                let (own_count, other_count, index) = if position < second_offset {
+                    (signatures[0].0, signatures[0].1, position)
+                } else {
+                    (signatures[1].0, signatures[1].1, position - second_offset)
+                };
+                let (own, other) = get_position(index, own_count, other_count);
+                for_each_predecessor(own, other, |predecessor_own, predecessor_other| {
+                    let predecessor = offset_of(predecessor_own) + get_index(predecessor_own, predecessor_other);
+                    if values[predecessor] != 0 {
+                        return;
+                    }
+                    if won {
+                        counts[predecessor] -= 1;
+                        worst[predecessor] = worst[predecessor].max(level + 1);
+                        if counts[predecessor] == 0 && best[predecessor] == NO_WIN {
+                            last_level = last_level.max(worst[predecessor]);
+                        }
+                    } else {
+                        best[predecessor] = best[predecessor].min(level + 1);
+                        last_level = last_level.max(level + 1);
+                    }
+                });
+            }
+            level += 1;
+        }
+
+        let second = values.split_off(second_offset);
+        self.tables[get_slot(own_count, other_count)] = Some(values);
+        if own_count != other_count {
+            self.tables[get_slot(other_count, own_count)] = Some(second);
+        }
+    }
+
+    /*
+        Every table is stored in its own file, e.g. 4v3.mtb, with the magic
+        bytes MTB1, the two numbers of tokens and then one byte per index
+        with the number of plies plus one, or 0 for a draw.
+    */
+    pub fn save(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        for (own_count, other_count) in self.get_signatures() {
+            let table = self.get_table(own_count, other_count).expect("missing table");
+            let mut bytes = Vec::with_capacity(table.len() + 6);
+            bytes.extend_from_slice(MAGIC);
+            bytes.extend_from_slice(&[own_count as u8, other_count as u8]);
+            bytes.extend_from_slice(table);
+            fs::write(directory.join(get_file_name(own_count, other_count)), bytes)?;
+        }
+        Ok(())
+    }
+
+    /* loads all tables of the directory */
+    pub fn load(directory: &Path) -> io::Result<Self> {
+        if !directory.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no directory {}", directory.display())));
+        }
+        let mut tablebase = Tablebase::new();
+        for own_count in MIN_TOKENS..=MAX_TOKENS {
+            for other_count in MIN_TOKENS..=MAX_TOKENS {
+                let path = directory.join(get_file_name(own_count, other_count));
+                if !path.exists() {
+                    continue;
+                }
+                let bytes = fs::read(&path)?;
+                if bytes.len() != get_table_size(own_count, other_count) + 6
+                    || &bytes[..4] != MAGIC
+                    || bytes[4..6] != [own_count as u8, other_count as u8] {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid table {}", path.display())));
+                }
+                tablebase.tables[get_slot(own_count, other_count)] = Some(bytes[6..].to_vec());
+            }
+        }
+        Ok(tablebase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use crate::logic::{
+        action::list_actions,
+        game_state::{GameState, Phase, Token},
+        position::Board,
+        r#move::apply_action,
+        rules::RuleSet
+    };
+    use super::{
+        expand, compress, for_each_predecessor, for_each_successor, from_rank, get_index, get_position, get_rank,
+        get_table_size, get_tokens, Tablebase, TablebaseValue
+    };
+
+    /* a small xorshift, the positions only need to differ */
+    fn random_positions(own_count: usize, other_count: usize, count: usize) -> Vec<(u32, u32)> {
+        let mut state = 0x2545F4914F6CDD1Du64;
+        (0..count).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            get_position(state as usize % get_table_size(own_count, other_count), own_count, other_count)
+        }).collect()
+    }
+
+    fn to_board(own: u32, other: u32) -> Board {
+        let mut board = Board::default();
+        for position in 0..24 {
+            if own & (1 << position) != 0 {
+                board.set_token_at(position, 0b11);
+            } else if other & (1 << position) != 0 {
+                board.set_token_at(position, 0b10);
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn test_index() {
+        assert_eq!(get_table_size(3, 3), 2024 * 1330);
+        for tokens in [0b111, 0b1011, 0b111 << 21, 0b1000_0000_0100_0010_0001_0001] {
+            assert_eq!(from_rank(get_rank(tokens), tokens.count_ones() as usize), tokens);
+        }
+        assert_eq!(get_rank(0b111 << 21), 2023);
+        assert_eq!(compress(0b1100_0000, 0b0010_0001), 0b0011_0000);
+        assert_eq!(expand(0b0011_0000, 0b0010_0001), 0b1100_0000);
+
+        for (own_count, other_count) in [(3, 3), (4, 3), (3, 5)] {
+            for (own, other) in random_positions(own_count, other_count, 200) {
+                assert_eq!((own.count_ones() as usize, other.count_ones() as usize), (own_count, other_count));
+                assert_eq!(own & other, 0);
+                assert_eq!(get_position(get_index(own, other), own_count, other_count), (own, other));
+            }
+        }
+        assert_eq!(get_index(0b111, 0b111000), 0);
+        assert_eq!(get_index(0b111 << 21, 0b111 << 18), get_table_size(3, 3) - 1);
+    }
+
+    #[test]
+    fn test_successors_match_actions() {
+        for (own_count, other_count) in [(3, 3), (4, 3), (5, 4), (3, 6)] {
+            for (own, other) in random_positions(own_count, other_count, 100) {
+                let board = to_board(own, other);
+                let mut expected = list_actions(board, 0b11, Phase::Move, Some(own_count as u8), RuleSet::default())
+                    .map(|action| get_tokens(apply_action(board, &action, 0b11), 0b10))
+                    .collect::<Vec<(u32, u32)>>();
+                let mut successors = Vec::new();
+                for_each_successor(own, other, |successor| successors.push((successor.own, successor.other)));
+                expected.sort();
+                successors.sort();
+                assert_eq!(successors, expected, "{}", board);
+            }
+        }
+    }
+
+    #[test]
+    fn test_predecessors() {
+        for (own_count, other_count) in [(3, 3), (4, 3), (3, 4), (5, 5)] {
+            for (own, other) in random_positions(own_count, other_count, 100) {
+                let mut predecessors = Vec::new();
+                for_each_predecessor(own, other, |predecessor_own, predecessor_other| predecessors.push((predecessor_own, predecessor_other)));
+                for (predecessor_own, predecessor_other) in predecessors {
+                    let mut found = false;
+                    for_each_successor(predecessor_own, predecessor_other, |successor| {
+                        found |= !successor.capture && (successor.own, successor.other) == (own, other);
+                    });
+                    assert!(found);
+                }
+
+                for_each_successor(own, other, |successor| {
+                    if !successor.capture {
+                        let mut found = false;
+                        for_each_predecessor(successor.own, successor.other, |predecessor_own, predecessor_other| {
+                            found |= (predecessor_own, predecessor_other) == (own, other);
+                        });
+                        assert!(found);
+                    }
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(TablebaseValue::decode(0), TablebaseValue::Draw);
+        assert_eq!(TablebaseValue::decode(1), TablebaseValue::Loss(0));
+        assert_eq!(TablebaseValue::decode(2), TablebaseValue::Win(1));
+        assert_eq!(TablebaseValue::decode(5), TablebaseValue::Loss(4));
+        assert_eq!(Tablebase::new().probe(Board::default(), 0b11), None);
+    }
+
+    /*
+        Generates the 4v3 tables and checks random values against the values
+        after all actions. Takes a while without optimizations, run it
+        with cargo test --release -- --ignored.
+    */
+    #[test]
+    #[ignore]
+    fn test_generate() {
+        let mut tablebase = Tablebase::new();
+        tablebase.generate(4, 3);
+        assert_eq!(tablebase.get_signatures(), vec![(3, 3), (3, 4), (4, 3)]);
+
+        for (own_count, other_count) in tablebase.get_signatures() {
+            for (own, other) in random_positions(own_count, other_count, 2000) {
+                let board = to_board(own, other);
+                let values = list_actions(board, 0b11, Phase::Move, Some(own_count as u8), RuleSet::default())
+                    .map(|action| {
+                        let next = apply_action(board, &action, 0b11);
+                        if next.get_number_of_tokens(0b10) < 3 {
+                            TablebaseValue::Loss(0)
+                        } else {
+                            tablebase.probe(next, 0b10).unwrap()
+                        }
+                    })
+                    .collect::<Vec<TablebaseValue>>();
+                let fastest_win = values.iter().filter_map(|value| match value {
+                    TablebaseValue::Loss(plies) => Some(plies + 1),
+                    _ => None
+                }).min();
+                let expected = if let Some(plies) = fastest_win {
+                    TablebaseValue::Win(plies)
+                } else if values.iter().all(|value| matches!(value, TablebaseValue::Win(_))) {
+                    TablebaseValue::Loss(values.iter().map(|value| match value {
+                        TablebaseValue::Win(plies) => plies + 1,
+                        _ => 0
+                    }).max().unwrap_or(0))
+                } else {
+                    TablebaseValue::Draw
+                };
+                assert_eq!(tablebase.probe(board, 0b11), Some(expected), "{}", board);
+                assert_eq!(tablebase.probe(board.reverse_tokens(), 0b10), Some(expected));
+            }
+        }
+
+        /* white flies to 7, closes the mill 7 0 1 and wins */
+        let game_state: GameState = "WWEEEWEEBEBEEEEEEEEEEEEB w 0 0 30 0".parse().unwrap();
+        assert_eq!(tablebase.probe(game_state.get_board(), Token::parse_to_u8(Token::White)), Some(TablebaseValue::Win(1)));
+
+        let directory = env::temp_dir().join(format!("muehle-tablebase-{}", std::process::id()));
+        tablebase.save(&directory).unwrap();
+        let loaded = Tablebase::load(&directory).unwrap();
+        assert_eq!(loaded.get_signatures(), tablebase.get_signatures());
+        assert_eq!(loaded.get_statistics(4, 3), tablebase.get_statistics(4, 3));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,17 @@
+/*
+    std::time is not available on wasm32-unknown-unknown, so the browser
+    build keeps using the clock of miniquad. Everywhere else the system
+    clock is used, which keeps the engine usable without the ui feature.
+*/
+#[cfg(all(target_arch = "wasm32", feature = "ui"))]
+pub fn time() -> f64 {
+    ggez::timer::time()
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "ui")))]
+pub fn time() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
@@ -0,0 +1,326 @@
+use std::{fmt, sync::Arc};
+use rayon::ThreadPool;
+use crate::logic::{
+    game_record::{GameRecord, Winner},
+    game_state::{GameState, Token}
+};
+use super::{
+    context::{SearchContext, SearchLimits},
+    evaluation::EvaluationWeights,
+    search,
+    transposition_table::{TranspositionTable, DEFAULT_HASH_SIZE},
+    tuning::MAX_GAME_PLIES,
+    AiPhase
+};
+
+/*
+    The start position and positions after one placement of each player,
+    so the games of a match don't all follow the same line.
+*/
+pub const DEFAULT_OPENINGS: [&str; 8] = [
+    "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0",
+    "WEEEEEEEBEEEEEEEEEEEEEEE w 8 8 2 0",
+    "EWEEEEEEEBEEEEEEEEEEEEEE w 8 8 2 0",
+    "EEEEWEEEEEEEBEEEEEEEEEEE w 8 8 2 0",
+    "EEEEEEEEEWEEEEEEEBEEEEEE w 8 8 2 0",
+    "EWBEEEEEEEEEEEEEEEEEEEEE w 8 8 2 0",
+    "EEEEEEEEWEEEEEEEBEEEEEEE w 8 8 2 0",
+    "EEEBWEEEEEEEEEEEEEEEEEEE w 8 8 2 0"
+];
+
+/*
+    The settings of one engine of a match. Every game starts with an empty
+    transposition table, which is kept between the moves of the game.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EngineConfig {
+    pub limits: SearchLimits,
+    pub weights: EvaluationWeights,
+    pub threads: usize,
+    pub hash_size: usize
+}
+
+impl EngineConfig {
+    pub fn new(limits: SearchLimits) -> Self {
+        EngineConfig {
+            limits,
+            weights: EvaluationWeights::default(),
+            threads: 1,
+            hash_size: DEFAULT_HASH_SIZE
+        }
+    }
+}
+
+/*
+    Plays one game from the opening. Games that are still running after
+    MAX_GAME_PLIES plies are a draw. The thread pool of the searches is
+    kept in thread_pool, so the following games can use it as well.
+*/
+pub fn play_game(white: &EngineConfig, black: &EngineConfig, opening: GameState, thread_pool: &mut Option<Arc<ThreadPool>>) -> Winner {
+    let tables = [white, black].map(|engine| Arc::new(TranspositionTable::new(engine.hash_size)));
+    let mut record = GameRecord::new(opening);
+
+    while record.get_winner().is_none() {
+        let game_state = record.get_game_state();
+        if game_state.get_step_counter() >= MAX_GAME_PLIES {
+            return Winner::Draw("Maximum number of plies reached".to_string());
+        }
+
+        let (engine, table) = match game_state.get_player_turn() {
+            Token::White => (white, &tables[0]),
+            _ => (black, &tables[1])
+        };
+        let context = SearchContext::with_table(engine.limits, Arc::clone(table))
+            .with_weights(engine.weights)
+            .with_threads(engine.threads)
+            .with_thread_pool(thread_pool.take());
+        *thread_pool = context.get_thread_pool();
+        let result = search(
+            game_state.get_board(),
+            game_state.get_player_turn(),
+            AiPhase::from_game_state(&game_state),
+            &context,
+            |_| {}
+        );
+        match result {
+            Some(result) => record.apply_action(result.action),
+            None => return Winner::Draw("No legal action".to_string())
+        }
+    }
+    record.get_winner().cloned().expect("game without winner")
+}
+
+/* wins, draws and losses from the view of the first engine */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize
+}
+
+impl MatchResult {
+    pub fn get_games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /* the average points per game, a draw counts half */
+    pub fn get_score(&self) -> f64 {
+        get_statistics(self.wins as f64, self.draws as f64, self.losses as f64).0
+    }
+
+    /* the Elo difference of the first engine, infinite if it won or lost every game */
+    pub fn get_elo(&self) -> f64 {
+        score_to_elo(self.get_score())
+    }
+
+    /* half the width of the 95% confidence interval of the Elo difference */
+    pub fn get_elo_error(&self) -> f64 {
+        let (score, variance) = get_statistics(self.wins as f64, self.draws as f64, self.losses as f64);
+        if self.get_games() == 0 || score <= 0.0 || score >= 1.0 {
+            return f64::INFINITY;
+        }
+        let error = 1.96 * (variance / self.get_games().max(1) as f64).sqrt();
+        (score_to_elo((score + error).min(1.0)) - score_to_elo((score - error).max(0.0))) / 2.0
+    }
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "+{} ={} -{}, score {:.1}%, Elo {:.1} +- {:.1}",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.get_score() * 100.0,
+            self.get_elo(),
+            self.get_elo_error()
+        )
+    }
+}
+
+/* the average points and the variance of the points of one game */
+fn get_statistics(wins: f64, draws: f64, losses: f64) -> (f64, f64) {
+    let games = (wins + draws + losses).max(1.0);
+    let score = (wins + draws / 2.0) / games;
+    let variance = (wins * (1.0 - score).powi(2) + draws * (0.5 - score).powi(2) + losses * score.powi(2)) / games;
+    (score, variance)
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10.0_f64.powf(-elo / 400.0))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SprtDecision {
+    Continue,
+    AcceptH0,
+    AcceptH1
+}
+
+/*
+    Sequential probability ratio test whether the first engine is elo0
+    (H0) or elo1 (H1) Elo stronger than the second one, with the error
+    probabilities alpha and beta. The log-likelihood ratio uses the normal
+    approximation of the points of a game, so it works with draws.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    pub fn get_bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /* half a game of every result is added, so the variance is never zero */
+    pub fn get_llr(&self, result: &MatchResult) -> f64 {
+        let (wins, draws, losses) = (result.wins as f64 + 0.5, result.draws as f64 + 0.5, result.losses as f64 + 0.5);
+        let (score, variance) = get_statistics(wins, draws, losses);
+        let (score0, score1) = (elo_to_score(self.elo0), elo_to_score(self.elo1));
+        (wins + draws + losses) * (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance)
+    }
+
+    pub fn get_decision(&self, result: &MatchResult) -> SprtDecision {
+        let llr = self.get_llr(result);
+        let (lower, upper) = self.get_bounds();
+        if llr <= lower {
+            SprtDecision::AcceptH0
+        } else if llr >= upper {
+            SprtDecision::AcceptH1
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+/*
+    Plays up to the given number of games between the two engines. Each
+    opening is played twice in a row, once with every engine as white,
+    and the openings are repeated if there are more games than openings.
+    With an SPRT the match ends as soon as it decides. Every game is
+    reported with on_game.
+*/
+pub fn run_match(
+    first: &EngineConfig,
+    second: &EngineConfig,
+    openings: &[GameState],
+    games: usize,
+    sprt: Option<Sprt>,
+    mut on_game: impl FnMut(usize, &Winner, &MatchResult)
+) -> MatchResult {
+    let mut result = MatchResult::default();
+    if openings.is_empty() {
+        return result;
+    }
+    let mut thread_pool = None;
+
+    for game in 0..games {
+        let opening = openings[(game / 2) % openings.len()];
+        let first_is_white = game % 2 == 0;
+        let winner = if first_is_white {
+            play_game(first, second, opening, &mut thread_pool)
+        } else {
+            play_game(second, first, opening, &mut thread_pool)
+        };
+
+        match (&winner, first_is_white) {
+            (Winner::Draw(_), _) => result.draws += 1,
+            (Winner::White(_), true) | (Winner::Black(_), false) => result.wins += 1,
+            _ => result.losses += 1
+        }
+        on_game(game, &winner, &result);
+
+        if sprt.is_some_and(|sprt| sprt.get_decision(&result) != SprtDecision::Continue) {
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agent::context::SearchLimits,
+        logic::{game_record::Winner, game_state::GameState}
+    };
+    use super::{play_game, run_match, EngineConfig, MatchResult, Sprt, SprtDecision, DEFAULT_OPENINGS};
+
+    #[test]
+    fn test_openings() {
+        for opening in DEFAULT_OPENINGS {
+            assert!(opening.parse::<GameState>().is_ok(), "{}", opening);
+        }
+    }
+
+    #[test]
+    fn test_elo() {
+        let even = MatchResult { wins: 10, draws: 20, losses: 10 };
+        assert_eq!(even.get_games(), 40);
+        assert_eq!(even.get_score(), 0.5);
+        assert_eq!(even.get_elo(), 0.0);
+
+        let result = MatchResult { wins: 60, draws: 20, losses: 20 };
+        assert!((result.get_score() - 0.7).abs() < 1e-9);
+        assert!((result.get_elo() - 147.19).abs() < 0.01);
+        assert!(result.get_elo_error() > 0.0 && result.get_elo_error() < result.get_elo());
+        assert!(MatchResult { wins: 600, draws: 200, losses: 200 }.get_elo_error() < result.get_elo_error());
+
+        assert_eq!(MatchResult { wins: 3, draws: 0, losses: 0 }.get_elo(), f64::INFINITY);
+        assert_eq!(MatchResult { wins: 3, draws: 0, losses: 0 }.get_elo_error(), f64::INFINITY);
+        assert_eq!(MatchResult { wins: 1, draws: 1, losses: 1 }.to_string(), "+1 =1 -1, score 50.0%, Elo 0.0 +- 561.2");
+    }
+
+    #[test]
+    fn test_sprt() {
+        let sprt = Sprt::new(0.0, 20.0);
+        let (lower, upper) = sprt.get_bounds();
+        assert!((lower + 2.944).abs() < 0.001 && (upper - 2.944).abs() < 0.001);
+
+        assert_eq!(sprt.get_decision(&MatchResult { wins: 6, draws: 2, losses: 4 }), SprtDecision::Continue);
+        assert_eq!(sprt.get_decision(&MatchResult { wins: 400, draws: 200, losses: 250 }), SprtDecision::AcceptH1);
+        assert_eq!(sprt.get_decision(&MatchResult { wins: 600, draws: 400, losses: 600 }), SprtDecision::AcceptH0);
+        assert_eq!(sprt.get_decision(&MatchResult { wins: 1, draws: 0, losses: 0 }), SprtDecision::Continue);
+        assert_eq!(sprt.get_decision(&MatchResult { wins: 0, draws: 0, losses: 30 }), SprtDecision::AcceptH0);
+    }
+
+    #[test]
+    fn test_play_game() {
+        let engine = EngineConfig::new(SearchLimits::depth(1));
+        let opening = DEFAULT_OPENINGS[1].parse().unwrap();
+        assert_eq!(play_game(&engine, &engine, opening, &mut None), play_game(&engine, &engine, opening, &mut None));
+
+        /* black has only two tokens left, so the game is already over */
+        let lost = "WWWEEEEEEEEEEEEEEEEEEBBE b 0 0 30 0".parse::<GameState>().unwrap();
+        assert!(lost.is_game_over());
+        assert_eq!(play_game(&engine, &engine, lost, &mut None), Winner::White("".to_string()));
+    }
+
+    #[test]
+    fn test_run_match() {
+        let engine = EngineConfig::new(SearchLimits::depth(1));
+        let openings = [DEFAULT_OPENINGS[0].parse().unwrap()];
+        let mut games = Vec::new();
+        let result = run_match(&engine, &engine, &openings, 2, None, |game, winner, _| games.push((game, winner.clone())));
+        assert_eq!(result.get_games(), 2);
+        assert_eq!(games.len(), 2);
+        /* the same engine with the same opening plays the same game with both colours */
+        assert_eq!(result.wins, result.losses);
+
+        let sprt = Sprt { elo0: 0.0, elo1: 1000.0, alpha: 0.4, beta: 0.4 };
+        assert!(run_match(&engine, &EngineConfig::new(SearchLimits::depth(3)), &openings, 20, Some(sprt), |_, _, _| ()).get_games() < 20);
+        assert_eq!(run_match(&engine, &engine, &[], 4, None, |_, _, _| ()), MatchResult::default());
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::logic::{game_state::Phase, move_token_count::SearchBoard};
+use super::AiPhase;
+
+pub const DEFAULT_HASH_SIZE: usize = 16;
+
+/* won and lost scores are this close to the maximum, since they contain the step counter */
+pub const WIN_THRESHOLD: isize = 1 << 20;
+const NO_MOVE: u64 = 0b1111111111;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper
+}
+
+/*
+    The best move is stored as index of the forward board in the order of
+    forward_step_boards, which identifies it for the position of the key.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableEntry {
+    pub score: isize,
+    pub depth: usize,
+    pub bound: Bound,
+    pub best_move: Option<usize>
+}
+
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64
+}
+
+/*
+    A lockless hash table shared by all threads of the search. Every slot
+    stores the key xor the data next to the data, so an entry that was
+    written by two threads at the same time doesn't match the key anymore
+    and is ignored.
+
+    The key consists of the 48 bits of the board, the player to move and
+    the step counter during the set phase, which determines the tokens in
+    hand. The data is packed into a u64:
+        32 bits score
+        8 bits depth
+        2 bits bound (0 for an empty slot)
+        10 bits best move
+*/
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: u64
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        /* the number of slots is the largest power of two that fits into the size */
+        let max_slots = size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<Slot>();
+        let slot_count = 1 << (usize::BITS - 1 - max_slots.leading_zeros());
+        TranspositionTable {
+            slots: (0..slot_count).map(|_| Slot::default()).collect(),
+            mask: slot_count as u64 - 1
+        }
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, key: u64, step_counter: u16) -> Option<TableEntry> {
+        let slot = &self.slots[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+        if slot.key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        let mut entry = unpack(data)?;
+        entry.score = from_table_score(entry.score, step_counter);
+        Some(entry)
+    }
+
+    /*
+        Entries of other positions are always replaced, entries of the same
+        position only by a search with at least the same depth.
+    */
+    pub fn store(&self, key: u64, step_counter: u16, mut entry: TableEntry) {
+        let slot = &self.slots[self.index(key)];
+        let old_data = slot.data.load(Ordering::Relaxed);
+        if slot.key.load(Ordering::Relaxed) ^ old_data == key {
+            if let Some(old_entry) = unpack(old_data) {
+                if old_entry.depth > entry.depth {
+                    return;
+                }
+            }
+        }
+
+        entry.score = to_table_score(entry.score, step_counter);
+        let data = pack(entry);
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key.wrapping_mul(0x9E3779B97F4A7C15).rotate_left(32) & self.mask) as usize
+    }
+}
+
+/*
+    The key holds the board, the player to move, the plies of the set
+    phase played so far and the rules including the moves of the no
+    capture draw, so the table can be shared by games with different
+    rules. With blocked players drawing at their first move the first
+    plies of the move phase get their own keys.
+*/
+pub fn get_key(board: SearchBoard, player: u8, phase: AiPhase) -> u64 {
+    let set_plies = phase.rules.get_set_plies();
+    let set_steps = if phase.phase == Phase::Set { phase.step_counter.min(set_plies as u16) as u8 } else { set_plies };
+    let key = board.board().to_raw()
+        | ((player == 0b11) as u64) << 48
+        | (set_steps as u64) << 49
+        | ((phase.phase == Phase::Move && phase.rules.is_blocked_draw(phase.step_counter)) as u64) << 61;
+    key ^ phase.rules.get_key()
+}
+
+/*
+    Won and lost scores contain the step counter at the end of the game.
+    The table stores them relative to the step counter of the position, so
+    they stay correct if the position is reached after a different number
+    of steps.
+*/
+fn to_table_score(score: isize, step_counter: u16) -> isize {
+    if score > isize::MAX - WIN_THRESHOLD {
+        score + step_counter as isize
+    } else if score < isize::MIN + WIN_THRESHOLD {
+        score - step_counter as isize
+    } else {
+        score
+    }
+}
+
+fn from_table_score(score: isize, step_counter: u16) -> isize {
+    if score > isize::MAX - WIN_THRESHOLD {
+        score - step_counter as isize
+    } else if score < isize::MIN + WIN_THRESHOLD {
+        score + step_counter as isize
+    } else {
+        score
+    }
+}
+
+fn pack(entry: TableEntry) -> u64 {
+    let score = if entry.score > isize::MAX - WIN_THRESHOLD {
+        i32::MAX - (isize::MAX - entry.score) as i32
+    } else if entry.score < isize::MIN + WIN_THRESHOLD {
+        i32::MIN + (entry.score - isize::MIN) as i32
+    } else {
+        entry.score.clamp(i32::MIN as isize + WIN_THRESHOLD, i32::MAX as isize - WIN_THRESHOLD) as i32
+    };
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3
+    };
+    let best_move = entry.best_move.map_or(NO_MOVE, |best_move| (best_move as u64).min(NO_MOVE - 1));
+
+    (score as u32 as u64) | (entry.depth.min(255) as u64) << 32 | bound << 40 | best_move << 42
+}
+
+fn unpack(data: u64) -> Option<TableEntry> {
+    let bound = match (data >> 40) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None
+    };
+    let score = data as u32 as i32;
+    let score = if score > i32::MAX - WIN_THRESHOLD as i32 {
+        isize::MAX - (i32::MAX - score) as isize
+    } else if score < i32::MIN + WIN_THRESHOLD as i32 {
+        isize::MIN + (score - i32::MIN) as isize
+    } else {
+        score as isize
+    };
+    let best_move = (data >> 42) & NO_MOVE;
+
+    Some(TableEntry {
+        score,
+        depth: ((data >> 32) & 0xFF) as usize,
+        bound,
+        best_move: if best_move == NO_MOVE { None } else { Some(best_move as usize) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{agent::AiPhase, logic::{game_state::Phase, move_token_count::SearchBoard, position::decode_positions, rules::{RuleSet, Variant}}};
+    use super::{get_key, Bound, TableEntry, TranspositionTable};
+
+    fn entry(score: isize, depth: usize, bound: Bound, best_move: Option<usize>) -> TableEntry {
+        TableEntry { score, depth, bound, best_move }
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(TranspositionTable::new(1).get_size(), 65536);
+        assert_eq!(TranspositionTable::new(16).get_size(), 1048576);
+        assert_eq!(TranspositionTable::new(0).get_size(), 65536);
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let table = TranspositionTable::new(1);
+        assert_eq!(table.probe(42, 20), None);
+
+        table.store(42, 20, entry(-17, 3, Bound::Lower, Some(5)));
+        assert_eq!(table.probe(42, 20), Some(entry(-17, 3, Bound::Lower, Some(5))));
+        assert_eq!(table.probe(43, 20), None);
+
+        table.store(42, 20, entry(8, 2, Bound::Exact, None));
+        assert_eq!(table.probe(42, 20), Some(entry(-17, 3, Bound::Lower, Some(5))));
+        table.store(42, 20, entry(8, 4, Bound::Upper, None));
+        assert_eq!(table.probe(42, 20), Some(entry(8, 4, Bound::Upper, None)));
+
+        table.clear();
+        assert_eq!(table.probe(42, 20), None);
+    }
+
+    #[test]
+    fn test_empty_slot_does_not_match_zero_key() {
+        let table = TranspositionTable::new(1);
+        assert_eq!(table.probe(0, 0), None);
+    }
+
+    #[test]
+    fn test_win_scores_are_relative() {
+        let table = TranspositionTable::new(1);
+        table.store(7, 20, entry(isize::MAX - 25, 6, Bound::Exact, None));
+        assert_eq!(table.probe(7, 20).unwrap().score, isize::MAX - 25);
+        assert_eq!(table.probe(7, 30).unwrap().score, isize::MAX - 35);
+
+        table.store(7, 20, entry(isize::MIN + 23, 6, Bound::Exact, None));
+        assert_eq!(table.probe(7, 24).unwrap().score, isize::MIN + 27);
+    }
+
+    #[test]
+    fn test_key() {
+        let board = SearchBoard::new(decode_positions("WBEEEEEEEEEEEEEEEEEEEEEE".to_string()).unwrap(), RuleSet::default());
+        let move_phase = get_key(board, 0b11, AiPhase::new(Phase::Move, 20));
+
+        assert_eq!(move_phase, get_key(board, 0b11, AiPhase::new(Phase::Move, 40)));
+        assert_ne!(move_phase, get_key(board, 0b10, AiPhase::new(Phase::Move, 20)));
+        assert_ne!(get_key(board, 0b11, AiPhase::new(Phase::Set, 2)), get_key(board, 0b11, AiPhase::new(Phase::Set, 4)));
+        assert_eq!(move_phase & 0xFFFFFFFFFFFF, board.board().to_raw());
+
+        let twelve = RuleSet::new(Variant::TwelveMensMorris);
+        assert_ne!(move_phase, get_key(board, 0b11, AiPhase::new(Phase::Move, 20).with_rules(twelve)));
+        assert_ne!(get_key(board, 0b11, AiPhase::new(Phase::Set, 20).with_rules(twelve)), get_key(board, 0b11, AiPhase::new(Phase::Set, 22).with_rules(twelve)));
+
+        let draw = |moves| get_key(board, 0b11, AiPhase::new(Phase::Move, 20).with_rules(RuleSet::default().with_no_capture_draw(Some(moves))));
+        assert_ne!(move_phase, draw(50));
+        assert_ne!(draw(50), draw(60));
+    }
+}
@@ -0,0 +1,300 @@
+use std::{error::Error, fmt, str::FromStr, sync::Arc};
+use rayon::{prelude::*, ThreadPool};
+use crate::logic::{
+    action::{list_actions, Action},
+    game_record::{GameRecord, Winner},
+    game_state::{GameState, Token},
+    move_token_count::SearchBoard,
+    position::PositionError
+};
+use super::{
+    context::SearchContext,
+    evaluation::{get_phase_index, get_terms, EvaluationWeights, PhaseWeights},
+    search,
+    tournament::EngineConfig,
+    transposition_table::TranspositionTable,
+    AiPhase,
+    Random
+};
+
+/* self-play and match games that are still running after this many plies are scored as a draw */
+pub const MAX_GAME_PLIES: u16 = 200;
+
+/*
+    A position of a self-play game together with the result of the game
+    from the view of white: 1 if white won, 0 if black won and 0.5 for a
+    draw. It is written as the position string followed by the result:
+        BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3 1-0
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrainingPosition {
+    pub game_state: GameState,
+    pub result: f64
+}
+
+impl fmt::Display for TrainingPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = if self.result > 0.75 {
+            "1-0"
+        } else if self.result < 0.25 {
+            "0-1"
+        } else {
+            "1/2-1/2"
+        };
+        write!(f, "{} {}", self.game_state, result)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TrainingPositionError {
+    InvalidPosition(PositionError),
+    InvalidResult(String)
+}
+
+impl fmt::Display for TrainingPositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrainingPositionError::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+            TrainingPositionError::InvalidResult(result) => write!(f, "invalid result '{}'", result)
+        }
+    }
+}
+
+impl Error for TrainingPositionError {}
+
+impl FromStr for TrainingPosition {
+    type Err = TrainingPositionError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (position, result) = line.trim().rsplit_once(' ').unwrap_or(("", line.trim()));
+        let result = match result {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            _ => return Err(TrainingPositionError::InvalidResult(result.to_string()))
+        };
+        let game_state = position.parse().map_err(TrainingPositionError::InvalidPosition)?;
+        Ok(TrainingPosition { game_state, result })
+    }
+}
+
+/*
+    Plays a game of the engine against itself. The first random_plies
+    plies are random legal actions chosen with the seed, so different
+    seeds lead to different games, and with one thread the same seed
+    always leads to the same game. The thread pool of the searches is
+    kept in thread_pool for the following games. Returns the quiet
+    positions the engine played in, i.e. the ones in which it didn't
+    capture a token, labelled with the result of the game.
+*/
+pub fn self_play_game(engine: &EngineConfig, random_plies: usize, seed: u64, thread_pool: &mut Option<Arc<ThreadPool>>) -> Vec<TrainingPosition> {
+    let table = Arc::new(TranspositionTable::new(engine.hash_size));
+    let mut random = Random::new(seed);
+    let mut record = GameRecord::new(GameState::default());
+    let mut positions = Vec::new();
+
+    while record.get_winner().is_none() && record.get_game_state().get_step_counter() < MAX_GAME_PLIES {
+        let game_state = record.get_game_state();
+        let player = game_state.get_player_turn();
+        let actions = list_actions(game_state.get_board(), Token::parse_to_u8(player), game_state.get_phase(), None, game_state.get_rules())
+            .collect::<Vec<Action>>();
+        if actions.is_empty() {
+            break;
+        }
+
+        let action = if (game_state.get_step_counter() as usize) < random_plies {
+            actions[random.next_index(actions.len())]
+        } else {
+            let context = SearchContext::with_table(engine.limits, Arc::clone(&table))
+                .with_weights(engine.weights)
+                .with_threads(engine.threads)
+                .with_thread_pool(thread_pool.take());
+            *thread_pool = context.get_thread_pool();
+            let action = search(game_state.get_board(), player, AiPhase::from_game_state(&game_state), &context, |_| {})
+                .expect("no legal action")
+                .action;
+            if action.beatable_position.is_none() {
+                positions.push(game_state);
+            }
+            action
+        };
+        record.apply_action(action);
+    }
+
+    let result = match record.get_winner() {
+        Some(Winner::White(_)) => 1.0,
+        Some(Winner::Black(_)) => 0.0,
+        _ => 0.5
+    };
+    positions.into_iter().map(|game_state| TrainingPosition { game_state, result }).collect()
+}
+
+struct TuningEntry {
+    phase: usize,
+    terms: [isize; PhaseWeights::TERMS],
+    result: f64
+}
+
+/*
+    Texel tuning: the evaluation is mapped to an expected result with
+    sigmoid(scaling * evaluation) and the weights are changed one at a time
+    by one, as long as that lowers the mean squared error between the
+    expected results and the results of the games. The scaling is fitted
+    to the initial weights once, so it keeps the weights in their range.
+*/
+pub struct Tuner {
+    entries: Vec<TuningEntry>,
+    weights: [[isize; PhaseWeights::TERMS]; 3],
+    scaling: f64,
+    error: f64
+}
+
+impl Tuner {
+    pub fn new(positions: &[TrainingPosition], weights: EvaluationWeights) -> Self {
+        let entries = positions.iter()
+            .filter(|position| !position.game_state.is_game_over())
+            .map(|position| {
+                let board = SearchBoard::new(position.game_state.get_board(), position.game_state.get_rules());
+                let phase = AiPhase::from_game_state(&position.game_state);
+                TuningEntry { phase: get_phase_index(board, phase), terms: get_terms(board, phase), result: position.result }
+            })
+            .collect();
+        let mut tuner = Tuner {
+            entries,
+            weights: weights.to_array().map(PhaseWeights::to_array),
+            scaling: 0.0,
+            error: 0.0
+        };
+        tuner.scaling = tuner.fit_scaling();
+        tuner.error = tuner.compute_error(&tuner.weights);
+        tuner
+    }
+
+    pub fn get_weights(&self) -> EvaluationWeights {
+        EvaluationWeights::from_array(self.weights.map(PhaseWeights::from_array))
+    }
+
+    pub fn get_scaling(&self) -> f64 {
+        self.scaling
+    }
+
+    pub fn get_error(&self) -> f64 {
+        self.error
+    }
+
+    pub fn get_position_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /* one pass over all weights, returns false once no weight changed */
+    pub fn step(&mut self) -> bool {
+        let mut improved = false;
+        for phase in 0..3 {
+            for term in 0..PhaseWeights::TERMS {
+                for delta in [1, -1] {
+                    let mut weights = self.weights;
+                    weights[phase][term] += delta;
+                    let error = self.compute_error(&weights);
+                    if error < self.error {
+                        self.weights = weights;
+                        self.error = error;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+        }
+        improved
+    }
+
+    fn compute_error_with_scaling(&self, weights: &[[isize; PhaseWeights::TERMS]; 3], scaling: f64) -> f64 {
+        let error = self.entries.par_iter()
+            .map(|entry| {
+                let evaluation = entry.terms.iter()
+                    .zip(weights[entry.phase])
+                    .map(|(term, weight)| term * weight)
+                    .sum::<isize>();
+                let expected = 1.0 / (1.0 + (-scaling * evaluation as f64).exp());
+                (entry.result - expected).powi(2)
+            })
+            .sum::<f64>();
+        error / self.entries.len().max(1) as f64
+    }
+
+    fn compute_error(&self, weights: &[[isize; PhaseWeights::TERMS]; 3]) -> f64 {
+        self.compute_error_with_scaling(weights, self.scaling)
+    }
+
+    /* golden section search of the scaling between 10^-4 and 1 on a log scale */
+    fn fit_scaling(&self) -> f64 {
+        let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+        let error = |exponent: f64| self.compute_error_with_scaling(&self.weights, 10.0_f64.powf(exponent));
+        let (mut low, mut high) = (-4.0, 0.0);
+        for _ in 0..40 {
+            let left = high - ratio * (high - low);
+            let right = low + ratio * (high - low);
+            if error(left) < error(right) {
+                high = right;
+            } else {
+                low = left;
+            }
+        }
+        10.0_f64.powf((low + high) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agent::{context::SearchLimits, evaluation::EvaluationWeights, tournament::EngineConfig},
+        logic::position::PositionError
+    };
+    use super::{self_play_game, Tuner, TrainingPosition, TrainingPositionError, MAX_GAME_PLIES};
+
+    #[test]
+    fn test_parse_training_position() {
+        let position = "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3 1-0".parse::<TrainingPosition>().unwrap();
+        assert_eq!(position.result, 1.0);
+        assert_eq!(position.game_state.to_string(), "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3");
+        assert_eq!(position.to_string(), "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3 1-0");
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 1/2-1/2".parse::<TrainingPosition>().map(|position| position.result), Ok(0.5));
+
+        assert_eq!(
+            "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 2-0".parse::<TrainingPosition>(),
+            Err(TrainingPositionError::InvalidResult("2-0".to_string()))
+        );
+        assert_eq!(
+            "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0-1".parse::<TrainingPosition>(),
+            Err(TrainingPositionError::InvalidPosition(PositionError::MissingField("reversible plies")))
+        );
+    }
+
+    #[test]
+    fn test_self_play_game() {
+        let engine = EngineConfig::new(SearchLimits::depth(1));
+        let positions = self_play_game(&engine, 6, 7, &mut None);
+        assert!(!positions.is_empty());
+        assert!(positions.iter().all(|position| position.game_state.get_step_counter() >= 6 && position.game_state.get_step_counter() < MAX_GAME_PLIES));
+        assert!(positions.iter().all(|position| position.result == positions[0].result));
+        assert_eq!(self_play_game(&engine, 6, 7, &mut None), positions);
+    }
+
+    #[test]
+    fn test_tuner() {
+        let positions = (0..2)
+            .flat_map(|seed| self_play_game(&EngineConfig::new(SearchLimits::depth(1)), 8, seed, &mut None))
+            .collect::<Vec<TrainingPosition>>();
+        let mut tuner = Tuner::new(&positions, EvaluationWeights::default());
+        assert!(tuner.get_position_count() > 0);
+        assert!(tuner.get_scaling() > 0.0);
+
+        let error = tuner.get_error();
+        let mut passes = 0;
+        while passes < 5 && tuner.step() {
+            assert!(tuner.get_error() < error);
+            passes += 1;
+        }
+        assert!(tuner.get_error() <= error);
+        assert_eq!(tuner.get_weights() == EvaluationWeights::default(), passes == 0);
+    }
+}
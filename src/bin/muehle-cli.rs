@@ -0,0 +1,622 @@
+use std::{env, fs, io::{self, BufRead, Write}, path::Path, process, sync::Arc};
+use rayon::ThreadPool;
+use muehle::{
+    agent::{
+        book::OpeningBook,
+        context::{SearchContext, SearchLimits},
+        evaluation::EvaluationWeights,
+        search,
+        SearchResult,
+        tablebase::{Tablebase, MAX_TOKENS, MIN_TOKENS},
+        transposition_table::{TranspositionTable, DEFAULT_HASH_SIZE},
+        tournament::{run_match, EngineConfig, Sprt, SprtDecision, DEFAULT_OPENINGS},
+        tuning::{self_play_game, TrainingPosition, Tuner},
+        AiPhase
+    },
+    logic::{
+        action::{list_actions, Action},
+        game_file::read_game,
+        game_record::{GameRecord, Winner},
+        game_state::{GameState, Token},
+        perft::{perft, perft_divide},
+        position::print_board,
+        rules::{RuleSet, Variant}
+    }
+};
+
+const USAGE: &str = "\
+Usage: muehle-cli <command> [options]
+
+Commands:
+    play                 play a game in the terminal
+    bestmove             print the best move of the engine
+    moves                list all legal moves
+    perft <depth>        count the leaf nodes of the game tree
+    bench                compare the move ordering heuristics on a fixed set of positions
+    selfplay <games>     print the positions of engine self-play games with their results
+    tune <file>          tune the evaluation weights on the positions of selfplay
+    match <games>        play games between two engines and estimate their Elo difference
+    tablebase <w>v<b>... generate the endgame tables, e.g. 3v3 or 4v3
+    book <plies> [files] print an opening book of searches and game files
+
+Options:
+    --position <fen>     start position, e.g. \"EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0\"
+    --variant <name>     start from the empty board of nine, twelve, six or three men's morris
+    --rules <rules>      start from the empty board of the rules, e.g. nine+doublecapture+draw50
+    --depth <plies>      maximum search depth of the engine (bench default 6)
+    --time <seconds>     maximum search time of the engine (default 3)
+    --nodes <count>      maximum number of nodes the engine searches
+    --hash <mb>          size of the transposition table (default 16)
+    --threads <count>    number of search threads (default one per core, 1 for bench, selfplay and match)
+    --weights <file>     evaluation weights as printed by tune
+    --tablebase <dir>    directory of the endgame tables (tablebase default tablebases)
+    --book <file>        opening book as printed by book (book extends it)
+    --book-random        choose book moves by their weight instead of the best one
+    --white <player>     human or engine (play only, default human)
+    --black <player>     human or engine (play only, default engine)
+    --divide             print the count for every move (perft only)
+    --random <plies>     random plies at the start of every game (selfplay only, default 8)
+    --seed <number>      seed of the first game (selfplay only, default 1)
+    --passes <count>     maximum number of passes over all weights (tune only, default 100)
+    --depth2 <plies>     --depth, --time, --nodes and --weights of the second engine,
+    --time2 <seconds>    the first one uses the options without 2 (match only,
+    --nodes2 <count>     by default both search to depth 4)
+    --weights2 <file>
+    --openings <file>    position strings of the openings, one per line (match only)
+    --sprt <elo0,elo1>   stop once an SPRT decides between elo0 and elo1 (match only)";
+
+/*
+    Without any limit the engine thinks for 3 seconds. If a depth or a
+    number of nodes is given the search is only limited by time if a time
+    is given as well.
+*/
+struct Options {
+    position: GameState,
+    depth: Option<usize>,
+    time: Option<f64>,
+    nodes: Option<u64>,
+    hash_size: usize,
+    threads: Option<usize>,
+    weights: EvaluationWeights,
+    tablebase: Option<String>,
+    book: Option<Arc<OpeningBook>>,
+    book_random: bool,
+    white_engine: bool,
+    black_engine: bool,
+    divide: bool,
+    random_plies: usize,
+    seed: u64,
+    passes: usize,
+    depth2: Option<usize>,
+    time2: Option<f64>,
+    nodes2: Option<u64>,
+    weights2: Option<EvaluationWeights>,
+    openings: Option<Vec<GameState>>,
+    sprt: Option<Sprt>
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            position: GameState::default(),
+            depth: None,
+            time: None,
+            nodes: None,
+            hash_size: DEFAULT_HASH_SIZE,
+            threads: None,
+            weights: EvaluationWeights::default(),
+            tablebase: None,
+            book: None,
+            book_random: false,
+            white_engine: false,
+            black_engine: true,
+            divide: false,
+            random_plies: 8,
+            seed: 1,
+            passes: 100,
+            depth2: None,
+            time2: None,
+            nodes2: None,
+            weights2: None,
+            openings: None,
+            sprt: None
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--position" => options.position = value()?.parse().map_err(|error| format!("invalid position: {}", error))?,
+                "--variant" => options.position = GameState::new(RuleSet::new(value()?.parse::<Variant>().map_err(|error| error.to_string())?)),
+                "--rules" => options.position = GameState::new(value()?.parse::<RuleSet>().map_err(|error| error.to_string())?),
+                "--depth" => options.depth = Some(parse_number(&value()?)?),
+                "--nodes" => options.nodes = Some(parse_number(&value()?)? as u64),
+                "--hash" => options.hash_size = parse_number(&value()?)?,
+                "--threads" => options.threads = Some(parse_number(&value()?)?),
+                "--time" => options.time = Some(value()?.parse().ok().filter(|time: &f64| *time > 0.0).ok_or("invalid time")?),
+                "--white" => options.white_engine = parse_player(&value()?)?,
+                "--black" => options.black_engine = parse_player(&value()?)?,
+                "--weights" => options.weights = read_file(&value()?)?.parse().map_err(|error| format!("invalid weights: {}", error))?,
+                "--tablebase" => options.tablebase = Some(value()?),
+                "--book" => options.book = Some(Arc::new(read_file(&value()?)?.parse().map_err(|error| format!("invalid book: {}", error))?)),
+                "--book-random" => options.book_random = true,
+                "--divide" => options.divide = true,
+                "--random" => options.random_plies = parse_number(&value()?)?,
+                "--seed" => options.seed = parse_number(&value()?)? as u64,
+                "--passes" => options.passes = parse_number(&value()?)?,
+                "--depth2" => options.depth2 = Some(parse_number(&value()?)?),
+                "--nodes2" => options.nodes2 = Some(parse_number(&value()?)? as u64),
+                "--time2" => options.time2 = Some(value()?.parse().ok().filter(|time: &f64| *time > 0.0).ok_or("invalid time")?),
+                "--weights2" => options.weights2 = Some(read_file(&value()?)?.parse().map_err(|error| format!("invalid weights: {}", error))?),
+                "--openings" => options.openings = Some(parse_openings(&read_file(&value()?)?)?),
+                "--sprt" => options.sprt = Some(parse_sprt(&value()?)?),
+                _ => return Err(format!("unknown option '{}'", arg))
+            }
+        }
+        Ok(options)
+    }
+
+    fn get_limits(&self) -> SearchLimits {
+        SearchLimits {
+            max_depth: self.depth,
+            max_time: self.time.or(if self.depth.is_none() && self.nodes.is_none() { Some(3.0) } else { None }),
+            max_nodes: self.nodes,
+            infinite: false
+        }
+    }
+
+    fn get_context(&self, table: &Arc<TranspositionTable>, tablebase: Option<&Arc<Tablebase>>) -> SearchContext {
+        let context = SearchContext::with_table(self.get_limits(), Arc::clone(table)).with_weights(self.weights);
+        let context = match tablebase {
+            Some(tablebase) => context.with_tablebase(Arc::clone(tablebase)),
+            None => context
+        };
+        let context = match self.book.as_ref() {
+            Some(book) => context.with_book(Arc::clone(book), self.book_random),
+            None => context
+        };
+        match self.threads {
+            Some(threads) => context.with_threads(threads),
+            None => context
+        }
+    }
+
+    fn load_tablebase(&self) -> Result<Option<Arc<Tablebase>>, String> {
+        self.tablebase.as_ref()
+            .map(|directory| Tablebase::load(Path::new(directory))
+                .map(Arc::new)
+                .map_err(|error| format!("can't load the tablebase: {}", error)))
+            .transpose()
+    }
+
+    /* the second engine uses the options of the first one that aren't given for it */
+    fn get_engines(&self) -> (EngineConfig, EngineConfig) {
+        let limits = SearchLimits { max_depth: Some(self.depth.unwrap_or(4)), ..self.get_limits() };
+        let limits2 = SearchLimits {
+            max_depth: self.depth2.or(limits.max_depth),
+            max_time: self.time2.or(limits.max_time),
+            max_nodes: self.nodes2.or(limits.max_nodes),
+            infinite: false
+        };
+        let engine = |limits, weights| EngineConfig {
+            weights,
+            threads: self.threads.unwrap_or(1),
+            hash_size: self.hash_size,
+            ..EngineConfig::new(limits)
+        };
+        (engine(limits, self.weights), engine(limits2, self.weights2.unwrap_or(self.weights)))
+    }
+
+    fn is_engine(&self, player: Token) -> bool {
+        match player {
+            Token::White => self.white_engine,
+            Token::Black => self.black_engine,
+            Token::None => false
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid number '{}'", value))
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|error| format!("can't read {}: {}", path, error))
+}
+
+fn parse_openings(openings: &str) -> Result<Vec<GameState>, String> {
+    openings.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse().map_err(|error| format!("invalid opening '{}': {}", line, error)))
+        .collect()
+}
+
+fn parse_sprt(value: &str) -> Result<Sprt, String> {
+    let invalid = || format!("invalid sprt '{}'", value);
+    let (elo0, elo1) = value.split_once(',').ok_or_else(invalid)?;
+    let elo0 = elo0.trim().parse::<f64>().map_err(|_| invalid())?;
+    let elo1 = elo1.trim().parse::<f64>().map_err(|_| invalid())?;
+    if elo0 >= elo1 {
+        return Err(invalid());
+    }
+    Ok(Sprt::new(elo0, elo1))
+}
+
+fn parse_player(value: &str) -> Result<bool, String> {
+    match value {
+        "human" => Ok(false),
+        "engine" => Ok(true),
+        _ => Err(format!("invalid player '{}'", value))
+    }
+}
+
+fn legal_actions(game_state: &GameState) -> Vec<Action> {
+    if game_state.is_game_over() {
+        return Vec::new();
+    }
+    list_actions(
+        game_state.get_board(),
+        Token::parse_to_u8(game_state.get_player_turn()),
+        game_state.get_phase(),
+        None,
+        game_state.get_rules()
+    ).collect()
+}
+
+fn best_move(
+    game_state: &GameState,
+    options: &Options,
+    table: &Arc<TranspositionTable>,
+    tablebase: Option<&Arc<Tablebase>>,
+    thread_pool: &mut Option<Arc<ThreadPool>>
+) -> Option<SearchResult> {
+    if legal_actions(game_state).is_empty() {
+        return None;
+    }
+    let context = options.get_context(table, tablebase).with_thread_pool(thread_pool.take());
+    *thread_pool = context.get_thread_pool();
+    search(
+        game_state.get_board(),
+        game_state.get_player_turn(),
+        AiPhase::from_game_state(game_state),
+        &context,
+        |_| {}
+    )
+}
+
+const BENCH_POSITIONS: [&str; 6] = [
+    "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0",
+    "WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0",
+    "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3",
+    "WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 18 0",
+    "WWEWBEEEEEEEEEEEEEEEEBBB w 0 0 30 0",
+    "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0"
+];
+
+/*
+    Searches every bench position once with the move ordering heuristics
+    and once with the moves only sorted by their static evaluation, each
+    time with an empty transposition table.
+*/
+fn bench(options: &Options) {
+    let limits = SearchLimits { max_depth: Some(options.depth.unwrap_or(6)), ..options.get_limits() };
+    let mut total = [(0, 0.0); 2];
+    let mut thread_pool = None;
+
+    println!("{:<40} {:>12} {:>12} {:>8}", "position", "static", "heuristics", "nodes");
+    for position in BENCH_POSITIONS {
+        let game_state: GameState = position.parse().expect("invalid bench position");
+        let mut nodes = [0; 2];
+        for (index, heuristics) in [false, true].into_iter().enumerate() {
+            let table = Arc::new(TranspositionTable::new(options.hash_size));
+            let context = SearchContext::with_table(limits, table)
+                .with_threads(options.threads.unwrap_or(1))
+                .with_thread_pool(thread_pool.take())
+                .with_weights(options.weights);
+            thread_pool = context.get_thread_pool();
+            let context = if heuristics { context } else { context.without_ordering_heuristics() };
+            search(
+                game_state.get_board(),
+                game_state.get_player_turn(),
+                AiPhase::from_game_state(&game_state),
+                &context,
+                |_| {}
+            );
+            nodes[index] = context.get_nodes();
+            total[index].0 += context.get_nodes();
+            total[index].1 += context.get_elapsed();
+        }
+        println!("{:<40} {:>12} {:>12} {:>7.1}%", position, nodes[0], nodes[1], reduction(nodes[0], nodes[1]));
+    }
+
+    println!("{:<40} {:>12} {:>12} {:>7.1}%", "total", total[0].0, total[1].0, reduction(total[0].0, total[1].0));
+    println!("{:<40} {:>11.2}s {:>11.2}s", "time", total[0].1, total[1].1);
+}
+
+fn reduction(before: u64, after: u64) -> f64 {
+    100.0 * (after as f64 - before as f64) / before.max(1) as f64
+}
+
+/*
+    Plays the games one after another, each with the next seed, and prints
+    their positions. The progress goes to stderr, so the positions can be
+    redirected into a file for tune.
+*/
+fn self_play(games: usize, options: &Options) {
+    let (engine, _) = options.get_engines();
+    let mut thread_pool = None;
+    for game in 0..games {
+        let positions = self_play_game(&engine, options.random_plies, options.seed + game as u64, &mut thread_pool);
+        eprintln!("game {}: {} positions, result {}", game + 1, positions.len(), positions.first().map_or(0.5, |position| position.result));
+        for position in positions {
+            println!("{}", position);
+        }
+    }
+}
+
+fn tune(path: &str, options: &Options) -> Result<(), String> {
+    let positions = read_file(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| line.parse().map_err(|error| format!("line {}: {}", index + 1, error)))
+        .collect::<Result<Vec<TrainingPosition>, String>>()?;
+
+    let mut tuner = Tuner::new(&positions, options.weights);
+    eprintln!("{} positions, scaling {:.6}, error {:.6}", tuner.get_position_count(), tuner.get_scaling(), tuner.get_error());
+    for pass in 0..options.passes {
+        if !tuner.step() {
+            break;
+        }
+        eprintln!("pass {}: error {:.6}", pass + 1, tuner.get_error());
+    }
+    print!("{}", tuner.get_weights());
+    Ok(())
+}
+
+fn play_match(games: usize, options: &Options) {
+    let (first, second) = options.get_engines();
+    let openings = options.openings.clone().unwrap_or_else(|| {
+        DEFAULT_OPENINGS.iter().map(|opening| opening.parse().expect("invalid default opening")).collect()
+    });
+
+    let result = run_match(&first, &second, &openings, games, options.sprt, |game, winner, result| {
+        let (white, black) = if game % 2 == 0 { ("first", "second") } else { ("second", "first") };
+        let outcome = match winner {
+            Winner::White(_) => "1-0",
+            Winner::Black(_) => "0-1",
+            Winner::Draw(_) => "1/2-1/2"
+        };
+        println!("game {} ({} - {}): {}, {}", game + 1, white, black, outcome, result);
+    });
+
+    println!("\n{}", result);
+    if let Some(sprt) = options.sprt {
+        let (lower, upper) = sprt.get_bounds();
+        let decision = match sprt.get_decision(&result) {
+            SprtDecision::Continue => "no decision",
+            SprtDecision::AcceptH0 => "H0 accepted",
+            SprtDecision::AcceptH1 => "H1 accepted"
+        };
+        println!("SPRT elo0 {} elo1 {}: LLR {:.2} ({:.2}, {:.2}), {}", sprt.elo0, sprt.elo1, sprt.get_llr(&result), lower, upper, decision);
+    }
+}
+
+fn parse_signature(signature: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("invalid signature '{}'", signature);
+    let (own, other) = signature.split_once('v').ok_or_else(invalid)?;
+    let own = own.parse::<usize>().map_err(|_| invalid())?;
+    let other = other.parse::<usize>().map_err(|_| invalid())?;
+    if !(MIN_TOKENS..=MAX_TOKENS).contains(&own) || !(MIN_TOKENS..=MAX_TOKENS).contains(&other) {
+        return Err(invalid());
+    }
+    Ok((own, other))
+}
+
+/*
+    Generates the tables in addition to the ones already in the directory
+    and saves all of them there.
+*/
+fn generate_tablebase(signatures: &[(usize, usize)], options: &Options) -> Result<(), String> {
+    let directory = Path::new(options.tablebase.as_deref().unwrap_or("tablebases"));
+    let mut tablebase = if directory.exists() {
+        Tablebase::load(directory).map_err(|error| format!("can't load the tablebase: {}", error))?
+    } else {
+        Tablebase::new()
+    };
+
+    for (own, other) in signatures.iter().copied() {
+        tablebase.generate(own, other);
+    }
+    for (own, other) in tablebase.get_signatures() {
+        let statistics = tablebase.get_statistics(own, other).expect("missing table");
+        println!(
+            "{}v{}: {} positions, {} won, {} lost, {} drawn, longest win {} plies",
+            own, other, statistics.positions, statistics.wins, statistics.losses, statistics.draws, statistics.longest_win
+        );
+    }
+    tablebase.save(directory).map_err(|error| format!("can't save the tablebase: {}", error))
+}
+
+/*
+    Adds the best moves of searches of all positions of the first plies and
+    the moves of the games to the book of --book and prints it. The
+    searches use the rules of --position, --variant or --rules.
+*/
+fn build_book(plies: u8, games: &[String], options: &Options) -> Result<(), String> {
+    let mut book = options.book.as_deref().cloned().unwrap_or_default();
+    for path in games {
+        let game = read_game(&read_file(path)?).map_err(|error| format!("invalid game {}: {}", path, error))?;
+        book.add_game(&game.record);
+    }
+
+    let limits = SearchLimits { max_depth: Some(options.depth.unwrap_or(6)), ..options.get_limits() };
+    book.add_searches(options.position.get_rules(), plies, limits, |game_state, result| eprintln!("{}: {} ({})", game_state, result.action, result));
+    print!("{}", book);
+    Ok(())
+}
+
+fn print_winner(winner: &Winner) {
+    let (result, reason) = match winner {
+        Winner::White(reason) => ("White wins", reason),
+        Winner::Black(reason) => ("Black wins", reason),
+        Winner::Draw(reason) => ("Draw", reason)
+    };
+    if reason.is_empty() {
+        println!("{}", result);
+    } else {
+        println!("{}: {}", result, reason);
+    }
+}
+
+fn play(options: &Options) -> Result<(), String> {
+    let mut record = GameRecord::new(options.position);
+    let table = Arc::new(TranspositionTable::new(options.hash_size));
+    let tablebase = options.load_tablebase()?;
+    let mut thread_pool = None;
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        let game_state = record.get_game_state();
+        println!();
+        print_board(game_state.get_board(), game_state.get_rules());
+        println!("{}", game_state);
+
+        if let Some(winner) = record.get_winner() {
+            print_winner(winner);
+            return Ok(());
+        }
+
+        let player = game_state.get_player_turn();
+        if options.is_engine(player) {
+            let result = best_move(&game_state, options, &table, tablebase.as_ref(), &mut thread_pool).ok_or("no legal move for the engine")?;
+            println!("{} plays {} ({})", player, result.action, result);
+            record.apply_action(result.action);
+            continue;
+        }
+
+        print!("{} to move> ", player);
+        io::stdout().flush().map_err(|error| error.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|error| error.to_string())?,
+            None => return Ok(())
+        };
+
+        match line.trim() {
+            "" => (),
+            "quit" => return Ok(()),
+            "help" => println!("Enter a move like d7, d7-g7 or d7-g7xa1, or one of: moves, undo, redo, quit"),
+            "moves" => println!("{}", legal_actions(&game_state).iter().map(Action::to_string).collect::<Vec<String>>().join(" ")),
+            "undo" => {
+                /* take back the own move and the answer of the engine */
+                record.undo();
+                while record.can_undo() && options.is_engine(record.get_game_state().get_player_turn()) {
+                    record.undo();
+                }
+            },
+            "redo" => {
+                record.redo();
+                while record.can_redo() && options.is_engine(record.get_game_state().get_player_turn()) {
+                    record.redo();
+                }
+            },
+            input => match input.parse::<Action>() {
+                Ok(action) if legal_actions(&game_state).contains(&action) => record.apply_action(action),
+                Ok(action) => println!("Illegal move {}", action),
+                Err(error) => println!("Invalid move: {}", error)
+            }
+        }
+    }
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let command = args.next().ok_or("missing command")?;
+    match command.as_str() {
+        "play" => play(&Options::parse(args)?),
+        "bestmove" => {
+            let options = Options::parse(args)?;
+            let table = Arc::new(TranspositionTable::new(options.hash_size));
+            let tablebase = options.load_tablebase()?;
+            match best_move(&options.position, &options, &table, tablebase.as_ref(), &mut None) {
+                Some(result) => {
+                    println!("{}", result);
+                    println!("bestmove {}", result.action);
+                },
+                None => println!("bestmove (none)")
+            }
+            Ok(())
+        },
+        "moves" => {
+            let options = Options::parse(args)?;
+            for action in legal_actions(&options.position) {
+                println!("{}", action);
+            }
+            Ok(())
+        },
+        "perft" => {
+            let depth = parse_number(&args.next().ok_or("missing depth")?)?;
+            let options = Options::parse(args)?;
+            if options.divide {
+                let divide = perft_divide(options.position, depth);
+                for (action, count) in divide.iter() {
+                    println!("{}: {}", action, count);
+                }
+                println!("\nmoves: {}", divide.len());
+                println!("nodes: {}", divide.iter().map(|(_, count)| count).sum::<u64>());
+            } else {
+                println!("{}", perft(options.position, depth));
+            }
+            Ok(())
+        },
+        "bench" => {
+            bench(&Options::parse(args)?);
+            Ok(())
+        },
+        "selfplay" => {
+            let games = parse_number(&args.next().ok_or("missing number of games")?)?;
+            self_play(games, &Options::parse(args)?);
+            Ok(())
+        },
+        "tune" => {
+            let path = args.next().ok_or("missing file")?;
+            tune(&path, &Options::parse(args)?)
+        },
+        "match" => {
+            let games = parse_number(&args.next().ok_or("missing number of games")?)?;
+            play_match(games, &Options::parse(args)?);
+            Ok(())
+        },
+        "tablebase" => {
+            let mut signatures = Vec::new();
+            let mut args = args.peekable();
+            while let Some(signature) = args.next_if(|arg| !arg.starts_with("--")) {
+                signatures.push(parse_signature(&signature)?);
+            }
+            if signatures.is_empty() {
+                return Err("missing signature".to_string());
+            }
+            generate_tablebase(&signatures, &Options::parse(args)?)
+        },
+        "book" => {
+            let plies = parse_number(&args.next().ok_or("missing number of plies")?)?;
+            let mut args = args.peekable();
+            let mut games = Vec::new();
+            while let Some(game) = args.next_if(|arg| !arg.starts_with("--")) {
+                games.push(game);
+            }
+            build_book(plies.min(u8::MAX as usize) as u8, &games, &Options::parse(args)?)
+        },
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        _ => Err(format!("unknown command '{}'", command))
+    }
+}
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1)) {
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        process::exit(2);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{fs, io::{self, BufRead}, path::Path, sync::Arc, thread::{self, JoinHandle}};
+use rayon::ThreadPool;
+use muehle::{
+    agent::{
+        book::{BookError, OpeningBook},
+        context::{SearchContext, SearchLimits},
+        search,
+        tablebase::Tablebase,
+        transposition_table::{TranspositionTable, DEFAULT_HASH_SIZE},
+        AiPhase
+    },
+    logic::{game_state::GameState, rules::{RuleSet, Variant}},
+    protocol::{format_info, parse_command, Command, GoOptions}
+};
+
+/*
+    Speaks the engine protocol of muehle::protocol on stdin and stdout. The
+    search runs on its own thread, so stop and quit are handled while the
+    engine is thinking.
+*/
+const MAX_HASH_SIZE: usize = 4096;
+const MAX_THREADS: usize = 256;
+
+struct RunningSearch {
+    context: Arc<SearchContext>,
+    handle: JoinHandle<()>
+}
+
+impl RunningSearch {
+    fn start(
+        game_state: GameState,
+        options: GoOptions,
+        table: Arc<TranspositionTable>,
+        threads: usize,
+        thread_pool: Option<Arc<ThreadPool>>,
+        tablebase: Option<Arc<Tablebase>>,
+        book: Option<(Arc<OpeningBook>, bool)>
+    ) -> Self {
+        let context = SearchContext::with_table(options.get_limits(game_state.get_player_turn()), table)
+            .with_threads(threads)
+            .with_thread_pool(thread_pool);
+        let context = match tablebase {
+            Some(tablebase) => context.with_tablebase(tablebase),
+            None => context
+        };
+        let context = Arc::new(match book {
+            Some((book, random)) => context.with_book(book, random),
+            None => context
+        });
+        let search_context = Arc::clone(&context);
+
+        let handle = thread::spawn(move || {
+            let result = if game_state.is_game_over() {
+                None
+            } else {
+                search(
+                    game_state.get_board(),
+                    game_state.get_player_turn(),
+                    AiPhase::from_game_state(&game_state),
+                    &search_context,
+                    |result| println!("{}", format_info(result))
+                )
+            };
+
+            match result {
+                Some(result) => println!("bestmove {}", result.action),
+                None => println!("bestmove (none)")
+            }
+        });
+
+        RunningSearch { context, handle }
+    }
+
+    fn stop(self) {
+        self.context.stop();
+        self.handle.join().expect("search thread panicked");
+    }
+}
+
+fn load_book(path: &str) -> Result<OpeningBook, String> {
+    let book = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    book.parse().map_err(|error: BookError| error.to_string())
+}
+
+fn main() {
+    let mut rules = RuleSet::default();
+    let mut game_state = GameState::default();
+    let mut table = Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE));
+    let mut threads = SearchContext::new(SearchLimits::default()).get_threads();
+    let mut thread_pool = None;
+    let mut tablebase: Option<Arc<Tablebase>> = None;
+    let mut book: Option<Arc<OpeningBook>> = None;
+    let mut book_random = false;
+    let mut running_search: Option<RunningSearch> = None;
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_command(&line, rules) {
+            Ok(Command::Uci) => {
+                println!("id name muehle {}", env!("CARGO_PKG_VERSION"));
+                println!("id author {}", env!("CARGO_PKG_AUTHORS"));
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH_SIZE, MAX_HASH_SIZE);
+                println!("option name Threads type spin default {} min 1 max {}", threads, MAX_THREADS);
+                println!("option name Clear Hash type button");
+                println!("option name TablebasePath type string default <empty>");
+                println!("option name BookFile type string default <empty>");
+                println!("option name BookRandom type check default false");
+                println!("option name Variant type combo default {} {}", rules.get_variant(), Variant::ALL.map(|variant| format!("var {}", variant)).join(" "));
+                println!("option name Rules type string default {}", rules);
+                println!("uciok");
+            },
+            Ok(Command::IsReady) => println!("readyok"),
+            Ok(Command::NewGame) => {
+                if let Some(search) = running_search.take() {
+                    search.stop();
+                }
+                game_state = GameState::new(rules);
+                table.clear();
+            },
+            Ok(Command::SetOption(name, value)) => {
+                if let Some(search) = running_search.take() {
+                    search.stop();
+                }
+                match (name.to_lowercase().as_str(), value.as_deref().and_then(|value| value.parse::<usize>().ok())) {
+                    ("hash", Some(size)) => table = Arc::new(TranspositionTable::new(size.clamp(1, MAX_HASH_SIZE))),
+                    ("threads", Some(count)) => threads = count.clamp(1, MAX_THREADS),
+                    ("clear hash", _) => table.clear(),
+                    ("tablebasepath", _) => tablebase = match value.as_deref() {
+                        None | Some("<empty>") => None,
+                        Some(directory) => match Tablebase::load(Path::new(directory)) {
+                            Ok(loaded) => {
+                                println!("info string loaded {} tablebases", loaded.get_signatures().len());
+                                Some(Arc::new(loaded))
+                            },
+                            Err(error) => {
+                                println!("info string can't load the tablebase from '{}': {}", directory, error);
+                                None
+                            }
+                        }
+                    },
+                    ("bookfile", _) => book = match value.as_deref() {
+                        None | Some("<empty>") => None,
+                        Some(path) => match load_book(path) {
+                            Ok(loaded) => {
+                                println!("info string loaded {} book positions", loaded.get_position_count());
+                                Some(Arc::new(loaded))
+                            },
+                            Err(error) => {
+                                println!("info string can't load the book from '{}': {}", path, error);
+                                None
+                            }
+                        }
+                    },
+                    ("bookrandom", _) => book_random = value.as_deref() == Some("true"),
+                    ("variant", _) => match value.as_deref().unwrap_or_default().parse() {
+                        Ok(variant) => {
+                            rules = RuleSet::new(variant);
+                            game_state = GameState::new(rules);
+                        },
+                        Err(error) => println!("info string {}", error)
+                    },
+                    ("rules", _) => match value.as_deref().unwrap_or_default().parse() {
+                        Ok(new_rules) => {
+                            rules = new_rules;
+                            game_state = GameState::new(rules);
+                        },
+                        Err(error) => println!("info string {}", error)
+                    },
+                    _ => println!("info string unknown option '{}'", name)
+                }
+            },
+            Ok(Command::Position(new_game_state)) => game_state = new_game_state,
+            Ok(Command::Go(options)) => {
+                if let Some(search) = running_search.take() {
+                    search.stop();
+                }
+                let book = book.clone().map(|book| (book, book_random));
+                let started = RunningSearch::start(game_state, options, Arc::clone(&table), threads, thread_pool.take(), tablebase.clone(), book);
+                thread_pool = started.context.get_thread_pool();
+                running_search = Some(started);
+            },
+            Ok(Command::Stop) => {
+                if let Some(search) = running_search.take() {
+                    search.stop();
+                }
+            },
+            Ok(Command::Quit) => break,
+            Err(error) => println!("info string {}", error)
+        }
+    }
+
+    if let Some(search) = running_search.take() {
+        search.stop();
+    }
+}
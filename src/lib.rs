@@ -0,0 +1,26 @@
+//! Rules engine and AI opponent for Nine Men's Morris and its variants.
+//!
+//! The graphical frontend lives in [`ui`] and is only compiled with the
+//! `ui` feature (enabled by default). Headless consumers can depend on the
+//! crate with `default-features = false` to avoid pulling in `good-web-game`.
+
+#[cfg(feature = "ui")]
+extern crate good_web_game as ggez;
+
+pub mod agent;
+pub mod logic;
+pub mod protocol;
+#[cfg(feature = "ui")]
+pub mod ui;
+
+pub use agent::{calculate_next_move, context::SearchLimits, AiPhase, SearchResult};
+pub use logic::{
+    action::{get_action_from_board, list_actions, Action},
+    game_state::{GameState, Phase, Token},
+    mill_detection::{is_all_part_of_mill, is_mill_closing, is_part_of_mill},
+    move_token_count::SearchBoard,
+    notation::{coordinate_to_position, position_to_coordinate, ParseActionError},
+    position::{decode_positions, encode_positions, print_board, Board, PositionError},
+    r#move::{apply_action, is_beat_possible, is_move_valid},
+    rules::{BlockedOutcome, RuleSet, Variant},
+};
@@ -1,21 +1,46 @@
 use std::iter;
+use itertools::Either;
 use crate::logic::{
     game_state::Phase, 
-    mill_detection::is_mill_closing, 
+    mill_detection::{count_closed_mills, is_mill_closing}, 
     r#move::{apply_move, is_beat_possible, is_move_valid}, 
-    position::{create_token_iter, get_number_of_tokens}
+    position::Board,
+    rules::RuleSet
 };
 
+/*
+    An action closing two mills at once captures two tokens with the
+    double capture rule, the captured positions are kept in ascending
+    order, so the same captures are always the same action.
+*/
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Action {
     pub start_position: Option<usize>,
     pub end_position: usize,
     pub beatable_position: Option<usize>,
+    pub second_beatable_position: Option<usize>
 }
 
 impl Action {
     pub fn new(start_position: Option<usize>, end_position: usize, beatable_position: Option<usize>) -> Self {
-        Action { start_position, end_position, beatable_position }
+        Action { start_position, end_position, beatable_position, second_beatable_position: None }
+    }
+
+    /* adds a captured position to the action */
+    pub fn with_capture(mut self, position: usize) -> Self {
+        match self.beatable_position {
+            None => self.beatable_position = Some(position),
+            Some(first) if first > position => {
+                self.beatable_position = Some(position);
+                self.second_beatable_position = Some(first);
+            },
+            Some(_) => self.second_beatable_position = Some(position)
+        }
+        self
+    }
+
+    pub fn get_captures(&self) -> impl Iterator<Item=usize> {
+        self.beatable_position.into_iter().chain(self.second_beatable_position)
     }
 }
 
@@ -31,45 +56,78 @@ impl Move {
     }
 }
 
-pub fn list_actions(board: &u64, token_type: u8, phase: Phase, number_of_token: Option<u8>) -> impl Iterator<Item=Action> + '_ {
-    list_moves(board, token_type, phase, number_of_token)
+pub fn list_actions(board: Board, token_type: u8, phase: Phase, number_of_token: Option<u8>, rules: RuleSet) -> impl Iterator<Item=Action> {
+    list_moves(board, token_type, phase, number_of_token, rules)
         .flat_map(move |possible_move| {
-            if is_mill_closing(*board, apply_move(board, &possible_move, token_type), token_type) {
-                return itertools::Either::Left(
-                    create_token_iter(*board).enumerate()
-                        .filter_map(move |(beatable_position, _)| {
-                            if is_beat_possible(*board, beatable_position, token_type) {
-                                Some(Action::new(possible_move.start_position, possible_move.end_position, Some(beatable_position)))
+            let action = Action::new(possible_move.start_position, possible_move.end_position, None);
+            let board_after_move = apply_move(board, &possible_move, token_type);
+            if !is_mill_closing(board, board_after_move, token_type, rules) {
+                Either::Right(iter::once(action))
+            } else if rules.has_double_capture() && count_closed_mills(board, board_after_move, token_type, rules) >= 2 {
+                Either::Left(Either::Left(list_double_captures(board_after_move, action, token_type, rules).into_iter()))
+            } else {
+                Either::Left(Either::Right(
+                    (0..24)
+                        .filter_map(move |beatable_position| {
+                            if is_beat_possible(board, beatable_position, token_type, rules) {
+                                Some(action.with_capture(beatable_position))
                             } else {
                                 None
                             }
-                        }))
-            } else {
-                itertools::Either::Right(iter::once(Action::new(possible_move.start_position, possible_move.end_position, None)))
+                        })))
             }
     })
 }
 
-pub fn list_moves(board: &u64, token_type: u8, phase: Phase, number_of_token: Option<u8>) -> impl Iterator<Item=Move> + '_ {
+/*
+    The actions capturing two tokens after closing two mills at once. The
+    pair of tokens can be captured if one of the orders is possible, so
+    the second token may be in a mill if the first one was the last token
+    outside of mills. If there is no second token to capture the action
+    captures only one.
+*/
+fn list_double_captures(board: Board, action: Action, token_type: u8, rules: RuleSet) -> Vec<Action> {
+    let can_capture = |first: usize, second: usize| {
+        let mut board_after_first = board;
+        board_after_first.set_token_at(first, 0b00);
+        is_beat_possible(board, first, token_type, rules) && is_beat_possible(board_after_first, second, token_type, rules)
+    };
+
+    let mut actions = Vec::new();
+    for first in 0..24 {
+        for second in first + 1..24 {
+            if can_capture(first, second) || can_capture(second, first) {
+                actions.push(action.with_capture(first).with_capture(second));
+            }
+        }
+        if is_beat_possible(board, first, token_type, rules) && !(0..24).any(|second| second != first && can_capture(first, second)) {
+            actions.push(action.with_capture(first));
+        }
+    }
+    actions
+}
+
+pub fn list_moves(board: Board, token_type: u8, phase: Phase, number_of_token: Option<u8>, rules: RuleSet) -> impl Iterator<Item=Move> {
     if phase == Phase::Set {
-        itertools::Either::Left(list_moves_set_phase(*board))
+        itertools::Either::Left(list_moves_set_phase(board, rules))
     } else {
-        itertools::Either::Right(list_moves_move_phase(board, token_type, number_of_token))
+        itertools::Either::Right(list_moves_move_phase(board, token_type, number_of_token, rules))
     }
 }
 
-fn list_moves_move_phase(board: &u64, token_type: u8, number_of_token: Option<u8>) -> impl Iterator<Item=Move> + '_ {
-    create_token_iter(*board).enumerate()
+fn list_moves_move_phase(board: Board, token_type: u8, number_of_token: Option<u8>, rules: RuleSet) -> impl Iterator<Item=Move> {
+    board.token_iter().enumerate()
         .filter(move |(_, token)| *token == token_type)
             .flat_map(move |(start_position, _)| {
-                create_token_iter(*board)
+                board.token_iter()
                     .enumerate()
                     .filter_map(move |(end_position, end_token)| {
                         if is_move_valid(
                             start_position, 
                             end_position, 
                             end_token, 
-                            number_of_token.unwrap_or_else(|| get_number_of_tokens(*board, token_type))
+                            number_of_token.unwrap_or_else(|| board.get_number_of_tokens(token_type)),
+                            rules
                         ) {
                             Some(Move::new(Some(start_position), end_position))
                         } else {
@@ -79,11 +137,12 @@ fn list_moves_move_phase(board: &u64, token_type: u8, number_of_token: Option<u8
             })
 }
 
-fn list_moves_set_phase(board: u64) -> impl Iterator<Item=Move> {
-    return create_token_iter(board)
+fn list_moves_set_phase(board: Board, rules: RuleSet) -> impl Iterator<Item=Move> {
+    let geometry = rules.get_geometry();
+    board.token_iter()
         .enumerate()
         .filter_map(move |(position, token)| {
-            if token == 0b00 {
+            if token == 0b00 && geometry.has_position(position) {
                 Some(Move::new(None, position))
             } else {
                 None
@@ -91,10 +150,12 @@ fn list_moves_set_phase(board: u64) -> impl Iterator<Item=Move> {
         })
 }
 
-pub fn get_action_from_board(mut board_before: u64, mut board_after: u64, token_type: u8) -> Action {
+pub fn get_action_from_board(board_before: Board, board_after: Board, token_type: u8) -> Action {
+    let mut board_before = board_before.to_raw();
+    let mut board_after = board_after.to_raw();
     let mut start_position = None;
     let mut end_position = 0;
-    let mut beatable_position = None;
+    let mut action = Action::new(None, 0, None);
 
     (0..24).rev().for_each( |index| {
         if board_before & 0b11 != board_after & 0b11 {
@@ -103,24 +164,24 @@ pub fn get_action_from_board(mut board_before: u64, mut board_after: u64, token_
             } else if (board_before & 0b11) as u8 == token_type {
                 start_position = Some(index as usize);
             } else {
-                beatable_position = Some(index as usize);
+                action = action.with_capture(index as usize);
             }
         }
         board_before >>= 2;
         board_after >>= 2;
     });
 
-    Action::new(start_position, end_position, beatable_position)
+    Action { start_position, end_position, ..action }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::logic::{action::get_action_from_board, game_state::{Phase, Token}, position::decode_positions};
+    use crate::logic::{action::get_action_from_board, game_state::{Phase, Token}, position::{decode_positions, Board}, r#move::apply_action, rules::{RuleSet, Variant}};
     use super::{list_actions, list_moves, list_moves_move_phase, list_moves_set_phase, Action, Move};
 
     #[test]
     fn test_list_actions() {
-        let board = decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string());
+        let board = decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string()).unwrap();
         let expected_actions = vec![
             Action::new(Some(2), 10, None),
             Action::new(Some(2), 3, None),
@@ -135,14 +196,14 @@ mod tests {
             Action::new(Some(4), 3, Some(16))
         ];
 
-        for action in list_actions(&board, Token::parse_to_u8(Token::White), Phase::Move, None) {
+        for action in list_actions(board, Token::parse_to_u8(Token::White), Phase::Move, None, RuleSet::default()) {
             assert!(expected_actions.contains(&action));
         }
     }
 
     #[test]
     fn test_list_actions2() {
-        let board = decode_positions("WBBEBWWWWEEBBBEEWWEEEEEE".to_string());
+        let board = decode_positions("WBBEBWWWWEEBBBEEWWEEEEEE".to_string()).unwrap();
         let expected_actions = vec![
             Action::new(Some(2), 10, None),
             Action::new(Some(2), 3, None),
@@ -152,14 +213,14 @@ mod tests {
             Action::new(Some(4), 3, Some(17))
         ];
 
-        for action in list_actions(&board, Token::parse_to_u8(Token::Black), Phase::Move, None) {
+        for action in list_actions(board, Token::parse_to_u8(Token::Black), Phase::Move, None, RuleSet::default()) {
             assert!(expected_actions.contains(&action));
         }
     }
 
     #[test]
     fn test_list_actions3() {
-        let board = decode_positions("BWWEWBBBBEEWWWEBBEEEEEEE".to_string());
+        let board = decode_positions("BWWEWBBBBEEWWWEBBEEEEEEE".to_string()).unwrap();
         let expected_actions = vec![
             Action::new(None, 9, None),
             Action::new(None, 10, None),
@@ -175,27 +236,90 @@ mod tests {
             Action::new(None, 3, Some(15)),
         ];
 
-        for action in list_actions(&board, Token::parse_to_u8(Token::White), Phase::Set, None) {
+        for action in list_actions(board, Token::parse_to_u8(Token::White), Phase::Set, None, RuleSet::default()) {
             assert!(expected_actions.contains(&action));
         }
     }
 
+    #[test]
+    fn test_list_actions_with_capture_options() {
+        let board = decode_positions("WWEEEWWEBBEBEEEBEEEEEEEE".to_string()).unwrap();
+        let white = Token::parse_to_u8(Token::White);
+        let double_capture = RuleSet::default().with_double_capture(true);
+        let captures = |rules: RuleSet| list_actions(board, white, Phase::Set, None, rules)
+            .filter(|action| action.end_position == 7)
+            .collect::<Vec<Action>>();
+
+        assert_eq!(captures(RuleSet::default()), vec![Action::new(None, 7, Some(11))]);
+        /* the tokens of the mill can only be captured after the token outside of it */
+        assert_eq!(captures(double_capture), vec![
+            Action::new(None, 7, Some(8)).with_capture(11),
+            Action::new(None, 7, Some(9)).with_capture(11),
+            Action::new(None, 7, Some(11)).with_capture(15)
+        ]);
+        assert_eq!(captures(RuleSet::default().with_capture_from_mills(true)).len(), 4);
+        assert_eq!(captures(double_capture.with_capture_from_mills(true)).len(), 6);
+
+        for action in captures(double_capture) {
+            assert_eq!(get_action_from_board(board, apply_action(board, &action, white), white), action);
+        }
+
+        /* without a second token to capture only one is captured */
+        let board = decode_positions("WWEEEWWEBEEEEEEEEEEEEEEE".to_string()).unwrap();
+        assert_eq!(
+            list_actions(board, white, Phase::Set, None, double_capture).filter(|action| action.end_position == 7).collect::<Vec<Action>>(),
+            vec![Action::new(None, 7, Some(8))]
+        );
+    }
+
+    #[test]
+    fn test_list_actions_with_diagonals() {
+        let board = decode_positions("EEEEBEEWEEEEEEEWEEEEEEEE".to_string()).unwrap();
+        let twelve = RuleSet::new(Variant::TwelveMensMorris);
+        let white = Token::parse_to_u8(Token::White);
+
+        assert!(list_actions(board, white, Phase::Set, None, RuleSet::default()).any(|action| action == Action::new(None, 23, None)));
+        assert!(list_actions(board, white, Phase::Set, None, twelve).any(|action| action == Action::new(None, 23, Some(4))));
+        assert!(!list_actions(board, white, Phase::Set, None, twelve).any(|action| action == Action::new(None, 23, None)));
+
+        /* in the move phase the tokens can move along the diagonals */
+        let moves = list_moves(board, white, Phase::Move, Some(9), twelve).collect::<Vec<Move>>();
+        assert!(moves.contains(&Move::new(Some(15), 23)));
+        assert!(!list_moves(board, white, Phase::Move, Some(9), RuleSet::default()).any(|r#move| r#move == Move::new(Some(15), 23)));
+    }
+
+    #[test]
+    fn test_list_actions_on_smaller_boards() {
+        let six = RuleSet::new(Variant::SixMensMorris);
+        let three = RuleSet::new(Variant::ThreeMensMorris);
+        let white = Token::parse_to_u8(Token::White);
+        assert_eq!(list_actions(Board::default(), white, Phase::Set, None, six).count(), 16);
+        assert_eq!(list_actions(Board::default(), white, Phase::Set, None, three).count(), 9);
+        assert!(list_actions(Board::default(), white, Phase::Set, None, three).all(|action| three.get_geometry().has_position(action.end_position)));
+
+        /* with three tokens the tokens of Three Men's Morris only move to their neighbors, also to the center */
+        let board = decode_positions("WBEEEEEBEEEEEEEWEBEEEEEW".to_string()).unwrap();
+        let moves = list_moves(board, white, Phase::Move, None, three).collect::<Vec<Move>>();
+        assert_eq!(moves, vec![Move::new(Some(0), 8), Move::new(Some(15), 8), Move::new(Some(23), 8), Move::new(Some(23), 16)]);
+        assert_eq!(list_moves(board, white, Phase::Move, None, RuleSet::default()).count(), 3 * 18);
+    }
+
     #[test]
     fn test_list_moves() {
-        let board = decode_positions("WBBEBWWWWEEBBBEEWEEEEEEE".to_string());
-        let moves_set_phase = list_moves(&board, Token::parse_to_u8(Token::Black), Phase::Set, None).collect::<Vec<Move>>();
-        let expected_move_set_phase = list_moves_set_phase(board).collect::<Vec<Move>>();
+        let board = decode_positions("WBBEBWWWWEEBBBEEWEEEEEEE".to_string()).unwrap();
+        let moves_set_phase = list_moves(board, Token::parse_to_u8(Token::Black), Phase::Set, None, RuleSet::default()).collect::<Vec<Move>>();
+        let expected_move_set_phase = list_moves_set_phase(board, RuleSet::default()).collect::<Vec<Move>>();
         assert_eq!(moves_set_phase, expected_move_set_phase);
 
-        let board2 = decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string());
-        let moves_move_phase = list_moves(&board2, Token::parse_to_u8(Token::White), Phase::Move, None).collect::<Vec<Move>>();
-        let expected_move_move_phase = list_moves_move_phase(&board2, Token::parse_to_u8(Token::White), None).collect::<Vec<Move>>();
+        let board2 = decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string()).unwrap();
+        let moves_move_phase = list_moves(board2, Token::parse_to_u8(Token::White), Phase::Move, None, RuleSet::default()).collect::<Vec<Move>>();
+        let expected_move_move_phase = list_moves_move_phase(board2, Token::parse_to_u8(Token::White), None, RuleSet::default()).collect::<Vec<Move>>();
         assert_eq!(moves_move_phase, expected_move_move_phase);
     }
 
     #[test]
     fn test_list_moves_move_phase() {
-        let board = decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string());
+        let board = decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string()).unwrap();
         let expected_moves = vec![
             Move::new(Some(2), 10),
             Move::new(Some(2), 3),
@@ -205,14 +329,14 @@ mod tests {
             Move::new(Some(13), 14),
         ];
 
-        for r#move in list_moves_move_phase(&board, Token::parse_to_u8(Token::White), None) {
+        for r#move in list_moves_move_phase(board, Token::parse_to_u8(Token::White), None, RuleSet::default()) {
             assert!(expected_moves.contains(&r#move));
         }
     }
 
     #[test]
     fn test_list_moves_set_phase() {
-        let board = decode_positions("WBBEBWWWWEEBBBEEWEEEEEEE".to_string());
+        let board = decode_positions("WBBEBWWWWEEBBBEEWEEEEEEE".to_string()).unwrap();
         let expected_moves = vec![
             Move::new(None, 3),
             Move::new(None, 9),
@@ -228,19 +352,19 @@ mod tests {
             Move::new(None, 23),
         ];
 
-        for r#move in list_moves_set_phase(board) {
+        for r#move in list_moves_set_phase(board, RuleSet::default()) {
             assert!(expected_moves.contains(&r#move));
         }
     }
 
     #[test]
     fn test_get_action_from_board() {
-        let board_before = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string());
-        let board_after1 = decode_positions("EWEEBBBBWWEEEEWEWEEEEEEE".to_string());
-        let board_after2 = decode_positions("WWEEBBBBWWEEEEWEWEEEEEEE".to_string());
-        let board_after3 = decode_positions("WEEBEBBBWWEEEEWEWEEEEEEE".to_string());
-        let board_after4 = decode_positions("WEEEEBBBWWEEEEEWWEEEEEEE".to_string());
-        let board_after5 = decode_positions("WEEWBBBBWWEEEEWEEEEEEEEE".to_string());
+        let board_before = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap();
+        let board_after1 = decode_positions("EWEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap();
+        let board_after2 = decode_positions("WWEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap();
+        let board_after3 = decode_positions("WEEBEBBBWWEEEEWEWEEEEEEE".to_string()).unwrap();
+        let board_after4 = decode_positions("WEEEEBBBWWEEEEEWWEEEEEEE".to_string()).unwrap();
+        let board_after5 = decode_positions("WEEWBBBBWWEEEEWEEEEEEEEE".to_string()).unwrap();
 
         assert_eq!(get_action_from_board(board_before, board_after1, 0b11), Action::new(Some(0), 1, None));
         assert_eq!(get_action_from_board(board_before, board_after2, 0b11), Action::new(None, 1, None));
@@ -1,50 +1,45 @@
 use crate::agent::AiPhase;
-use super::move_token_count::{
-    extract_black_token_count_from_board, 
-    extract_white_token_count_from_board, 
-    update_possible_move_count, 
-    BLACK_TOKEN_FIRST_POSITION, 
-    WHITE_TOKEN_FIRST_POSITION
-};
+use super::move_token_count::SearchBoard;
 use super::{
     action::list_actions, 
     game_state::Phase, 
-    position::{negate_token, set_token_at}
+    position::negate_token,
+    r#move::apply_action
 };
 
-pub fn forward_step_boards(board: &u64, token_type: u8, phase: AiPhase) -> impl Iterator<Item=u64> + '_ {
-    let number_of_token = if token_type == 0b11 {
-        extract_white_token_count_from_board(*board) as u8
-    } else {
-        extract_black_token_count_from_board(*board) as u8
-    };
+pub fn forward_step_boards(board: SearchBoard, token_type: u8, phase: AiPhase) -> impl Iterator<Item=SearchBoard> {
+    let number_of_token = board.get_token_count(token_type) as u8;
+    let rules = phase.rules;
 
-    list_actions(board, token_type, phase.phase, Some(number_of_token)).map(move |action| {
-        let mut new_board = *board;
+    list_actions(board.board(), token_type, phase.phase, Some(number_of_token), rules).map(move |action| {
+        if !rules.has_incremental_counters() {
+            return SearchBoard::new(apply_action(board.board(), &action, token_type), rules);
+        }
+        let mut new_board = board;
         
-        if phase.phase == Phase::Set && phase.step_counter >= 4 {
-            new_board += if token_type == 0b11 {
-                WHITE_TOKEN_FIRST_POSITION
-            } else {
-                BLACK_TOKEN_FIRST_POSITION
-            };
+        /*
+            The token counters start at 2, so they only change while the
+            player has at least 2 tokens before a placement and at least
+            2 tokens after a capture. Captures in the set phase can happen
+            before the other player has placed 3 tokens.
+        */
+        if phase.phase == Phase::Set && board.board().get_number_of_tokens(token_type) >= 2 {
+            new_board.increase_token_count(token_type);
         }
         
-        if phase.phase == Phase::Move {
-            new_board = set_token_at(new_board, action.start_position.unwrap(), 0b00);
-            new_board = update_possible_move_count(new_board, token_type, action.start_position.unwrap(), true)
+        if let Some(start_position) = action.start_position {
+            new_board.set_token_at(start_position, 0b00);
+            new_board.update_possible_move_count(token_type, start_position, true, rules);
         }
-        new_board = set_token_at(new_board, action.end_position, token_type);
-        new_board = update_possible_move_count(new_board, token_type, action.end_position, false);
-
-        if action.beatable_position.is_some() {
-            new_board = set_token_at(new_board, action.beatable_position.unwrap(), 0b00);
-            new_board -= if token_type == 0b11 {
-                BLACK_TOKEN_FIRST_POSITION
-            } else {
-                WHITE_TOKEN_FIRST_POSITION
-            };
-            new_board = update_possible_move_count(new_board, negate_token(token_type), action.beatable_position.unwrap(), true);
+        new_board.set_token_at(action.end_position, token_type);
+        new_board.update_possible_move_count(token_type, action.end_position, false, rules);
+
+        for (index, beatable_position) in action.get_captures().enumerate() {
+            new_board.set_token_at(beatable_position, 0b00);
+            if board.board().get_number_of_tokens(negate_token(token_type)) > 2 + index as u8 {
+                new_board.decrease_token_count(negate_token(token_type));
+            }
+            new_board.update_possible_move_count(negate_token(token_type), beatable_position, true, rules);
         }
 
         new_board
@@ -53,7 +48,7 @@ pub fn forward_step_boards(board: &u64, token_type: u8, phase: AiPhase) -> impl
 
 #[cfg(test)]
 mod tests {
-    use crate::{agent::AiPhase, logic::{game_state::{Phase, Token}, move_token_count::{BLACK_POSSIBLE_MOVES_FIRST_POSITION, BLACK_TOKEN_FIRST_POSITION, WHITE_POSSIBLE_MOVES_FIRST_POSITION, WHITE_TOKEN_FIRST_POSITION}, position::decode_positions}};
+    use crate::{agent::AiPhase, logic::{game_state::{Phase, Token}, move_token_count::{SearchBoard, BLACK_POSSIBLE_MOVES_FIRST_POSITION, BLACK_TOKEN_FIRST_POSITION, WHITE_POSSIBLE_MOVES_FIRST_POSITION, WHITE_TOKEN_FIRST_POSITION}, position::decode_positions, rules::{RuleSet, Variant}}};
     use super::forward_step_boards;
 
     /*
@@ -70,36 +65,36 @@ mod tests {
         let white_9_moves = 9 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
         let white_6_moves = 6 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
         let board1 = 
-            decode_positions("WEEEEBBBWWEEEEEEWEEEEEEE".to_string())
+            decode_positions("WEEEEBBBWWEEEEEEWEEEEEEE".to_string()).unwrap().to_raw()
             + black_2_moves
             + 5 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
             + token_count;
     
         let expected_boards = vec![
-            decode_positions("EWEEEBBBWWEEEEEEWEEEEEEE".to_string())
+            decode_positions("EWEEEBBBWWEEEEEEWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + 3 * BLACK_POSSIBLE_MOVES_FIRST_POSITION
             + 7 * WHITE_POSSIBLE_MOVES_FIRST_POSITION,
-            decode_positions("WEEEEBBBEWEEEEEWWEEEEEEE".to_string())
+            decode_positions("WEEEEBBBEWEEEEEWWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + black_2_moves
             + white_9_moves,
-            decode_positions("WEEEEBBBWEWEEEEEWEEEEEEE".to_string())
+            decode_positions("WEEEEBBBWEWEEEEEWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + black_2_moves
             + white_9_moves,
-            decode_positions("WEEEEBBBWWEEEEEEEWEEEEEE".to_string())
+            decode_positions("WEEEEBBBWWEEEEEEEWEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + black_2_moves
             + white_6_moves,
-            decode_positions("WEEEEBBBWWEEEEEEEEEEEEEW".to_string())
+            decode_positions("WEEEEBBBWWEEEEEEEEEEEEEW".to_string()).unwrap().to_raw()
             + token_count
             + black_2_moves
             + white_6_moves
         ];
         
-        for forward_board in forward_step_boards(&board1, Token::parse_to_u8(Token::White), AiPhase::new(Phase::Move, 25)) {
-            assert!(expected_boards.contains(&forward_board));
+        for forward_board in forward_step_boards(SearchBoard::from_raw(board1), Token::parse_to_u8(Token::White), AiPhase::new(Phase::Move, 25)) {
+            assert!(expected_boards.contains(&forward_board.to_raw()));
         }
     }
 
@@ -112,48 +107,48 @@ mod tests {
         let white_9_moves = 9 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
         let white_10_moves = 10 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
         let board = 
-            decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string())
+            decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + white_8_moves
             + black_2_moves;
         let expected_boards = vec![
-            decode_positions("EWEEBBBBWWEEEEWEWEEEEEEE".to_string())
+            decode_positions("EWEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + white_10_moves
             + black_3_moves,
-            decode_positions("WEEEBBBBEWEEEEWWWEEEEEEE".to_string())
+            decode_positions("WEEEBBBBEWEEEEWWWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + white_10_moves
             + black_2_moves,
-            decode_positions("WEEEBBBBWEWEEEWEWEEEEEEE".to_string())
+            decode_positions("WEEEBBBBWEWEEEWEWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + 12 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
             + black_2_moves,
-            decode_positions("WEEEBBBBWWEEEEWEEWEEEEEE".to_string())
+            decode_positions("WEEEBBBBWWEEEEWEEWEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + white_9_moves
             + black_2_moves,
-            decode_positions("WEEEBBBBWWEEEEWEEEEEEEEW".to_string())
+            decode_positions("WEEEBBBBWWEEEEWEEEEEEEEW".to_string()).unwrap().to_raw()
             + token_count
             + white_9_moves
             + black_2_moves,
-            decode_positions("WEEEBBBBWWEEEWEEWEEEEEEE".to_string())
+            decode_positions("WEEEBBBBWWEEEWEEWEEEEEEE".to_string()).unwrap().to_raw()
             + token_count
             + 7 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
             + black_3_moves,
-            decode_positions("WEEEBBBBWWEEEEEEWEEEEEWE".to_string())
+            decode_positions("WEEEBBBBWWEEEEEEWEEEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_8_moves
             + black_3_moves,
-            decode_positions("WEEEEBBBWWEEEEEWWEEEEEEE".to_string()) // with mill
+            decode_positions("WEEEEBBBWWEEEEEWWEEEEEEE".to_string()).unwrap().to_raw() // with mill
             + BLACK_TOKEN_FIRST_POSITION
             + 3 * WHITE_TOKEN_FIRST_POSITION
             + 5 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
             + black_2_moves 
         ];
 
-        for forward_board in forward_step_boards(&board, Token::parse_to_u8(Token::White), AiPhase::new(Phase::Move, 25)) {
-            assert!(expected_boards.contains(&forward_board));
+        for forward_board in forward_step_boards(SearchBoard::from_raw(board), Token::parse_to_u8(Token::White), AiPhase::new(Phase::Move, 25)) {
+            assert!(expected_boards.contains(&forward_board.to_raw()));
         }
     }
 
@@ -165,56 +160,88 @@ mod tests {
         let white_9_moves = 9 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
         let black_2_moves = 2 * BLACK_POSSIBLE_MOVES_FIRST_POSITION;
         let board = 
-            decode_positions("BWEEEWBBWBBWWBBWEEEEEEWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEEEEEEWE".to_string()).unwrap().to_raw()
             + 5 * BLACK_TOKEN_FIRST_POSITION
             + 2 * BLACK_POSSIBLE_MOVES_FIRST_POSITION
             + 5 * WHITE_TOKEN_FIRST_POSITION
             + 7 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
         let expected_boards = vec![
-            decode_positions("BWEEEWBBWBBWWBBWWEEEEEWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWWEEEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_8_moves
             + black_2_moves,
-            decode_positions("BWEEEWBBWBBWWBBWEWEEEEWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEWEEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_9_moves
             + black_2_moves,
-            decode_positions("BWEEEWBBWBBWWBBWEEWEEEWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEEWEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_9_moves
             + BLACK_POSSIBLE_MOVES_FIRST_POSITION,
-            decode_positions("BWEEEWBBWBBWWBBWEEEWEEWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEEEWEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_9_moves
             + black_2_moves,
-            decode_positions("BWEEEWBBWBBWWBBWEEEEWEWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEEEEWEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_8_moves
             + black_2_moves,
-            decode_positions("BWEEEWBBWBBWWBBWEEEEEWWE".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEEEEEWWE".to_string()).unwrap().to_raw()
             + token_count
             + white_7_moves
             + black_2_moves,
-            decode_positions("BWEEEWBBWBBWWBBWEEEEEEWW".to_string())
+            decode_positions("BWEEEWBBWBBWWBBWEEEEEEWW".to_string()).unwrap().to_raw()
             + token_count
             + white_7_moves
             + black_2_moves,
-            decode_positions("BWWEEWBBWBBWWBBWEEEEEEWE".to_string())
+            decode_positions("BWWEEWBBWBBWWBBWEEEEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_7_moves
             + BLACK_POSSIBLE_MOVES_FIRST_POSITION,
-            decode_positions("BWEWEWBBWBBWWBBWEEEEEEWE".to_string())
+            decode_positions("BWEWEWBBWBBWWBBWEEEEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + white_9_moves
             + black_2_moves,
-            decode_positions("BWEEWWBBWBBWWBBWEEEEEEWE".to_string())
+            decode_positions("BWEEWWBBWBBWWBBWEEEEEEWE".to_string()).unwrap().to_raw()
             + token_count
             + 6 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
             + black_2_moves
         ];
 
-        for forward_board in forward_step_boards(&board, Token::parse_to_u8(Token::White), AiPhase::new(Phase::Set, 14)) {
-            assert!(expected_boards.contains(&forward_board));
+        for forward_board in forward_step_boards(SearchBoard::from_raw(board), Token::parse_to_u8(Token::White), AiPhase::new(Phase::Set, 14)) {
+            assert!(expected_boards.contains(&forward_board.to_raw()));
+        }
+    }
+
+    #[test]
+    fn test_capture_in_set_phase_keeps_token_counters() {
+        let board = SearchBoard::new(decode_positions("WWEEEEEEBBEEEEEEEEEEEEEE".to_string()).unwrap(), RuleSet::default());
+
+        for forward_board in forward_step_boards(board, Token::parse_to_u8(Token::White), AiPhase::new(Phase::Set, 4)) {
+            assert_eq!(forward_board, SearchBoard::new(forward_board.board(), RuleSet::default()));
+        }
+    }
+
+    #[test]
+    fn test_counters_with_diagonals() {
+        let twelve = RuleSet::new(Variant::TwelveMensMorris);
+        let board = SearchBoard::new(decode_positions("WEWWWEWEEWEWEWEWWEEEWEWB".to_string()).unwrap(), twelve);
+
+        for forward_board in forward_step_boards(board, Token::parse_to_u8(Token::White), AiPhase::new(Phase::Move, 30).with_rules(twelve)) {
+            assert_eq!(forward_board, SearchBoard::new(forward_board.board(), twelve));
+        }
+    }
+
+    #[test]
+    fn test_counters_with_double_capture() {
+        let rules = RuleSet::default().with_double_capture(true);
+        let board = SearchBoard::new(decode_positions("WWEEEWWEBBEBEEEBEEEEEEEE".to_string()).unwrap(), rules);
+        let forward_boards = forward_step_boards(board, Token::parse_to_u8(Token::White), AiPhase::new(Phase::Set, 8).with_rules(rules))
+            .collect::<Vec<SearchBoard>>();
+
+        assert!(forward_boards.iter().any(|forward_board| forward_board.board().get_number_of_tokens(0b10) == 2));
+        for forward_board in forward_boards {
+            assert_eq!(forward_board, SearchBoard::new(forward_board.board(), rules));
         }
     }
 }
@@ -0,0 +1,285 @@
+use std::{error::Error, fmt};
+use super::{
+    action::{list_actions, Action},
+    game_record::{GameRecord, Winner},
+    game_state::{GameState, Token},
+    notation::ParseActionError,
+    position::PositionError
+};
+
+/*
+    A saved game looks like a PGN file. It starts with a header of tags
+    followed by the move list in coordinate notation and the result:
+        [White "Human"]
+        [Black "Engine"]
+        [Difficulty "Normal"]
+        [Date "2024.07.01"]
+        [Result "1-0"]
+
+        1. d7 d6 2. g7 f6 3. a7xf6 ... 1-0
+
+    Games that don't start from the empty board additionally store the
+    start position as position string (see GameState) in the Position tag.
+*/
+#[derive(Clone, Default)]
+pub struct GameFile {
+    pub white: String,
+    pub black: String,
+    pub difficulty: Option<String>,
+    pub date: String,
+    pub record: GameRecord
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GameFileError {
+    InvalidTag(String),
+    InvalidPosition(PositionError),
+    InvalidAction(String, ParseActionError),
+    IllegalAction(String)
+}
+
+impl fmt::Display for GameFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameFileError::InvalidTag(line) => write!(f, "invalid tag '{}'", line),
+            GameFileError::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+            GameFileError::InvalidAction(action, error) => write!(f, "invalid action '{}': {}", action, error),
+            GameFileError::IllegalAction(action) => write!(f, "illegal action '{}'", action)
+        }
+    }
+}
+
+impl Error for GameFileError {}
+
+fn result_of(winner: Option<&Winner>) -> &'static str {
+    match winner {
+        Some(Winner::White(_)) => "1-0",
+        Some(Winner::Black(_)) => "0-1",
+        Some(Winner::Draw(_)) => "1/2-1/2",
+        None => "*"
+    }
+}
+
+fn reason_of(winner: Option<&Winner>) -> &str {
+    match winner {
+        Some(Winner::White(reason)) | Some(Winner::Black(reason)) | Some(Winner::Draw(reason)) => reason,
+        None => ""
+    }
+}
+
+fn parse_tag(line: &str) -> Option<(&str, String)> {
+    let content = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = content.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name, value.replace("\\\"", "\"")))
+}
+
+pub fn write_game(game: &GameFile) -> String {
+    let mut output = String::new();
+    let winner = game.record.get_winner();
+    let mut push_tag = |name: &str, value: &str| {
+        output.push_str(&format!("[{} \"{}\"]\n", name, value.replace('"', "\\\"")));
+    };
+
+    push_tag("White", &game.white);
+    push_tag("Black", &game.black);
+    if let Some(difficulty) = game.difficulty.as_ref() {
+        push_tag("Difficulty", difficulty);
+    }
+    push_tag("Date", &game.date);
+    push_tag("Result", result_of(winner));
+    if !reason_of(winner).is_empty() {
+        push_tag("Termination", reason_of(winner));
+    }
+
+    let initial_state = game.record.get_initial_state();
+    if initial_state != GameState::default() {
+        push_tag("Position", &initial_state.to_string());
+    }
+    output.push('\n');
+
+    let mut line = String::new();
+    let mut game_state = initial_state;
+    for (index, action) in game.record.get_actions().enumerate() {
+        let mut word = String::new();
+        if game_state.get_player_turn() == Token::White {
+            word.push_str(&format!("{}. ", game_state.get_step_counter() / 2 + 1));
+        } else if index == 0 {
+            word.push_str(&format!("{}... ", game_state.get_step_counter() / 2 + 1));
+        }
+        word.push_str(&action.to_string());
+
+        if !line.is_empty() && line.len() + word.len() + 1 > 80 {
+            output.push_str(&line);
+            output.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+        game_state = game.record.get_entries()[index].game_state;
+    }
+
+    if !line.is_empty() {
+        line.push(' ');
+    }
+    line.push_str(result_of(winner));
+    output.push_str(&line);
+    output.push('\n');
+    output
+}
+
+pub fn read_game(input: &str) -> Result<GameFile, GameFileError> {
+    let mut game = GameFile::default();
+    let mut initial_state = GameState::default();
+    let mut result = "*".to_string();
+    let mut termination = String::new();
+    let mut lines = input.lines().map(str::trim).peekable();
+
+    while let Some(line) = lines.next_if(|line| line.is_empty() || line.starts_with('[')) {
+        if line.is_empty() {
+            continue;
+        }
+        let (name, value) = parse_tag(line).ok_or_else(|| GameFileError::InvalidTag(line.to_string()))?;
+        match name {
+            "White" => game.white = value,
+            "Black" => game.black = value,
+            "Difficulty" => game.difficulty = Some(value),
+            "Date" => game.date = value,
+            "Result" => result = value,
+            "Termination" => termination = value,
+            "Position" => initial_state = value.parse().map_err(GameFileError::InvalidPosition)?,
+            _ => ()
+        }
+    }
+
+    game.record = GameRecord::new(initial_state);
+    for word in lines.flat_map(str::split_whitespace) {
+        if matches!(word, "1-0" | "0-1" | "1/2-1/2" | "*") {
+            break;
+        }
+        let word = word.trim_start_matches(|char: char| char.is_ascii_digit() || char == '.');
+        if word.is_empty() {
+            continue;
+        }
+
+        let action = word.parse::<Action>().map_err(|error| GameFileError::InvalidAction(word.to_string(), error))?;
+        let game_state = game.record.get_game_state();
+        let is_legal = game.record.get_winner().is_none() && list_actions(
+            game_state.get_board(),
+            Token::parse_to_u8(game_state.get_player_turn()),
+            game_state.get_phase(),
+            None,
+            game_state.get_rules()
+        ).any(|possible_action| possible_action == action);
+        if !is_legal {
+            return Err(GameFileError::IllegalAction(word.to_string()));
+        }
+        game.record.apply_action(action);
+    }
+
+    if game.record.get_winner().is_none() {
+        match result.as_str() {
+            "1-0" => game.record.set_winner(Winner::White(termination)),
+            "0-1" => game.record.set_winner(Winner::Black(termination)),
+            "1/2-1/2" => game.record.set_winner(Winner::Draw(termination)),
+            _ => ()
+        }
+    }
+
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::{action::Action, game_record::{GameRecord, Winner}, game_state::{GameState, Token}, notation::ParseActionError, position::{decode_positions, PositionError}};
+    use super::{read_game, write_game, GameFile, GameFileError};
+
+    fn example_game() -> GameFile {
+        let mut record = GameRecord::default();
+        for action in ["d7", "d6", "g7", "f6", "a7xf6"] {
+            record.apply_action(action.parse().unwrap());
+        }
+        GameFile {
+            white: "Human".to_string(),
+            black: "Engine".to_string(),
+            difficulty: Some("Normal".to_string()),
+            date: "2024.07.01".to_string(),
+            record
+        }
+    }
+
+    #[test]
+    fn test_write_game() {
+        let expected = "[White \"Human\"]\n[Black \"Engine\"]\n[Difficulty \"Normal\"]\n[Date \"2024.07.01\"]\n[Result \"*\"]\n\n1. d7 d6 2. g7 f6 3. a7xf6 *\n";
+        assert_eq!(write_game(&example_game()), expected);
+    }
+
+    #[test]
+    fn test_read_game() {
+        let game = read_game(&write_game(&example_game())).unwrap();
+
+        assert_eq!(game.white, "Human");
+        assert_eq!(game.black, "Engine");
+        assert_eq!(game.difficulty, Some("Normal".to_string()));
+        assert_eq!(game.date, "2024.07.01");
+        assert_eq!(game.record.get_game_state(), example_game().record.get_game_state());
+        assert_eq!(game.record.get_actions().collect::<Vec<Action>>(), example_game().record.get_actions().collect::<Vec<Action>>());
+        assert_eq!(game.record.get_winner(), None);
+    }
+
+    #[test]
+    fn test_round_trip_with_position_and_result() {
+        let mut game_state = GameState::default();
+        game_state.set_board(decode_positions("EWWEBEEBEEEEEEEEWEEEEBEE".to_string()).unwrap());
+        game_state.change_player();
+        for _ in 0..21 {
+            game_state.increase_step_counter();
+        }
+        let mut record = GameRecord::new(game_state);
+        record.apply_action("a7-a4".parse().unwrap());
+        record.apply_action("d5-g1xd1".parse().unwrap());
+        let game = GameFile { white: "Engine".to_string(), black: "Human \"Max\"".to_string(), record, ..Default::default() };
+
+        let written = write_game(&game);
+        assert!(written.contains("[Result \"1-0\"]"));
+        assert!(written.contains("[Position \"EWWEBEEBEEEEEEEEWEEEEBEE b 0 0 21 0\"]"));
+        assert!(written.contains("11... a7-a4 12. d5-g1xd1 1-0"));
+
+        let read = read_game(&written).unwrap();
+        assert_eq!(read.black, "Human \"Max\"");
+        assert_eq!(read.record.get_initial_state(), game_state);
+        assert_eq!(read.record.get_game_state().get_player_turn(), Token::Black);
+        assert_eq!(read.record.get_winner(), Some(&Winner::White("".to_string())));
+    }
+
+    #[test]
+    fn test_round_trip_with_variant() {
+        let game_state: GameState = "WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 24 0 twelve".parse().unwrap();
+        let mut record = GameRecord::new(game_state);
+        /* the diagonal move is only legal in Twelve Men's Morris */
+        record.apply_action("g1-f2".parse().unwrap());
+        let written = write_game(&GameFile { record, ..Default::default() });
+        assert!(written.contains("[Position \"WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 24 0 twelve\"]"));
+
+        let read = read_game(&written).unwrap();
+        assert_eq!(read.record.get_initial_state(), game_state);
+        assert_eq!(read.record.get_actions().count(), 1);
+        assert!(read_game(&written.replace(" twelve", "")).is_err());
+    }
+
+    #[test]
+    fn test_read_game_result_without_final_position() {
+        let game = read_game("[Result \"0-1\"]\n[Termination \"White attempted illegal move\"]\n\n1. d7 *").unwrap();
+        assert_eq!(game.record.get_winner(), Some(&Winner::Black("White attempted illegal move".to_string())));
+    }
+
+    #[test]
+    fn test_read_game_errors() {
+        assert_eq!(read_game("[White Human]").err(), Some(GameFileError::InvalidTag("[White Human]".to_string())));
+        assert_eq!(read_game("[Position \"EEE w 9 9 0 0\"]").err(), Some(GameFileError::InvalidPosition(PositionError::InvalidLength(3))));
+        assert_eq!(read_game("1. d4").err(), Some(GameFileError::InvalidAction("d4".to_string(), ParseActionError::InvalidCoordinate("d4".to_string()))));
+        assert_eq!(read_game("1. d7 d7").err(), Some(GameFileError::IllegalAction("d7".to_string())));
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use super::{
+    action::Action,
+    game_state::{GameState, Phase, Token},
+    position::Board
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Winner {
+    White(String),
+    Black(String),
+    Draw(String)
+}
+
+/* counted tells whether the game state was added to the repetition counter */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordEntry {
+    pub action: Action,
+    pub game_state: GameState,
+    counted: bool
+}
+
+/*
+    The record keeps every applied action together with the game state
+    after it. Undone entries stay in the record until a new action is
+    applied, so they can be redone. The repetition counter and the winner
+    always describe the current position of the record.
+*/
+#[derive(Clone, Default)]
+pub struct GameRecord {
+    initial_state: GameState,
+    entries: Vec<RecordEntry>,
+    current: usize,
+    repetition: HashMap<(Board, Token), u8>,
+    winner: Option<Winner>
+}
+
+impl GameRecord {
+    pub fn new(initial_state: GameState) -> Self {
+        let mut record = GameRecord {
+            initial_state,
+            ..Default::default()
+        };
+        /* a game started in the move phase has already been in its initial position once */
+        if initial_state.get_phase() == Phase::Move {
+            record.repetition.insert((initial_state.get_board(), initial_state.get_player_turn()), 1);
+        }
+        record
+    }
+
+    pub fn get_initial_state(&self) -> GameState {
+        self.initial_state
+    }
+
+    pub fn get_game_state(&self) -> GameState {
+        match self.current {
+            0 => self.initial_state,
+            current => self.entries[current - 1].game_state
+        }
+    }
+
+    pub fn get_winner(&self) -> Option<&Winner> {
+        self.winner.as_ref()
+    }
+
+    pub fn set_winner(&mut self, winner: Winner) {
+        self.winner = Some(winner);
+    }
+
+    pub fn get_entries(&self) -> &[RecordEntry] {
+        &self.entries[..self.current]
+    }
+
+    pub fn get_actions(&self) -> impl Iterator<Item = Action> + '_ {
+        self.get_entries().iter().map(|entry| entry.action)
+    }
+
+    pub fn apply_action(&mut self, action: Action) {
+        let mut game_state = self.get_game_state();
+        game_state.apply_action(&action);
+
+        self.entries.truncate(self.current);
+        self.entries.push(RecordEntry { action, game_state, counted: false });
+        self.current += 1;
+        self.enter_current();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.current < self.entries.len()
+    }
+
+    pub fn undo(&mut self) -> Option<Action> {
+        if !self.can_undo() {
+            return None;
+        }
+
+        let entry = self.entries[self.current - 1];
+        let key = (entry.game_state.get_board(), entry.game_state.get_player_turn());
+        if let Some(cnt) = self.repetition.get_mut(&key).filter(|_| entry.counted) {
+            *cnt -= 1;
+            if *cnt == 0 {
+                self.repetition.remove(&key);
+            }
+        }
+        self.winner = None;
+        self.current -= 1;
+        Some(self.entries[self.current].action)
+    }
+
+    pub fn redo(&mut self) -> Option<Action> {
+        if !self.can_redo() {
+            return None;
+        }
+
+        self.current += 1;
+        self.enter_current();
+        Some(self.entries[self.current - 1].action)
+    }
+
+    fn enter_current(&mut self) {
+        let game_state = self.get_game_state();
+        self.entries[self.current - 1].counted = false;
+
+        if let Some(reason) = game_state.get_draw_reason() {
+            self.winner = Some(Winner::Draw(reason.to_string()));
+        } else if game_state.is_game_over() {
+            self.winner = Some(match game_state.get_player_turn() {
+                Token::White => Winner::Black("".to_string()),
+                Token::Black => Winner::White("".to_string()),
+                _ => unreachable!()
+            });
+        } else if game_state.get_phase() == Phase::Move {
+            let cnt = self.repetition.entry((game_state.get_board(), game_state.get_player_turn())).or_insert(0);
+            *cnt += 1;
+            self.entries[self.current - 1].counted = true;
+            if *cnt >= 3 {
+                self.winner = Some(Winner::Draw("Position repeated thrice".to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::{action::Action, game_state::{GameState, Phase, Token}, position::decode_positions, rules::{RuleSet, Variant}};
+    use super::RecordEntry;
+    use super::{GameRecord, Winner};
+
+    fn play(record: &mut GameRecord, actions: &[Action]) {
+        for action in actions {
+            record.apply_action(*action);
+        }
+    }
+
+    #[test]
+    fn test_apply_action() {
+        let mut record = GameRecord::default();
+        play(&mut record, &[Action::new(None, 0, None), Action::new(None, 8, None)]);
+
+        let game_state = record.get_game_state();
+        assert_eq!(game_state.get_board(), decode_positions("WEEEEEEEBEEEEEEEEEEEEEEE".to_string()).unwrap());
+        assert_eq!(game_state.get_player_turn(), Token::White);
+        assert_eq!(game_state.get_step_counter(), 2);
+        assert_eq!(record.get_actions().collect::<Vec<Action>>(), vec![Action::new(None, 0, None), Action::new(None, 8, None)]);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut record = GameRecord::default();
+        assert!(!record.can_undo());
+        assert_eq!(record.undo(), None);
+
+        play(&mut record, &[Action::new(None, 0, None), Action::new(None, 8, None), Action::new(None, 1, None)]);
+        let state_after_three = record.get_game_state();
+
+        assert_eq!(record.undo(), Some(Action::new(None, 1, None)));
+        assert_eq!(record.get_game_state().get_step_counter(), 2);
+        assert_eq!(record.get_game_state().get_player_turn(), Token::White);
+        assert!(record.can_redo());
+
+        assert_eq!(record.redo(), Some(Action::new(None, 1, None)));
+        assert_eq!(record.get_game_state(), state_after_three);
+        assert!(!record.can_redo());
+
+        record.undo();
+        record.undo();
+        record.apply_action(Action::new(None, 16, None));
+        assert!(!record.can_redo());
+        assert_eq!(record.get_entries().len(), 2);
+        assert_eq!(record.get_game_state().get_board(), decode_positions("WEEEEEEEEEEEEEEEBEEEEEEE".to_string()).unwrap());
+
+        while record.undo().is_some() {}
+        assert_eq!(record.get_game_state(), GameState::default());
+    }
+
+    #[test]
+    fn test_undo_restores_phase() {
+        let mut record = GameRecord::default();
+        for position in [0, 1, 2, 3, 4, 5, 6, 7, 9, 8, 11, 10, 13, 12, 15, 14, 17, 16] {
+            record.apply_action(Action::new(None, position, None));
+        }
+        assert_eq!(record.get_game_state().get_phase(), Phase::Move);
+
+        record.undo();
+        assert_eq!(record.get_game_state().get_phase(), Phase::Set);
+        record.redo();
+        assert_eq!(record.get_game_state().get_phase(), Phase::Move);
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game_state = GameState::default();
+        game_state.set_board(decode_positions("WWEWBBEBEEEEEEEEWEEEEBEE".to_string()).unwrap());
+        for _ in 0..18 {
+            game_state.increase_step_counter();
+        }
+        let mut record = GameRecord::new(game_state);
+        let shuffle = [
+            Action::new(Some(16), 17, None),
+            Action::new(Some(21), 20, None),
+            Action::new(Some(17), 16, None),
+            Action::new(Some(20), 21, None)
+        ];
+
+        play(&mut record, &shuffle);
+        assert_eq!(record.get_winner(), None);
+        play(&mut record, &shuffle);
+        assert_eq!(record.get_game_state().get_reversible_plies(), 8);
+        assert_eq!(record.get_winner(), Some(&Winner::Draw("Position repeated thrice".to_string())));
+
+        record.undo();
+        assert_eq!(record.get_winner(), None);
+        record.redo();
+        assert_eq!(record.get_winner(), Some(&Winner::Draw("Position repeated thrice".to_string())));
+    }
+
+    #[test]
+    fn test_undo_redo_draw_by_rules() {
+        let game_state: GameState = "WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 18 0 nine+draw4".parse().unwrap();
+        let mut record = GameRecord::new(game_state);
+        let shuffle = [
+            Action::new(Some(16), 17, None),
+            Action::new(Some(21), 20, None),
+            Action::new(Some(17), 16, None),
+            Action::new(Some(20), 21, None)
+        ];
+        let count = |record: &GameRecord| record.repetition.get(&(game_state.get_board(), Token::White)).copied();
+
+        assert_eq!(count(&record), Some(1));
+        play(&mut record, &shuffle);
+        assert_eq!(count(&record), Some(2));
+        play(&mut record, &shuffle);
+        assert_eq!(record.get_winner(), Some(&Winner::Draw("No capture within the move limit".to_string())));
+        assert!(!record.get_entries().last().is_some_and(|entry: &RecordEntry| entry.counted));
+        assert_eq!(count(&record), Some(2));
+
+        /* the draw wasn't counted, so undoing it keeps the count of the earlier positions */
+        record.undo();
+        assert_eq!(record.get_winner(), None);
+        assert_eq!(count(&record), Some(2));
+        record.redo();
+        assert_eq!(count(&record), Some(2));
+        while record.undo().is_some() {}
+        assert_eq!(count(&record), Some(1));
+        assert_eq!(record.repetition.len(), 1);
+    }
+
+    #[test]
+    fn test_three_mens_mill_in_set_phase() {
+        let mut record = GameRecord::new(GameState::new(RuleSet::new(Variant::ThreeMensMorris)));
+        play(&mut record, &[Action::new(None, 7, None), Action::new(None, 15, None), Action::new(None, 0, None), Action::new(None, 8, None)]);
+        assert_eq!(record.get_winner(), None);
+
+        record.apply_action(Action::new(None, 1, Some(15)));
+        assert_eq!(record.get_game_state().get_phase(), Phase::Set);
+        assert!(record.get_game_state().is_game_over());
+        assert_eq!(record.get_winner(), Some(&Winner::White("".to_string())));
+        record.undo();
+        assert_eq!(record.get_winner(), None);
+    }
+
+    #[test]
+    fn test_winner() {
+        let mut game_state = GameState::default();
+        game_state.set_board(decode_positions("EWWEBEEBEEEEEEEEWEEEEBEE".to_string()).unwrap());
+        for _ in 0..18 {
+            game_state.increase_step_counter();
+        }
+        let mut record = GameRecord::new(game_state);
+
+        record.apply_action(Action::new(Some(16), 17, None));
+        assert_eq!(record.get_game_state().get_reversible_plies(), 1);
+        record.undo();
+        record.apply_action(Action::new(Some(16), 3, Some(4)));
+        assert_eq!(record.get_game_state().get_reversible_plies(), 0);
+        assert_eq!(record.get_winner(), Some(&Winner::White("".to_string())));
+        record.undo();
+        assert_eq!(record.get_winner(), None);
+    }
+}
@@ -1,4 +1,11 @@
 use core::fmt;
+use std::str::FromStr;
+use super::{
+    action::{list_actions, Action},
+    position::{decode_positions, encode_positions, Board, PositionError},
+    r#move::apply_action,
+    rules::RuleSet
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Token {
@@ -57,29 +64,42 @@ impl Token {
             13      12       11
         5            4               3
 */
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct GameState {
-    board: u64,
+    board: Board,
     player_turn: Token,
-    step_counter: u8
+    step_counter: u16,
+    reversible_plies: u16,
+    rules: RuleSet
 }
 
 impl Default for GameState {
     fn default() -> GameState {
+        GameState::new(RuleSet::default())
+    }
+}
+
+impl GameState {
+    /* the empty board at the start of a game with the rules */
+    pub fn new(rules: RuleSet) -> GameState {
         GameState {
-            board: 0b0,
+            board: Board::default(),
             player_turn: Token::White,
-            step_counter: 0
+            step_counter: 0,
+            reversible_plies: 0,
+            rules
         }
     }
-}
 
-impl GameState {
-    pub fn get_board(&self) -> u64 {
+    pub fn get_rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    pub fn get_board(&self) -> Board {
         self.board
     }
 
-    pub fn set_board(&mut self, new_board: u64) {
+    pub fn set_board(&mut self, new_board: Board) {
         self.board = new_board;
     }
 
@@ -95,42 +115,197 @@ impl GameState {
         }
     }
 
-    pub fn get_step_counter(&self) -> u8 {
+    pub fn get_step_counter(&self) -> u16 {
         self.step_counter
     }
 
     pub fn increase_step_counter(&mut self) {
-        self.step_counter += 1;
+        self.step_counter = self.step_counter.saturating_add(1);
     }
 
     pub fn get_phase(&self) -> Phase {
-        if self.step_counter < 18 {
+        if self.step_counter < self.rules.get_set_plies() as u16 {
             Phase::Set
         } else {
             Phase::Move
         }
     }
+
+    pub fn get_reversible_plies(&self) -> u16 {
+        self.reversible_plies
+    }
+
+    pub fn increase_reversible_plies(&mut self) {
+        self.reversible_plies = self.reversible_plies.saturating_add(1);
+    }
+
+    pub fn reset_reversible_plies(&mut self) {
+        self.reversible_plies = 0;
+    }
+
+    pub fn get_tokens_in_hand(&self, token: Token) -> u8 {
+        let placed_tokens = match token {
+            Token::White => self.step_counter.div_ceil(2),
+            Token::Black => self.step_counter / 2,
+            Token::None => return 0
+        };
+        let tokens = self.rules.get_tokens_per_player();
+        tokens - placed_tokens.min(tokens as u16) as u8
+    }
+
+    pub fn apply_action(&mut self, action: &Action) {
+        if self.get_phase() == Phase::Set || action.beatable_position.is_some() {
+            self.reset_reversible_plies();
+        } else {
+            self.increase_reversible_plies();
+        }
+        self.board = apply_action(self.board, action, Token::parse_to_u8(self.player_turn));
+        self.change_player();
+        self.increase_step_counter();
+    }
+
+    fn is_blocked(&self) -> bool {
+        list_actions(self.board, Token::parse_to_u8(self.player_turn), Phase::Move, None, self.rules).next().is_none()
+    }
+
+    /*
+        A player with fewer than three tokens on the board and in hand has
+        lost, which during the set phase only happens after a capture in
+        Three Men's Morris.
+    */
+    pub fn is_game_over(&self) -> bool {
+        let tokens = self.board.get_number_of_tokens(Token::parse_to_u8(self.player_turn)) + self.get_tokens_in_hand(self.player_turn);
+        tokens < 3 || (self.get_phase() == Phase::Move && (self.is_blocked() || self.rules.is_no_capture_draw(self.reversible_plies)))
+    }
+
+    /*
+        The reason if the game is over with a draw by the rules, i.e. the
+        player to move is blocked at their first move or the players moved
+        without capturing for too long. A player that lost the game by the
+        last move doesn't draw.
+    */
+    pub fn get_draw_reason(&self) -> Option<&'static str> {
+        if self.get_phase() != Phase::Move || self.board.get_number_of_tokens(Token::parse_to_u8(self.player_turn)) < 3 {
+            return None;
+        }
+        if self.is_blocked() {
+            return self.rules.is_blocked_draw(self.step_counter).then_some("Blocked after the set phase");
+        }
+        self.rules.is_no_capture_draw(self.reversible_plies).then_some("No capture within the move limit")
+    }
+}
+
+/*
+    A position is written similar to FEN as six fields separated by spaces:
+        BWWEWBBBBEEWWWEEBEEEEEEE w 0 0 25 3
+    1. the 24 positions as in encode_positions
+    2. the player to move (w or b)
+    3. the number of white tokens left to place
+    4. the number of black tokens left to place
+    5. the step counter (number of plies played)
+    6. the number of reversible plies, i.e. moves without a capture since
+       the last placement or capture. Only positions within these plies
+       can repeat the current one.
+    Positions of other rules than Nine Men's Morris have the rules (see
+    RuleSet) as seventh field, e.g. "twelve".
+*/
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, 
+            "{} {} {} {} {} {}", 
+            encode_positions(self.board),
+            if self.player_turn == Token::Black { "b" } else { "w" },
+            self.get_tokens_in_hand(Token::White),
+            self.get_tokens_in_hand(Token::Black),
+            self.step_counter,
+            self.reversible_plies
+        )?;
+        if self.rules != RuleSet::default() {
+            write!(f, " {}", self.rules)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameState {
+    type Err = PositionError;
+
+    fn from_str(position: &str) -> Result<Self, Self::Err> {
+        let rules = position.split_whitespace().nth(6)
+            .map(|field| field.parse::<RuleSet>().map_err(PositionError::InvalidRules))
+            .transpose()?;
+        let tokens = rules.unwrap_or_default().get_tokens_per_player();
+        let mut fields = position.split_whitespace();
+        let mut next_field = |name: &'static str| fields.next().ok_or(PositionError::MissingField(name));
+
+        let board = decode_positions(next_field("board")?.to_string())?;
+        let player_turn = match next_field("player to move")? {
+            "w" => Token::White,
+            "b" => Token::Black,
+            other => return Err(PositionError::InvalidField("player to move", other.to_string()))
+        };
+        let mut parse_number = |name: &'static str, max: u16| {
+            let field = next_field(name)?;
+            field.parse::<u16>().ok()
+                .filter(|number| *number <= max)
+                .ok_or_else(|| PositionError::InvalidField(name, field.to_string()))
+        };
+        let white_in_hand = parse_number("white tokens in hand", tokens as u16)? as u8;
+        let black_in_hand = parse_number("black tokens in hand", tokens as u16)? as u8;
+        let step_counter = parse_number("step counter", u16::MAX)?;
+        let reversible_plies = parse_number("reversible plies", u16::MAX)?;
+        let remaining = fields.skip(rules.is_some() as usize).collect::<Vec<&str>>();
+        if !remaining.is_empty() {
+            return Err(PositionError::TrailingInput(remaining.join(" ")));
+        }
+
+        let rules = rules.unwrap_or_default();
+        let game_state = GameState { board, player_turn, step_counter, reversible_plies, rules };
+
+        if let Some(position) = (0..24).find(|position| board.get_token_at(*position) != 0b00 && !rules.get_geometry().has_position(*position)) {
+            return Err(PositionError::OutsideBoard(position));
+        }
+
+        for (token, in_hand) in [(Token::White, white_in_hand), (Token::Black, black_in_hand)] {
+            if board.get_number_of_tokens(Token::parse_to_u8(token)) + in_hand > tokens {
+                return Err(PositionError::TooManyTokens(token.to_string()));
+            }
+            if game_state.get_tokens_in_hand(token) != in_hand {
+                return Err(PositionError::InconsistentField("tokens in hand", in_hand.to_string()));
+            }
+        }
+        if game_state.get_phase() == Phase::Set && (player_turn == Token::Black) != (step_counter % 2 == 1) {
+            return Err(PositionError::InconsistentField("player to move", player_turn.to_string()));
+        }
+        if reversible_plies > step_counter.saturating_sub(rules.get_set_plies() as u16) {
+            return Err(PositionError::InconsistentField("reversible plies", reversible_plies.to_string()));
+        }
+
+        Ok(game_state)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::logic::{game_state::{GameState, Phase, Token}, position::decode_positions};
+    use crate::logic::{action::Action, game_state::{GameState, Phase, Token}, position::{decode_positions, Board, PositionError}, rules::{ParseRulesError, RuleSet, Variant}};
 
     #[test]
     fn test_game_state() {
         let game = GameState::default();
 
-        assert_eq!(game.board, 0b0);
+        assert_eq!(game.board, Board::default());
         assert_eq!(game.player_turn, Token::White);
-        assert_eq!(game.step_counter, 0)
+        assert_eq!(game.step_counter, 0);
+        assert_eq!(game.reversible_plies, 0);
     }
 
     #[test]
     fn test_get_board() {
         let mut game = GameState::default();
-        assert_eq!(game.get_board(), 0b0);
+        assert_eq!(game.get_board(), Board::default());
 
-        let new_board = decode_positions("BEEEEEEEEEEEEEEEEEEEEEEE".to_string());
+        let new_board = decode_positions("BEEEEEEEEEEEEEEEEEEEEEEE".to_string()).unwrap();
         game.set_board(new_board);
         assert_eq!(game.get_board(), new_board);
     }
@@ -138,9 +313,9 @@ mod tests {
     #[test]
     fn test_set_board() {
         let mut game = GameState::default();
-        assert_eq!(game.get_board(), 0b0);
+        assert_eq!(game.get_board(), Board::default());
 
-        let new_board = decode_positions("BEEEEEEEEEEEEEEEEEEEEEEE".to_string());
+        let new_board = decode_positions("BEEEEEEEEEEEEEEEEEEEEEEE".to_string()).unwrap();
         game.set_board(new_board);
         assert_eq!(game.get_board(), new_board);
     }
@@ -174,11 +349,167 @@ mod tests {
         assert_eq!(game.get_step_counter(), step_counter_before + 1);
     }
 
+    #[test]
+    fn test_long_game() {
+        let mut game: GameState = "WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 18 0".parse().unwrap();
+        let shuffle = [
+            Action::new(Some(16), 17, None),
+            Action::new(Some(21), 20, None),
+            Action::new(Some(17), 16, None),
+            Action::new(Some(20), 21, None)
+        ];
+        for action in shuffle.iter().cycle().take(300) {
+            game.apply_action(action);
+            assert_eq!(game.get_phase(), Phase::Move);
+        }
+        assert_eq!(game.get_step_counter(), 318);
+        assert_eq!(game.get_reversible_plies(), 300);
+        assert_eq!((game.get_tokens_in_hand(Token::White), game.get_tokens_in_hand(Token::Black)), (0, 0));
+        assert!(!game.is_game_over());
+        assert_eq!(game.to_string(), "WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 318 300");
+        assert_eq!(game.to_string().parse::<GameState>(), Ok(game));
+    }
+
     #[test]
     fn test_get_phase() {
         let mut game = GameState::default();
         assert_eq!(game.get_phase(), Phase::Set);
         game.step_counter = 18;
         assert_eq!(game.get_phase(), Phase::Move);
+
+        let mut game = GameState::new(RuleSet::new(Variant::TwelveMensMorris));
+        game.step_counter = 18;
+        assert_eq!(game.get_phase(), Phase::Set);
+        assert_eq!((game.get_tokens_in_hand(Token::White), game.get_tokens_in_hand(Token::Black)), (3, 3));
+        game.step_counter = 24;
+        assert_eq!(game.get_phase(), Phase::Move);
+    }
+
+    #[test]
+    fn test_get_tokens_in_hand() {
+        let mut game = GameState::default();
+        assert_eq!((game.get_tokens_in_hand(Token::White), game.get_tokens_in_hand(Token::Black)), (9, 9));
+        game.step_counter = 5;
+        assert_eq!((game.get_tokens_in_hand(Token::White), game.get_tokens_in_hand(Token::Black)), (6, 7));
+        game.step_counter = 30;
+        assert_eq!((game.get_tokens_in_hand(Token::White), game.get_tokens_in_hand(Token::Black)), (0, 0));
+        assert_eq!(game.get_tokens_in_hand(Token::None), 0);
+    }
+
+    #[test]
+    fn test_reversible_plies() {
+        let mut game = GameState::default();
+        game.increase_reversible_plies();
+        game.increase_reversible_plies();
+        assert_eq!(game.get_reversible_plies(), 2);
+        game.reset_reversible_plies();
+        assert_eq!(game.get_reversible_plies(), 0);
+    }
+
+    #[test]
+    fn test_apply_action() {
+        let mut game = GameState::default();
+        game.apply_action(&Action::new(None, 0, None));
+        assert_eq!(game.to_string(), "WEEEEEEEEEEEEEEEEEEEEEEE b 8 9 1 0");
+
+        let mut game: GameState = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0".parse().unwrap();
+        game.apply_action(&Action::new(Some(16), 17, None));
+        assert_eq!(game.to_string(), "EWWEBEEBEEEEEEEEEWEEEBEE b 0 0 19 1");
+        game.apply_action(&Action::new(Some(21), 20, None));
+        game.apply_action(&Action::new(Some(17), 16, None));
+        assert_eq!(game.get_reversible_plies(), 3);
+        game.apply_action(&Action::new(Some(20), 21, None));
+        game.apply_action(&Action::new(Some(16), 3, Some(4)));
+        assert_eq!(game.to_string(), "EWWWEEEBEEEEEEEEEEEEEBEE b 0 0 23 0");
+    }
+
+    #[test]
+    fn test_is_game_over() {
+        assert!(!GameState::default().is_game_over());
+        assert!(!"EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0".parse::<GameState>().unwrap().is_game_over());
+        assert!("EWWWEEEBEEEEEEEEEEEEEBEE b 0 0 23 0".parse::<GameState>().unwrap().is_game_over());
+        assert!("BWBWBWBWEEEEEEEEEEEEEEEE w 0 0 18 0".parse::<GameState>().unwrap().is_game_over());
+        assert!(!"BWBWBWBWEEEEEEEEEEEEEEEE b 0 0 19 0".parse::<GameState>().unwrap().is_game_over());
+        /* a full board after the set phase of Twelve Men's Morris */
+        assert!("BWBWBWBWWBWBWBWBBWBWBWBW w 0 0 24 0 twelve".parse::<GameState>().unwrap().is_game_over());
+        /* without flying three tokens can be blocked, in Three Men's Morris a capture ends the game */
+        assert!("WWWBEEEBBEBEEEEEEEEEEEEE w 0 0 12 0 six".parse::<GameState>().unwrap().is_game_over());
+        assert!(!"WWWBEEEBBEBEEEEEEEEEEEEE w 0 0 18 0".parse::<GameState>().unwrap().is_game_over());
+        assert!("WWEEEEEWBEEEEEEEBEEEEEEE b 0 0 7 1 three".parse::<GameState>().unwrap().is_game_over());
+    }
+
+    #[test]
+    fn test_draw_by_rules() {
+        let full_board = "BWBWBWBWWBWBWBWBBWBWBWBW w 0 0 24 0 twelve";
+        assert_eq!(full_board.parse::<GameState>().unwrap().get_draw_reason(), None);
+        let game = format!("{}+blockeddraw", full_board).parse::<GameState>().unwrap();
+        assert!(game.is_game_over());
+        assert_eq!(game.get_draw_reason(), Some("Blocked after the set phase"));
+        /* later in the game a blocked player still loses */
+        let game = "WWWBEEEBBEBEEEEEEEEEEEEE w 0 0 20 2 six+blockeddraw".parse::<GameState>().unwrap();
+        assert!(game.is_game_over() && game.get_draw_reason().is_none());
+
+        let mut game = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 117 99 nine+draw50".parse::<GameState>().unwrap();
+        assert!(!game.is_game_over());
+        game.apply_action(&Action::new(Some(16), 17, None));
+        assert!(game.is_game_over());
+        assert_eq!(game.get_draw_reason(), Some("No capture within the move limit"));
+        assert!(!"EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 118 100".parse::<GameState>().unwrap().is_game_over());
+    }
+
+    #[test]
+    fn test_position_string() {
+        let game = GameState::default();
+        assert_eq!(game.to_string(), "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0");
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0".parse(), Ok(game));
+
+        let game = GameState {
+            board: decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string()).unwrap(),
+            player_turn: Token::Black,
+            step_counter: 25,
+            reversible_plies: 3,
+            rules: RuleSet::default()
+        };
+        assert_eq!(game.to_string(), "BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3");
+        assert_eq!("  BWWEWBBBBEEWWWEEBEEEEEEE   b 0 0 25 3 ".parse(), Ok(game));
+
+        let game = GameState {
+            board: decode_positions("WEEEEEEEBEEEEEEEEEEEEEEE".to_string()).unwrap(),
+            player_turn: Token::White,
+            step_counter: 2,
+            reversible_plies: 0,
+            rules: RuleSet::default()
+        };
+        assert_eq!(game.to_string().parse(), Ok(game));
+
+        let game = GameState::new(RuleSet::new(Variant::TwelveMensMorris));
+        assert_eq!(game.to_string(), "EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0 twelve");
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0 twelve".parse(), Ok(game));
+
+        let game = GameState::new(RuleSet::default().with_flying(false).with_no_capture_draw(Some(50)));
+        assert_eq!(game.to_string(), "EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 nine+noflying+draw50");
+        assert_eq!(game.to_string().parse(), Ok(game));
+    }
+
+    #[test]
+    fn test_position_string_errors() {
+        assert_eq!("".parse::<GameState>(), Err(PositionError::MissingField("board")));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE".parse::<GameState>(), Err(PositionError::MissingField("player to move")));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0".parse::<GameState>(), Err(PositionError::InvalidLength(23)));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEA w 9 9 0 0".parse::<GameState>(), Err(PositionError::InvalidCharacter('A')));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE x 9 9 0 0".parse::<GameState>(), Err(PositionError::InvalidField("player to move", "x".to_string())));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 10 9 0 0".parse::<GameState>(), Err(PositionError::InvalidField("white tokens in hand", "10".to_string())));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 -1 0".parse::<GameState>(), Err(PositionError::InvalidField("step counter", "-1".to_string())));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0".parse::<GameState>(), Err(PositionError::MissingField("reversible plies")));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 x".parse::<GameState>(), Err(PositionError::InvalidRules(ParseRulesError::UnknownVariant("x".to_string()))));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0 nine+draw0".parse::<GameState>(), Err(PositionError::InvalidRules(ParseRulesError::UnknownOption("draw0".to_string()))));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0 twelve x".parse::<GameState>(), Err(PositionError::TrailingInput("x".to_string())));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0".parse::<GameState>(), Err(PositionError::InvalidField("white tokens in hand", "12".to_string())));
+        assert_eq!("WWEEEEEEEEEEEEEEEEEEEEEE w 8 9 1 0".parse::<GameState>(), Err(PositionError::TooManyTokens("White".to_string())));
+        assert_eq!("EEEEEEEEEEEEEEEEWEEEEEEE b 5 6 1 0 six".parse::<GameState>(), Err(PositionError::OutsideBoard(16)));
+        assert_eq!("EEWEEEEEEEEEEEEEEEEEEEEE b 2 3 1 0 three".parse::<GameState>(), Err(PositionError::OutsideBoard(2)));
+        assert_eq!("EEEEEEEEEEEEEEEEEEEEEEEE b 9 9 1 0".parse::<GameState>(), Err(PositionError::InconsistentField("tokens in hand", "9".to_string())));
+        assert_eq!("WEEEEEEEEEEEEEEEEEEEEEEE w 8 9 1 0".parse::<GameState>(), Err(PositionError::InconsistentField("player to move", "White".to_string())));
+        assert_eq!("BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 8".parse::<GameState>(), Err(PositionError::InconsistentField("reversible plies", "8".to_string())));
     }
 }
@@ -1,156 +1,154 @@
-use crate::logic::position::reverse_token_of_board;
-
-const POSSIBLE_MILLS_WHITE: [u64; 16] = [
-    // horizontal mills
-    0b111100000000001100000000000000000000000000000000, // 7 0 1
-    0b000000000000000011110000000000110000000000000000, // 15 8 9
-    0b000000000000000000000000000000001111000000000011, // 23 16 17
-    0b000000000000110000000000000011000000000000001100, // 6 14 22
-    0b000011000000000000001100000000000000110000000000, // 18 10 2
-    0b000000000000000000000000000000000000001111110000, // 21 20 19
-    0b000000000000000000000011111100000000000000000000, // 13 12 11
-    0b000000111111000000000000000000000000000000000000, // 5 4 3
-    // vertical mills
-    0b000000000011111100000000000000000000000000000000, // 7 6 5
-    0b000000000000000000000000001111110000000000000000, // 15 14 13
-    0b000000000000000000000000000000000000000000111111, // 23 22 21
-    0b110000000000000011000000000000001100000000000000, // 0 8 16
-    0b000000001100000000000000110000000000000011000000, // 20 12 4
-    0b000000000000000000000000000000000011111100000000, // 17 18 19
-    0b000000000000000000111111000000000000000000000000, // 9 10 11
-    0b001111110000000000000000000000000000000000000000, // 1 2 3
-];
+use crate::logic::{position::Board, rules::RuleSet};
 
-const MILL_INDICES_FOR_POSITION: [(usize, usize); 24] = [
-    (0, 11),
-    (0, 15),
-    (4, 15),
-    (7, 15),
-    (7, 12),
-    (7, 8),
-    (3, 8),
-    (0, 8),
-    (1, 11),
-    (1, 14),
-    (4, 14),
-    (6, 14),
-    (6, 12),
-    (6, 9),
-    (3, 9),
-    (1, 9),
-    (2, 11),
-    (2, 13),
-    (4, 13),
-    (5, 13),
-    (5, 12),
-    (5, 10),
-    (3, 10),
-    (2, 10),
+/* the mills of Nine Men's Morris, first the horizontal and then the vertical ones */
+pub const MILLS: [[usize; 3]; 16] = [
+    [7, 0, 1],
+    [15, 8, 9],
+    [23, 16, 17],
+    [6, 14, 22],
+    [18, 10, 2],
+    [21, 20, 19],
+    [13, 12, 11],
+    [5, 4, 3],
+    [7, 6, 5],
+    [15, 14, 13],
+    [23, 22, 21],
+    [0, 8, 16],
+    [20, 12, 4],
+    [17, 18, 19],
+    [9, 10, 11],
+    [1, 2, 3]
 ];
 
-pub fn is_part_of_mill(board: u64, position: usize, token_type: u8) -> bool {
-    let (index1, index2) = MILL_INDICES_FOR_POSITION[position];
-    let possible_mill_position = POSSIBLE_MILLS_WHITE[index1];
-    let possible_mill_position2 = POSSIBLE_MILLS_WHITE[index2];
+fn reverse_token_of_mill(possible_mill: u64) -> u64 {
+    Board::from_raw(possible_mill).reverse_tokens().to_raw()
+}
+
+fn get_mill_of_token(mill_mask: u64, token_type: u8) -> u64 {
     if token_type == 0b11 {
-        (board & possible_mill_position) == POSSIBLE_MILLS_WHITE[index1]
-        || (board & possible_mill_position2) == POSSIBLE_MILLS_WHITE[index2]
-    } else if token_type == 0b10 {
-        (board & possible_mill_position) == reverse_token_of_board(POSSIBLE_MILLS_WHITE[index1]) 
-            || (board & possible_mill_position2) == reverse_token_of_board(POSSIBLE_MILLS_WHITE[index2]) 
+        mill_mask
     } else {
-        false
+        reverse_token_of_mill(mill_mask)
     }
 }
 
-pub fn is_mill_closing(pos_before: u64, pos_after: u64, token_type: u8) -> bool {
-    (0..16).any(|i| {
-        let possible_mill = if token_type == 0b11 {
-            POSSIBLE_MILLS_WHITE[i]
-        } else {
-            reverse_token_of_board(POSSIBLE_MILLS_WHITE[i])
-        };
+pub fn is_part_of_mill(board: Board, position: usize, token_type: u8, rules: RuleSet) -> bool {
+    if token_type != 0b11 && token_type != 0b10 {
+        return false
+    }
+    let board = board.to_raw();
+    let geometry = rules.get_geometry();
+    let mill_masks = geometry.get_mill_masks();
+    geometry.get_position_mills(position).iter().any(|index| {
+        let possible_mill_position = mill_masks[*index];
+        board & possible_mill_position == get_mill_of_token(possible_mill_position, token_type)
+    })
+}
 
-        let possible_mill_position = POSSIBLE_MILLS_WHITE[i];
+pub fn is_mill_closing(pos_before: Board, pos_after: Board, token_type: u8, rules: RuleSet) -> bool {
+    let (pos_before, pos_after) = (pos_before.to_raw(), pos_after.to_raw());
+    rules.get_geometry().get_mill_masks().iter().any(|possible_mill_position| {
+        let possible_mill = get_mill_of_token(*possible_mill_position, token_type);
         let is_mill_before = pos_before & possible_mill_position == possible_mill;
         let is_mill_after = pos_after & possible_mill_position == possible_mill;
         !is_mill_before && is_mill_after
     })
 }
 
-pub fn is_all_part_of_mill(board: u64, token_of_opponent: u8) -> bool {
-    let mut board_mod: u64 = board;
+/* the number of mills of the player that are closed by the action, which is 2 at most */
+pub fn count_closed_mills(pos_before: Board, pos_after: Board, token_type: u8, rules: RuleSet) -> usize {
+    let (pos_before, pos_after) = (pos_before.to_raw(), pos_after.to_raw());
+    rules.get_geometry().get_mill_masks().iter().filter(|possible_mill_position| {
+        let possible_mill = get_mill_of_token(**possible_mill_position, token_type);
+        pos_before & **possible_mill_position != possible_mill && pos_after & **possible_mill_position == possible_mill
+    }).count()
+}
+
+pub fn is_all_part_of_mill(board: Board, token_of_opponent: u8, rules: RuleSet) -> bool {
+    let mut board_mod: u64 = board.to_raw();
     !(0..24).any(|index| {
         let token: u8 = (board_mod & 0b11) as u8;
         board_mod >>= 2;
-        token == token_of_opponent && !is_part_of_mill(board, 23 - index, token_of_opponent)
+        token == token_of_opponent && !is_part_of_mill(board, 23 - index, token_of_opponent, rules)
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::logic::{mill_detection::{is_all_part_of_mill, is_mill_closing, is_part_of_mill}, position::{decode_positions, reverse_token_of_board}};
+    use crate::logic::{mill_detection::{is_all_part_of_mill, is_mill_closing, is_part_of_mill}, position::{decode_positions, Board}, rules::{RuleSet, Variant}};
+
+    #[test]
+    fn test_mills() {
+        for variant in Variant::ALL {
+            let geometry = RuleSet::new(variant).get_geometry();
+            for (mill, possible_mill) in geometry.get_mills().iter().zip(geometry.get_mill_masks()) {
+                let mut board = Board::from_raw(0);
+                mill.iter().for_each(|position| board.set_token_at(*position, 0b11));
+                assert_eq!(board.to_raw(), *possible_mill);
+            }
+        }
+    }
 
     #[test]
     fn test_is_part_of_mill() {
-        let board = 0b111111101010101111000010001111110000001000100000;
+        let board = Board::from_raw(0b111111101010101111000010001111110000001000100000);
         let now = std::time::Instant::now();
         
         for _ in 0..100000 {
-            assert!(is_part_of_mill(board, 0, 0b11));
-            assert!(is_part_of_mill(board, 7, 0b11));
-            assert!(is_part_of_mill(board, 1, 0b11));
-            assert!(is_part_of_mill(board, 13, 0b11));
-            assert!(is_part_of_mill(board, 14, 0b11));
-            assert!(is_part_of_mill(board, 15, 0b11));
-
-            assert!(!is_part_of_mill(board, 2, 0b11));
-            assert!(!is_part_of_mill(board, 8, 0b11));
-            assert!(!is_part_of_mill(board, 4, 0b11));
-            assert!(!is_part_of_mill(board, 5, 0b11));
-            assert!(!is_part_of_mill(board, 6, 0b11));
-            assert!(!is_part_of_mill(board, 9, 0b11));
+            assert!(is_part_of_mill(board, 0, 0b11, RuleSet::default()));
+            assert!(is_part_of_mill(board, 7, 0b11, RuleSet::default()));
+            assert!(is_part_of_mill(board, 1, 0b11, RuleSet::default()));
+            assert!(is_part_of_mill(board, 13, 0b11, RuleSet::default()));
+            assert!(is_part_of_mill(board, 14, 0b11, RuleSet::default()));
+            assert!(is_part_of_mill(board, 15, 0b11, RuleSet::default()));
+
+            assert!(!is_part_of_mill(board, 2, 0b11, RuleSet::default()));
+            assert!(!is_part_of_mill(board, 8, 0b11, RuleSet::default()));
+            assert!(!is_part_of_mill(board, 4, 0b11, RuleSet::default()));
+            assert!(!is_part_of_mill(board, 5, 0b11, RuleSet::default()));
+            assert!(!is_part_of_mill(board, 6, 0b11, RuleSet::default()));
+            assert!(!is_part_of_mill(board, 9, 0b11, RuleSet::default()));
         
-            assert!(is_part_of_mill(board, 3, 0b10));
-            assert!(is_part_of_mill(board, 4, 0b10));
-            assert!(is_part_of_mill(board, 5, 0b10));
+            assert!(is_part_of_mill(board, 3, 0b10, RuleSet::default()));
+            assert!(is_part_of_mill(board, 4, 0b10, RuleSet::default()));
+            assert!(is_part_of_mill(board, 5, 0b10, RuleSet::default()));
 
-            assert!(!is_part_of_mill(board, 1, 0b10));
-            assert!(!is_part_of_mill(board, 2, 0b10));
+            assert!(!is_part_of_mill(board, 1, 0b10, RuleSet::default()));
+            assert!(!is_part_of_mill(board, 2, 0b10, RuleSet::default()));
         }
         println!("Time elapsed: {:?}", now.elapsed());
 
-        assert!(!is_part_of_mill(board, 8, 0b10));
-        assert!(!is_part_of_mill(board, 9, 0b10));
-        assert!(!is_part_of_mill(board, 10, 0b10));
-        assert!(!is_part_of_mill(board, 19, 0b10));
-        assert!(!is_part_of_mill(board, 21, 0b10));
+        assert!(!is_part_of_mill(board, 8, 0b10, RuleSet::default()));
+        assert!(!is_part_of_mill(board, 9, 0b10, RuleSet::default()));
+        assert!(!is_part_of_mill(board, 10, 0b10, RuleSet::default()));
+        assert!(!is_part_of_mill(board, 19, 0b10, RuleSet::default()));
+        assert!(!is_part_of_mill(board, 21, 0b10, RuleSet::default()));
     }
 
     #[test]
     fn test_is_closing_mill() {
         // normal mill closing
-        let board_before = decode_positions("EWWEWEEEEEEEEWEEEBBBEEWE".to_string());
-        let board_after = decode_positions("EWWWEEEEEEEEEWEEEBBBEEWE".to_string());
+        let board_before = decode_positions("EWWEWEEEEEEEEWEEEBBBEEWE".to_string()).unwrap();
+        let board_after = decode_positions("EWWWEEEEEEEEEWEEEBBBEEWE".to_string()).unwrap();
         // zwick mill closing
-        let board_before2 = decode_positions("EWWWEEEEEWEWWEEEBBBEEWE".to_string());
-        let board_after2 = decode_positions("EWEWEEEEEWWWWEEEBBBEEWE".to_string());
+        let board_before2 = decode_positions("EEWWWEEEEEWEWWEEEBBBEEWE".to_string()).unwrap();
+        let board_after2 = decode_positions("EEWEWEEEEEWWWWEEEBBBEEWE".to_string()).unwrap();
         // normal move
-        let board_before3 = decode_positions("EWWWEEEEEWEWWEEEBBBEEWE".to_string());
-        let board_after3 = decode_positions("EWWEWEEEEWEWWEEEBBBEEWE".to_string());
-
-        assert!(is_mill_closing(board_before, board_after, 0b11));
-        assert!(is_mill_closing(board_before2, board_after2, 0b11));
-        assert!(!is_mill_closing(board_before3, board_after3, 0b11));
-        assert!(is_mill_closing(reverse_token_of_board(board_before), reverse_token_of_board(board_after), 0b10));
-        assert!(is_mill_closing(reverse_token_of_board(board_before2), reverse_token_of_board(board_after2), 0b10));
-        assert!(!is_mill_closing(reverse_token_of_board(board_before3), reverse_token_of_board(board_after3), 0b10));
+        let board_before3 = decode_positions("EEWWWEEEEEWEWWEEEBBBEEWE".to_string()).unwrap();
+        let board_after3 = decode_positions("EEWWEWEEEEWEWWEEEBBBEEWE".to_string()).unwrap();
+
+        assert!(is_mill_closing(board_before, board_after, 0b11, RuleSet::default()));
+        assert!(is_mill_closing(board_before2, board_after2, 0b11, RuleSet::default()));
+        assert!(!is_mill_closing(board_before3, board_after3, 0b11, RuleSet::default()));
+        assert!(is_mill_closing(board_before.reverse_tokens(), board_after.reverse_tokens(), 0b10, RuleSet::default()));
+        assert!(is_mill_closing(board_before2.reverse_tokens(), board_after2.reverse_tokens(), 0b10, RuleSet::default()));
+        assert!(!is_mill_closing(board_before3.reverse_tokens(), board_after3.reverse_tokens(), 0b10, RuleSet::default()));
     }
 
     #[test]
     fn test_is_all_part_of_mill() {
-        let board = decode_positions("WWEEEBBBWEEEEBBBWEEEEEEE".to_string());
-        assert!(is_all_part_of_mill(board, 0b10));
-        assert!(!is_all_part_of_mill(board, 0b11));
+        let board = decode_positions("WWEEEBBBWEEEEBBBWEEEEEEE".to_string()).unwrap();
+        assert!(is_all_part_of_mill(board, 0b10, RuleSet::default()));
+        assert!(!is_all_part_of_mill(board, 0b11, RuleSet::default()));
     }
 }
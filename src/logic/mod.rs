@@ -1,7 +1,13 @@
 pub mod action;
+pub mod game_file;
+pub mod game_record;
 pub mod game_state;
 pub mod mill_detection;
 pub mod r#move;
+pub mod notation;
+pub mod perft;
 pub mod position;
+pub mod rules;
+pub mod symmetry;
 pub mod forward_boards;
 pub mod move_token_count;
@@ -1,9 +1,11 @@
 use crate::logic::{
     action::{Action, Move}, 
     mill_detection::{is_all_part_of_mill, is_part_of_mill}, 
-    position::{get_token_at, negate_token, set_token_at}
+    position::{negate_token, Board},
+    rules::RuleSet
 };
 
+/* the neighbors of Nine Men's Morris, filled up with 24 */
 pub const NEIGHBORS: [[usize; 4]; 24] = [
     [1, 7, 8, 24],
     [0, 2, 24, 24],
@@ -31,75 +33,81 @@ pub const NEIGHBORS: [[usize; 4]; 24] = [
     [16, 22, 24, 24],
 ];
 
-pub fn is_move_valid(start_position: usize, end_position: usize, end_token: u8, number_of_token_type: u8) -> bool {
+pub fn is_move_valid(start_position: usize, end_position: usize, end_token: u8, number_of_token_type: u8, rules: RuleSet) -> bool {
     if end_token != 0b00 {
         return false
     }
     
-    if number_of_token_type == 3 || is_neighbor(start_position, end_position) {
+    let geometry = rules.get_geometry();
+    if rules.can_fly(number_of_token_type) && geometry.has_position(end_position) {
         return true
     }
-    false
+    geometry.is_neighbor(start_position, end_position)
 }
 
-fn is_neighbor(position1: usize, position2: usize) -> bool {
-    NEIGHBORS[position1].contains(&position2)
-}
-
-pub fn apply_move(board: &u64, r#move: &Move, token_type: u8) -> u64 {
-    let mut new_board = *board;
-    if r#move.start_position.is_some() {
-        new_board = set_token_at(*board, r#move.start_position.unwrap(), 0b00);
+pub fn apply_move(board: Board, r#move: &Move, token_type: u8) -> Board {
+    let mut new_board = board;
+    if let Some(start_position) = r#move.start_position {
+        new_board.set_token_at(start_position, 0b00);
     }
-    set_token_at(new_board, r#move.end_position, token_type)
+    new_board.set_token_at(r#move.end_position, token_type);
+    new_board
 }
 
-pub fn apply_action(board: &u64, action: &Action, token_type: u8) -> u64 {
-    let mut new_board = *board;
-    if action.start_position.is_some() {
-        new_board = set_token_at(*board, action.start_position.unwrap(), 0b00);
+pub fn apply_action(board: Board, action: &Action, token_type: u8) -> Board {
+    let mut new_board = board;
+    if let Some(start_position) = action.start_position {
+        new_board.set_token_at(start_position, 0b00);
     }
-    new_board = set_token_at(new_board, action.end_position, token_type);
-    if action.beatable_position.is_some() {
-        new_board = set_token_at(new_board, action.beatable_position.unwrap(), 0b00);
+    new_board.set_token_at(action.end_position, token_type);
+    for beatable_position in action.get_captures() {
+        new_board.set_token_at(beatable_position, 0b00);
     }
     new_board
 }
 
-pub fn is_beat_possible(board: u64, position: usize, token_current_player: u8) -> bool {
+pub fn is_beat_possible(board: Board, position: usize, token_current_player: u8, rules: RuleSet) -> bool {
     let token_of_opponent: u8 = negate_token(token_current_player);
     
-    if get_token_at(board, position) != token_of_opponent {
+    if board.get_token_at(position) != token_of_opponent {
         return false
     }
     
-    if !is_part_of_mill(board, position, token_of_opponent) {
+    if rules.can_capture_from_mills() || !is_part_of_mill(board, position, token_of_opponent, rules) {
         return true
     }
-    is_all_part_of_mill(board, token_of_opponent)
+    is_all_part_of_mill(board, token_of_opponent, rules)
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::logic::{action::{Action, Move}, game_state::Token, r#move::{apply_action, apply_move, is_beat_possible, is_move_valid, is_neighbor}, position::{decode_positions, set_token_at}};
+    use crate::logic::{action::{Action, Move}, game_state::Token, r#move::{apply_action, apply_move, is_beat_possible, is_move_valid}, position::{decode_positions, Board}, rules::{RuleSet, Variant}};
     
     #[test]
     fn test_is_move_valid() {
 
         // move phase
-        assert_eq!(is_move_valid(7, 6, 0b11, 9), false);
-        assert_eq!(is_move_valid(7, 0, 0b00, 9), true);
-        assert_eq!(is_move_valid(8, 16, 0b00, 9), true);
-        assert_eq!(is_move_valid(9, 1, 0b00, 9), false);
+        assert_eq!(is_move_valid(7, 6, 0b11, 9, RuleSet::default()), false);
+        assert_eq!(is_move_valid(7, 0, 0b00, 9, RuleSet::default()), true);
+        assert_eq!(is_move_valid(8, 16, 0b00, 9, RuleSet::default()), true);
+        assert_eq!(is_move_valid(9, 1, 0b00, 9, RuleSet::default()), false);
 
         // end phase
-        assert_eq!(is_move_valid(4, 23, 0b00, 3), true);
-        assert_eq!(is_move_valid(1, 5, 0b00, 3), true);
-        assert_eq!(is_move_valid(4, 1, 0b11, 3), false);
+        assert_eq!(is_move_valid(4, 23, 0b00, 3, RuleSet::default()), true);
+        assert_eq!(is_move_valid(1, 5, 0b00, 3, RuleSet::default()), true);
+        assert_eq!(is_move_valid(4, 1, 0b11, 3, RuleSet::default()), false);
+        assert!(!is_move_valid(4, 23, 0b00, 3, RuleSet::default().with_flying(false)));
+        assert!(is_move_valid(4, 15, 0b00, 3, RuleSet::new(Variant::SixMensMorris).with_flying(true)));
+
+        // diagonals
+        assert!(!is_move_valid(1, 9, 0b00, 9, RuleSet::default()));
+        assert!(is_move_valid(1, 9, 0b00, 9, RuleSet::new(Variant::TwelveMensMorris)));
     }
 
     #[test]
     fn test_is_neighbor() {
+        let is_neighbor = |position1, position2| RuleSet::default().get_geometry().is_neighbor(position1, position2);
         let now = std::time::Instant::now();
         for _ in 0..10000 {
             assert_eq!(is_neighbor(0, 1), true);
@@ -118,74 +126,85 @@ mod tests {
             assert_eq!(is_neighbor(16, 0), false);
         }
         println!("Time elapsed: {:?}", now.elapsed());
+
+        /* the diagonals of Twelve Men's Morris only connect neighboring rings */
+        let twelve = RuleSet::new(Variant::TwelveMensMorris).get_geometry();
+        assert!(twelve.is_neighbor(7, 15));
+        assert!(twelve.is_neighbor(23, 15));
+        assert!(!twelve.is_neighbor(7, 23));
+        assert!(!twelve.is_neighbor(0, 16));
     }
 
     #[test]
     fn test_apply_move() {
-        let board = decode_positions("WBWWWWBBBEEEEEEEEEEEEEEE".to_string());
-        let expected_board = decode_positions("WBWWWWBBEBEEEEEEEEEEEEEE".to_string());
+        let board = decode_positions("WBWWWWBBBEEEEEEEEEEEEEEE".to_string()).unwrap();
+        let expected_board = decode_positions("WBWWWWBBEBEEEEEEEEEEEEEE".to_string()).unwrap();
 
-        assert_eq!(apply_move(&board, &Move::new(Some(8), 9), Token::parse_to_u8(Token::Black)), expected_board);
+        assert_eq!(apply_move(board, &Move::new(Some(8), 9), Token::parse_to_u8(Token::Black)), expected_board);
     }
 
     #[test]
     fn test_apply_action() {
-        let board = decode_positions("WBWWWWBBBEEEEEEEEEEEEEEE".to_string());
-        let expected_board = decode_positions("WBWWWWBBEBEEEEEEEEEEEEEE".to_string());
+        let board = decode_positions("WBWWWWBBBEEEEEEEEEEEEEEE".to_string()).unwrap();
+        let expected_board = decode_positions("WBWWWWBBEBEEEEEEEEEEEEEE".to_string()).unwrap();
 
-        assert_eq!(apply_action(&board, &Action::new(Some(8), 9, None), Token::parse_to_u8(Token::Black)), expected_board);
+        assert_eq!(apply_action(board, &Action::new(Some(8), 9, None), Token::parse_to_u8(Token::Black)), expected_board);
 
-        let board2 = decode_positions("WBWWWEWBBEEEEEEEEEEEEEEE".to_string());
-        let expected_board2 = decode_positions("WBWWWWEEBEEEEEEEEEEEEEEE".to_string());
+        let board2 = decode_positions("WBWWWEWBBEEEEEEEEEEEEEEE".to_string()).unwrap();
+        let expected_board2 = decode_positions("WBWWWWEEBEEEEEEEEEEEEEEE".to_string()).unwrap();
 
-        assert_eq!(apply_action(&board2, &Action::new(Some(6), 5, Some(7)), Token::parse_to_u8(Token::White)), expected_board2);
+        assert_eq!(apply_action(board2, &Action::new(Some(6), 5, Some(7)), Token::parse_to_u8(Token::White)), expected_board2);
     }
 
     #[test]
     fn test_is_beat_possible() {
-        let mut board = 0b111111111010101000000000000000000000000000000000;
+        let mut board = Board::from_raw(0b111111111010101000000000000000000000000000000000);
 
         let now = std::time::Instant::now();
         for _ in 0..1000000 {
-            assert!(is_beat_possible(board, 0, 0b10));
-            assert!(!is_beat_possible(board, 1, 0b10));
-            assert!(!is_beat_possible(board, 2, 0b10));
+            assert!(is_beat_possible(board, 0, 0b10, RuleSet::default()));
+            assert!(!is_beat_possible(board, 1, 0b10, RuleSet::default()));
+            assert!(!is_beat_possible(board, 2, 0b10, RuleSet::default()));
         }
         println!("Time elapsed: {:?}", now.elapsed());
 
-        assert!(!is_beat_possible(board, 3, 0b10));
-        assert!(!is_beat_possible(board, 4, 0b10));
-        assert!(!is_beat_possible(board, 5, 0b10));
-        assert!(!is_beat_possible(board, 6, 0b10));
-        assert!(!is_beat_possible(board, 7, 0b10));
-
-        assert!(!is_beat_possible(board, 0, 0b11));
-        assert!(!is_beat_possible(board, 1, 0b11));
-        assert!(!is_beat_possible(board, 2, 0b11));
-        assert!(!is_beat_possible(board, 3, 0b11));
-        assert!(is_beat_possible(board, 4, 0b11));
-        assert!(!is_beat_possible(board, 5, 0b11));
-        assert!(!is_beat_possible(board, 6, 0b11));
-        assert!(!is_beat_possible(board, 7, 0b11));
-
-        board = set_token_at(board, 7, 0b11);
-
-        assert!(is_beat_possible(board, 0, 0b10));
-        assert!(is_beat_possible(board, 1, 0b10));
-        assert!(is_beat_possible(board, 2, 0b10));
-        assert!(is_beat_possible(board, 3, 0b10));
-        assert!(!is_beat_possible(board, 4, 0b10));
-        assert!(!is_beat_possible(board, 5, 0b10));
-        assert!(!is_beat_possible(board, 6, 0b10));
-        assert!(is_beat_possible(board, 7, 0b10));
-
-        assert!(!is_beat_possible(board, 0, 0b11));
-        assert!(!is_beat_possible(board, 1, 0b11));
-        assert!(!is_beat_possible(board, 2, 0b11));
-        assert!(!is_beat_possible(board, 3, 0b11));
-        assert!(is_beat_possible(board, 4, 0b11));
-        assert!(is_beat_possible(board, 5, 0b11));
-        assert!(is_beat_possible(board, 6, 0b11));
-        assert!(!is_beat_possible(board, 7, 0b11));
+        assert!(!is_beat_possible(board, 3, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 4, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 5, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 6, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 7, 0b10, RuleSet::default()));
+
+        assert!(!is_beat_possible(board, 0, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 1, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 2, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 3, 0b11, RuleSet::default()));
+        assert!(is_beat_possible(board, 4, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 5, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 6, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 7, 0b11, RuleSet::default()));
+
+        let capture_from_mills = RuleSet::default().with_capture_from_mills(true);
+        assert!(is_beat_possible(board, 1, 0b10, capture_from_mills));
+        assert!(!is_beat_possible(board, 7, 0b10, capture_from_mills));
+
+        board.set_token_at(7, 0b11);
+
+        assert!(is_beat_possible(board, 0, 0b10, RuleSet::default()));
+        assert!(is_beat_possible(board, 1, 0b10, RuleSet::default()));
+        assert!(is_beat_possible(board, 2, 0b10, RuleSet::default()));
+        assert!(is_beat_possible(board, 3, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 4, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 5, 0b10, RuleSet::default()));
+        assert!(!is_beat_possible(board, 6, 0b10, RuleSet::default()));
+        assert!(is_beat_possible(board, 7, 0b10, RuleSet::default()));
+
+        assert!(!is_beat_possible(board, 0, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 1, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 2, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 3, 0b11, RuleSet::default()));
+        assert!(is_beat_possible(board, 4, 0b11, RuleSet::default()));
+        assert!(is_beat_possible(board, 5, 0b11, RuleSet::default()));
+        assert!(is_beat_possible(board, 6, 0b11, RuleSet::default()));
+        assert!(!is_beat_possible(board, 7, 0b11, RuleSet::default()));
     }
 }
@@ -1,11 +1,11 @@
 use core::panic;
 use crate::logic::{
-    r#move::NEIGHBORS, 
-    position::{get_number_of_tokens, get_token_at}
+    position::{Board, BOARD_MASK},
+    rules::RuleSet
 };
 
 /*
-    One Board u64 looks like:
+    One SearchBoard (u64) looks like:
         5 bits with possible moves for white
         5 bits with possible moves for black
         3 bits with number of white tokens
@@ -19,60 +19,145 @@ use crate::logic::{
     001 -> 3
     ...
     111 -> 9
+
+    In variants with more tokens or neighbors the counters saturate at
+    9 tokens and 31 moves, see RuleSet::has_incremental_counters.
 */
 pub const WHITE_TOKEN_FIRST_POSITION: u64 =          0b0000000000001000000000000000000000000000000000000000000000000000;
 pub const BLACK_TOKEN_FIRST_POSITION: u64 =          0b0000000000000001000000000000000000000000000000000000000000000000;
 pub const WHITE_POSSIBLE_MOVES_FIRST_POSITION: u64 = 0b0000100000000000000000000000000000000000000000000000000000000000;
 pub const BLACK_POSSIBLE_MOVES_FIRST_POSITION: u64 = 0b0000000001000000000000000000000000000000000000000000000000000000;
 
-pub fn insert_token_count_to_board(board: u64) -> u64 {
-    let white_token_count: u64 = match get_number_of_tokens(board, 0b11)
-    {
-        0 => 0,
-        1 => 0,
-        val => val as u64 - 2
-    };
-    let black_token_count: u64 = match get_number_of_tokens(board, 0b10)
-    {
-        0 => 0,
-        1 => 0,
-        val => val as u64 - 2
-    };
-
-    (board & 0b1111111111000000111111111111111111111111111111111111111111111111) | (white_token_count << 51) | (black_token_count << 48)
-}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SearchBoard(u64);
 
-pub fn insert_number_of_possible_moves_to_board(board: u64) -> u64 {
-    let white_possible_moves = get_possible_move_count(board, 0b11) as u64;
-    let black_possible_moves = get_possible_move_count(board, 0b10) as u64;
+impl SearchBoard {
+    pub fn new(board: Board, rules: RuleSet) -> SearchBoard {
+        SearchBoard(board.to_raw())
+            .insert_token_count()
+            .insert_number_of_possible_moves(rules)
+    }
 
-    (board & 0b0000000000111111111111111111111111111111111111111111111111111111) | (white_possible_moves << 59) | (black_possible_moves << 54)
-}
+    pub fn from_raw(raw: u64) -> SearchBoard {
+        SearchBoard(raw)
+    }
 
-pub fn extract_white_token_count_from_board(board: u64) -> u64 {
-    ((board & 0b0000000000111000000000000000000000000000000000000000000000000000) >> 51) + 2
-}
+    pub fn to_raw(self) -> u64 {
+        self.0
+    }
 
-pub fn extract_black_token_count_from_board(board: u64) -> u64 {
-    ((board & 0b0000000000000111000000000000000000000000000000000000000000000000) >> 48) + 2
-}
+    pub fn board(self) -> Board {
+        Board::from_raw(self.0)
+    }
 
-pub fn extract_white_move_count_from_board(board: u64) -> u64 {
-    (board & 0b1111100000000000000000000000000000000000000000000000000000000000) >> 59
-}
+    pub fn get_token_at(self, position: usize) -> u8 {
+        self.board().get_token_at(position)
+    }
+
+    pub fn set_token_at(&mut self, position: usize, token: u8) {
+        let mut board = self.board();
+        board.set_token_at(position, token);
+        self.0 = (self.0 & !BOARD_MASK) | board.to_raw();
+    }
+
+    pub fn insert_token_count(self) -> SearchBoard {
+        let white_token_count: u64 = match self.board().get_number_of_tokens(0b11)
+        {
+            0 => 0,
+            1 => 0,
+            val => val.min(9) as u64 - 2
+        };
+        let black_token_count: u64 = match self.board().get_number_of_tokens(0b10)
+        {
+            0 => 0,
+            1 => 0,
+            val => val.min(9) as u64 - 2
+        };
+
+        SearchBoard((self.0 & 0b1111111111000000111111111111111111111111111111111111111111111111) | (white_token_count << 51) | (black_token_count << 48))
+    }
+
+    pub fn insert_number_of_possible_moves(self, rules: RuleSet) -> SearchBoard {
+        let white_possible_moves = get_possible_move_count(self.board(), 0b11, rules).min(31) as u64;
+        let black_possible_moves = get_possible_move_count(self.board(), 0b10, rules).min(31) as u64;
+
+        SearchBoard((self.0 & 0b0000000000111111111111111111111111111111111111111111111111111111) | (white_possible_moves << 59) | (black_possible_moves << 54))
+    }
+
+    pub fn get_white_token_count(self) -> u64 {
+        ((self.0 & 0b0000000000111000000000000000000000000000000000000000000000000000) >> 51) + 2
+    }
+
+    pub fn get_black_token_count(self) -> u64 {
+        ((self.0 & 0b0000000000000111000000000000000000000000000000000000000000000000) >> 48) + 2
+    }
+
+    pub fn get_token_count(self, token_type: u8) -> u64 {
+        if token_type == 0b11 {
+            self.get_white_token_count()
+        } else {
+            self.get_black_token_count()
+        }
+    }
+
+    pub fn get_white_move_count(self) -> u64 {
+        (self.0 & 0b1111100000000000000000000000000000000000000000000000000000000000) >> 59
+    }
 
-pub fn extract_black_move_count_from_board(board: u64) -> u64 {
-    (board & 0b0000011111000000000000000000000000000000000000000000000000000000) >> 54
+    pub fn get_black_move_count(self) -> u64 {
+        (self.0 & 0b0000011111000000000000000000000000000000000000000000000000000000) >> 54
+    }
+
+    pub fn increase_token_count(&mut self, token_type: u8) {
+        self.0 += if token_type == 0b11 {
+            WHITE_TOKEN_FIRST_POSITION
+        } else {
+            BLACK_TOKEN_FIRST_POSITION
+        };
+    }
+
+    pub fn decrease_token_count(&mut self, token_type: u8) {
+        self.0 -= if token_type == 0b11 {
+            WHITE_TOKEN_FIRST_POSITION
+        } else {
+            BLACK_TOKEN_FIRST_POSITION
+        };
+    }
+
+    pub fn update_possible_move_count(&mut self, token_type: u8, position: usize, remove: bool, rules: RuleSet) {
+        let token_at_position = self.get_token_at(position);
+        if token_type == 0b00 || (token_at_position != 0b00 && remove) 
+            || (token_at_position == 0b00 && !remove) 
+            || (token_at_position != token_type && !remove) {
+            panic!("Invalid token type or position")
+        }
+
+        rules.get_geometry().get_neighbors(position).iter()
+            .for_each(|neighbor| {
+                match self.get_token_at(*neighbor) {
+                    0b00 if remove && token_type == 0b11 => self.0 -= WHITE_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b00 if remove && token_type == 0b10 => self.0 -= BLACK_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b00 if !remove && token_type == 0b11 => self.0 += WHITE_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b00 if !remove && token_type == 0b10 => self.0 += BLACK_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b11 if remove => self.0 += WHITE_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b11 if !remove => self.0 -= WHITE_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b10 if remove => self.0 += BLACK_POSSIBLE_MOVES_FIRST_POSITION,
+                    0b10 if !remove => self.0 -= BLACK_POSSIBLE_MOVES_FIRST_POSITION,
+                    _ => ()
+                }
+        });
+    }
 }
 
-pub fn get_possible_move_count(board: u64, token_type: u8) -> usize {
-    let mut board_mut = board;
+pub fn get_possible_move_count(board: Board, token_type: u8, rules: RuleSet) -> usize {
+    let geometry = rules.get_geometry();
+    let mut board_mut = board.to_raw();
     let mut count = 0;
     let mut index: isize = 23;
     while index >= 0 {
         if (board_mut & 0b11) as u8 == token_type {
-            for neighbor in NEIGHBORS[index as usize].iter() {
-                if *neighbor != 24 && get_token_at(board, *neighbor) == 0b00 {
+            for neighbor in geometry.get_neighbors(index as usize).iter() {
+                if board.get_token_at(*neighbor) == 0b00 {
                     count += 1;
                 }
             }
@@ -83,175 +168,182 @@ pub fn get_possible_move_count(board: u64, token_type: u8) -> usize {
     count
 }
 
-pub fn update_possible_move_count(board: u64, token_type: u8, position: usize, remove: bool) -> u64 {
-    let mut new_board = board;
-
-    let token_at_position = get_token_at(new_board, position);
-    if token_type == 0b00 || (token_at_position != 0b00 && remove) 
-        || (token_at_position == 0b00 && !remove) 
-        || (token_at_position != token_type && !remove) {
-        panic!("Invalid token type or position")
-    }
-
-    NEIGHBORS[position].iter()
-        .for_each(|neighbor| {
-            if *neighbor == 24 {
-                return
-            }
-
-            match get_token_at(new_board, *neighbor) {
-                0b00 if remove && token_type == 0b11 => new_board -= WHITE_POSSIBLE_MOVES_FIRST_POSITION,
-                0b00 if remove && token_type == 0b10 => new_board -= BLACK_POSSIBLE_MOVES_FIRST_POSITION,
-                0b00 if !remove && token_type == 0b11 => new_board += WHITE_POSSIBLE_MOVES_FIRST_POSITION,
-                0b00 if !remove && token_type == 0b10 => new_board += BLACK_POSSIBLE_MOVES_FIRST_POSITION,
-                0b11 if remove => new_board += WHITE_POSSIBLE_MOVES_FIRST_POSITION,
-                0b11 if !remove => new_board -= WHITE_POSSIBLE_MOVES_FIRST_POSITION,
-                0b10 if remove => new_board += BLACK_POSSIBLE_MOVES_FIRST_POSITION,
-                0b10 if !remove => new_board -= BLACK_POSSIBLE_MOVES_FIRST_POSITION,
-                _ => ()
-            }
-    });
-
-    new_board
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::{logic::move_token_count::{extract_black_move_count_from_board, extract_black_token_count_from_board, extract_white_move_count_from_board, extract_white_token_count_from_board, get_possible_move_count, insert_number_of_possible_moves_to_board, insert_token_count_to_board, update_possible_move_count}, logic::position::{decode_positions, set_token_at}};
+    use crate::logic::{move_token_count::{get_possible_move_count, SearchBoard}, position::{decode_positions, Board}, rules::{RuleSet, Variant}};
     use super::{BLACK_POSSIBLE_MOVES_FIRST_POSITION, WHITE_POSSIBLE_MOVES_FIRST_POSITION};
 
     #[test]
-    fn test_insert_token_count_to_board() {
-        let board1: u64 = 0b0;
-        let board2: u64 = 0b101000000011110011101110110010110011101100100010;
-        let board3: u64 = 0b000000000011110011101110110010110011101100100010;
-        let board4: u64 = 0b000000000000000000000000000010000000000000000000;
+    fn test_insert_token_count() {
+        let board1 = SearchBoard::from_raw(0b0);
+        let board2 = SearchBoard::from_raw(0b101000000011110011101110110010110011101100100010);
+        let board3 = SearchBoard::from_raw(0b000000000011110011101110110010110011101100100010);
+        let board4 = SearchBoard::from_raw(0b000000000000000000000000000010000000000000000000);
         
         let exp_board1: u64 = 0b0;
         let exp_board2: u64 = 0b0000000000110110101000000011110011101110110010110011101100100010; // 8v8
         let exp_board3: u64 = 0b0000000000110100000000000011110011101110110010110011101100100010; // 8v6
         let exp_board4: u64 = 0b0000000000000000000000000000000000000000000010000000000000000000; // 0v1
         
-        assert_eq!(exp_board1, insert_token_count_to_board(board1));
-        assert_eq!(exp_board2, insert_token_count_to_board(board2));
-        assert_eq!(exp_board3, insert_token_count_to_board(board3));
-        assert_eq!(exp_board4, insert_token_count_to_board(board4));
+        assert_eq!(exp_board1, board1.insert_token_count().to_raw());
+        assert_eq!(exp_board2, board2.insert_token_count().to_raw());
+        assert_eq!(exp_board3, board3.insert_token_count().to_raw());
+        assert_eq!(exp_board4, board4.insert_token_count().to_raw());
     }
 
     #[test]
-    fn test_insert_number_of_possible_moves_to_board() {
-        let board1: u64 = 0b0;
-        let board2: u64 = 0b101000000011110011101110110010110011101100100010;
-        let board3: u64 = 0b000000000011110011101110110010110011101100100010;
-        let board4: u64 = 0b111100000011110011101110110010110011101100100010;
+    fn test_insert_number_of_possible_moves() {
+        let board1 = Board::from_raw(0b0);
+        let board2 = Board::from_raw(0b101000000011110011101110110010110011101100100010);
+        let board3 = Board::from_raw(0b000000000011110011101110110010110011101100100010);
+        let board4 = Board::from_raw(0b111100000011110011101110110010110011101100100010);
         
         let filter_possible_moves_black = 0b0000011111000000000000000000000000000000000000000000000000000000;
         let filter_possible_moves_white = 0b1111100000000000000000000000000000000000000000000000000000000000;
 
-        let inserted_board1 = insert_number_of_possible_moves_to_board(board1);
+        let inserted_board1 = SearchBoard::from_raw(board1.to_raw()).insert_number_of_possible_moves(RuleSet::default()).to_raw();
         let inserted_possible_move_white1 = (inserted_board1 & filter_possible_moves_white) >> 59;
         let inserted_possible_move_black1 = (inserted_board1 & filter_possible_moves_black) >> 54;
-        assert_eq!(get_possible_move_count(board1, 0b11), inserted_possible_move_white1 as usize);
-        assert_eq!(get_possible_move_count(board1, 0b10), inserted_possible_move_black1 as usize);
+        assert_eq!(get_possible_move_count(board1, 0b11, RuleSet::default()), inserted_possible_move_white1 as usize);
+        assert_eq!(get_possible_move_count(board1, 0b10, RuleSet::default()), inserted_possible_move_black1 as usize);
 
-        let inserted_board2 = insert_number_of_possible_moves_to_board(board2);
+        let inserted_board2 = SearchBoard::from_raw(board2.to_raw()).insert_number_of_possible_moves(RuleSet::default()).to_raw();
         let inserted_possible_move_white2 = (inserted_board2 & filter_possible_moves_white) >> 59;
         let inserted_possible_move_black2 = (inserted_board2 & filter_possible_moves_black) >> 54;
-        assert_eq!(get_possible_move_count(board2, 0b11), inserted_possible_move_white2 as usize);
-        assert_eq!(get_possible_move_count(board2, 0b10), inserted_possible_move_black2 as usize);
+        assert_eq!(get_possible_move_count(board2, 0b11, RuleSet::default()), inserted_possible_move_white2 as usize);
+        assert_eq!(get_possible_move_count(board2, 0b10, RuleSet::default()), inserted_possible_move_black2 as usize);
 
-        let inserted_board3 = insert_number_of_possible_moves_to_board(board3);
+        let inserted_board3 = SearchBoard::from_raw(board3.to_raw()).insert_number_of_possible_moves(RuleSet::default()).to_raw();
         let inserted_possible_move_white3 = (inserted_board3 & filter_possible_moves_white) >> 59;
         let inserted_possible_move_black3 = (inserted_board3 & filter_possible_moves_black) >> 54;
-        assert_eq!(get_possible_move_count(board3, 0b11), inserted_possible_move_white3 as usize);
-        assert_eq!(get_possible_move_count(board3, 0b10), inserted_possible_move_black3 as usize);
+        assert_eq!(get_possible_move_count(board3, 0b11, RuleSet::default()), inserted_possible_move_white3 as usize);
+        assert_eq!(get_possible_move_count(board3, 0b10, RuleSet::default()), inserted_possible_move_black3 as usize);
 
-        let inserted_board4 = insert_number_of_possible_moves_to_board(board4);        
+        let inserted_board4 = SearchBoard::from_raw(board4.to_raw()).insert_number_of_possible_moves(RuleSet::default()).to_raw();        
         let inserted_possible_move_white4 = (inserted_board4 & filter_possible_moves_white) >> 59;
         let inserted_possible_move_black4 = (inserted_board4 & filter_possible_moves_black) >> 54;
-        assert_eq!(get_possible_move_count(board4, 0b11), inserted_possible_move_white4 as usize);
-        assert_eq!(get_possible_move_count(board4, 0b10), inserted_possible_move_black4 as usize);
+        assert_eq!(get_possible_move_count(board4, 0b11, RuleSet::default()), inserted_possible_move_white4 as usize);
+        assert_eq!(get_possible_move_count(board4, 0b10, RuleSet::default()), inserted_possible_move_black4 as usize);
     }
 
     #[test]
     fn test_extract_methods() {
-        let board1: u64 = 0b101000000011110011101110110010110011101100100010;
-        let board2: u64 = 0b000000000011110011101110110010110011101100100010;
+        let board1 = Board::from_raw(0b101000000011110011101110110010110011101100100010);
+        let board2 = Board::from_raw(0b000000000011110011101110110010110011101100100010);
 
         let filter_token_black = 0b0000000000000111000000000000000000000000000000000000000000000000;
         let filter_token_white = 0b0000000000111000000000000000000000000000000000000000000000000000;
         let filter_possible_moves_black = 0b0000011111000000000000000000000000000000000000000000000000000000;
         let filter_possible_moves_white = 0b1111100000000000000000000000000000000000000000000000000000000000;
 
-        let move_and_token_count_board1 = insert_number_of_possible_moves_to_board(insert_number_of_possible_moves_to_board(board1));
-        let inserted_possible_move_white1 = (move_and_token_count_board1 & filter_possible_moves_white) >> 59;
-        let inserted_possible_move_black1 = (move_and_token_count_board1 & filter_possible_moves_black) >> 54;
-        let inserted_token_count_white1 = (move_and_token_count_board1 & filter_token_white) >> 51;
-        let inserted_token_count_black1 = (move_and_token_count_board1 & filter_token_black) >> 48;
-        assert_eq!(inserted_possible_move_white1, extract_white_move_count_from_board(move_and_token_count_board1));
-        assert_eq!(inserted_possible_move_black1, extract_black_move_count_from_board(move_and_token_count_board1));
-        assert_eq!(inserted_token_count_white1, extract_white_token_count_from_board(move_and_token_count_board1) - 2);
-        assert_eq!(inserted_token_count_black1, extract_black_token_count_from_board(move_and_token_count_board1) - 2);
-
-        let move_and_token_count_board2 = insert_number_of_possible_moves_to_board(insert_number_of_possible_moves_to_board(board2));
-        let inserted_possible_move_white2 = (move_and_token_count_board2 & filter_possible_moves_white) >> 59;
-        let inserted_possible_move_black2 = (move_and_token_count_board2 & filter_possible_moves_black) >> 54;
-        let inserted_token_count_white2 = (move_and_token_count_board1 & filter_token_white) >> 51;
-        let inserted_token_count_black2 = (move_and_token_count_board1 & filter_token_black) >> 48;
-        assert_eq!(inserted_possible_move_white2, extract_white_move_count_from_board(move_and_token_count_board2));
-        assert_eq!(inserted_possible_move_black2, extract_black_move_count_from_board(move_and_token_count_board2));
-        assert_eq!(inserted_token_count_white2, extract_white_token_count_from_board(move_and_token_count_board2) - 2);
-        assert_eq!(inserted_token_count_black2, extract_black_token_count_from_board(move_and_token_count_board2) - 2);
+        let move_and_token_count_board1 = SearchBoard::new(board1, RuleSet::default());
+        let inserted_possible_move_white1 = (move_and_token_count_board1.to_raw() & filter_possible_moves_white) >> 59;
+        let inserted_possible_move_black1 = (move_and_token_count_board1.to_raw() & filter_possible_moves_black) >> 54;
+        let inserted_token_count_white1 = (move_and_token_count_board1.to_raw() & filter_token_white) >> 51;
+        let inserted_token_count_black1 = (move_and_token_count_board1.to_raw() & filter_token_black) >> 48;
+        assert_eq!(inserted_possible_move_white1, move_and_token_count_board1.get_white_move_count());
+        assert_eq!(inserted_possible_move_black1, move_and_token_count_board1.get_black_move_count());
+        assert_eq!(inserted_token_count_white1, move_and_token_count_board1.get_white_token_count() - 2);
+        assert_eq!(inserted_token_count_black1, move_and_token_count_board1.get_black_token_count() - 2);
+
+        let move_and_token_count_board2 = SearchBoard::new(board2, RuleSet::default());
+        let inserted_possible_move_white2 = (move_and_token_count_board2.to_raw() & filter_possible_moves_white) >> 59;
+        let inserted_possible_move_black2 = (move_and_token_count_board2.to_raw() & filter_possible_moves_black) >> 54;
+        let inserted_token_count_white2 = (move_and_token_count_board2.to_raw() & filter_token_white) >> 51;
+        let inserted_token_count_black2 = (move_and_token_count_board2.to_raw() & filter_token_black) >> 48;
+        assert_eq!(inserted_possible_move_white2, move_and_token_count_board2.get_white_move_count());
+        assert_eq!(inserted_possible_move_black2, move_and_token_count_board2.get_black_move_count());
+        assert_eq!(inserted_token_count_white2, move_and_token_count_board2.get_white_token_count() - 2);
+        assert_eq!(inserted_token_count_black2, move_and_token_count_board2.get_black_token_count() - 2);
+    }
+
+    #[test]
+    fn test_board() {
+        let board = decode_positions("BWEEEWBBWBBWWBBWEEEEEEWE".to_string()).unwrap();
+        let search_board = SearchBoard::new(board, RuleSet::default());
+
+        assert_eq!(search_board.board(), board);
+        assert_eq!(search_board.get_white_token_count(), 7);
+        assert_eq!(search_board.get_black_token_count(), 7);
+        assert_eq!(search_board.get_white_move_count(), 7);
+        assert_eq!(search_board.get_black_move_count(), 2);
+    }
+
+    #[test]
+    fn test_saturated_counters() {
+        let board = decode_positions("WEWWWEWEEWEWEWEWWEEEWEWE".to_string()).unwrap();
+        let twelve = RuleSet::new(Variant::TwelveMensMorris);
+        let search_board = SearchBoard::new(board, twelve);
+
+        assert_eq!(get_possible_move_count(board, 0b11, twelve), 34);
+        assert_eq!(search_board.get_white_move_count(), 31);
+        assert_eq!(search_board.get_white_token_count(), 9);
+        assert_eq!(search_board.get_black_token_count(), 2);
+        assert_eq!(search_board.board(), board);
     }
 
     #[test]
     fn test_get_possible_move_count() {
-        let board = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string());
-        let board2 = decode_positions("BWEEEWBBWBBWWBBWEEEEEEWE".to_string());
-        let board3 = decode_positions("EEEEEEEEEEEEEEEEEEEEEEEE".to_string());
-
-        assert_eq!(get_possible_move_count(board, 0b11), 8);
-        assert_eq!(get_possible_move_count(board, 0b10), 2);
-        assert_eq!(get_possible_move_count(board2, 0b11), 7);
-        assert_eq!(get_possible_move_count(board2, 0b10), 2);
-        assert_eq!(get_possible_move_count(board3, 0b11), 0);
-        assert_eq!(get_possible_move_count(board3, 0b10), 0);
+        let board = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap();
+        let board2 = decode_positions("BWEEEWBBWBBWWBBWEEEEEEWE".to_string()).unwrap();
+        let board3 = decode_positions("EEEEEEEEEEEEEEEEEEEEEEEE".to_string()).unwrap();
+
+        assert_eq!(get_possible_move_count(board, 0b11, RuleSet::default()), 8);
+        assert_eq!(get_possible_move_count(board, 0b10, RuleSet::default()), 2);
+        assert_eq!(get_possible_move_count(board2, 0b11, RuleSet::default()), 7);
+        assert_eq!(get_possible_move_count(board2, 0b10, RuleSet::default()), 2);
+        assert_eq!(get_possible_move_count(board3, 0b11, RuleSet::default()), 0);
+        assert_eq!(get_possible_move_count(board3, 0b10, RuleSet::default()), 0);
     }
 
     #[test]
     fn test_update_possible_move_count() {
-        let board = 
-            decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string())
+        let board = SearchBoard::from_raw(
+            decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap().to_raw()
             + 2 * BLACK_POSSIBLE_MOVES_FIRST_POSITION
-            + 8 * WHITE_POSSIBLE_MOVES_FIRST_POSITION;
+            + 8 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
+        );
+        let updated = |mut board: SearchBoard, token_type: u8, position: usize, remove: bool| {
+            board.update_possible_move_count(token_type, position, remove, RuleSet::default());
+            board.to_raw()
+        };
         
-        let mut new_board = set_token_at(board, 18, 0b11);
-        assert_eq!(update_possible_move_count(new_board, 0b11, 18, false), new_board + 3 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
-        new_board = set_token_at(board, 18, 0b10);
-        assert_eq!(update_possible_move_count(new_board, 0b10, 18, false), new_board + 3 * BLACK_POSSIBLE_MOVES_FIRST_POSITION);
-        new_board = set_token_at(board, 12, 0b10);
-        assert_eq!(update_possible_move_count(new_board, 0b10, 12, false), new_board + 2 * BLACK_POSSIBLE_MOVES_FIRST_POSITION);
-        new_board = set_token_at(board, 15, 0b11);
-        assert_eq!(update_possible_move_count(new_board, 0b11, 15, false), new_board - 2 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
-        new_board = set_token_at(board, 15, 0b10);
-        assert_eq!(update_possible_move_count(new_board, 0b10, 15, false), new_board - 2 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
-        new_board = set_token_at(board, 10, 0b10);
-        assert_eq!(update_possible_move_count(new_board, 0b10, 10, false), 
-            new_board 
+        let mut new_board = board;
+        new_board.set_token_at(18, 0b11);
+        assert_eq!(updated(new_board, 0b11, 18, false), new_board.to_raw() + 3 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
+        new_board = board;
+        new_board.set_token_at(18, 0b10);
+        assert_eq!(updated(new_board, 0b10, 18, false), new_board.to_raw() + 3 * BLACK_POSSIBLE_MOVES_FIRST_POSITION);
+        new_board = board;
+        new_board.set_token_at(12, 0b10);
+        assert_eq!(updated(new_board, 0b10, 12, false), new_board.to_raw() + 2 * BLACK_POSSIBLE_MOVES_FIRST_POSITION);
+        new_board = board;
+        new_board.set_token_at(15, 0b11);
+        assert_eq!(updated(new_board, 0b11, 15, false), new_board.to_raw() - 2 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
+        new_board = board;
+        new_board.set_token_at(15, 0b10);
+        assert_eq!(updated(new_board, 0b10, 15, false), new_board.to_raw() - 2 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
+        new_board = board;
+        new_board.set_token_at(10, 0b10);
+        assert_eq!(updated(new_board, 0b10, 10, false), 
+            new_board.to_raw() 
             + 3 * BLACK_POSSIBLE_MOVES_FIRST_POSITION
             - WHITE_POSSIBLE_MOVES_FIRST_POSITION
         );
-        new_board = set_token_at(board, 8, 0b00);
-        assert_eq!(update_possible_move_count(new_board, 0b11, 8, true), new_board + 2 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
-        new_board = set_token_at(board, 14, 0b00);
-        assert_eq!(update_possible_move_count(new_board, 0b11, 14, true), 
-            new_board
+        new_board = board;
+        new_board.set_token_at(8, 0b00);
+        assert_eq!(updated(new_board, 0b11, 8, true), new_board.to_raw() + 2 * WHITE_POSSIBLE_MOVES_FIRST_POSITION);
+        new_board = board;
+        new_board.set_token_at(14, 0b00);
+        assert_eq!(updated(new_board, 0b11, 14, true), 
+            new_board.to_raw()
             - 3 * WHITE_POSSIBLE_MOVES_FIRST_POSITION
             + BLACK_POSSIBLE_MOVES_FIRST_POSITION
         );
-        new_board = set_token_at(board, 6, 0b00);
-        assert_eq!(update_possible_move_count(new_board, 0b10, 6, true), 
-            new_board
+        new_board = board;
+        new_board.set_token_at(6, 0b00);
+        assert_eq!(updated(new_board, 0b10, 6, true), 
+            new_board.to_raw()
             + 2 * BLACK_POSSIBLE_MOVES_FIRST_POSITION
             + WHITE_POSSIBLE_MOVES_FIRST_POSITION
         );
@@ -260,32 +352,32 @@ mod tests {
     #[test]
     #[should_panic]
     fn test_update_possible_move_count2() {
-        let board = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string());
+        let mut board = SearchBoard::new(decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap(), RuleSet::default());
 
-        assert_eq!(update_possible_move_count(board, 0b00, 18, false), board);
+        board.update_possible_move_count(0b00, 18, false, RuleSet::default());
     }
 
     #[test]
     #[should_panic]
     fn test_update_possible_move_count3() {
-        let board = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string());
+        let mut board = SearchBoard::new(decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap(), RuleSet::default());
 
-        assert_eq!(update_possible_move_count(board, 0b11, 18, false), board);
+        board.update_possible_move_count(0b11, 18, false, RuleSet::default());
     }
 
     #[test]
     #[should_panic]
     fn test_update_possible_move_count4() {
-        let board = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string());
+        let mut board = SearchBoard::new(decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap(), RuleSet::default());
 
-        assert_eq!(update_possible_move_count(board, 0b10, 7, true), board);
+        board.update_possible_move_count(0b10, 7, true, RuleSet::default());
     }
 
     #[test]
     #[should_panic]
     fn test_update_possible_move_count5() {
-        let board = decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string());
+        let mut board = SearchBoard::new(decode_positions("WEEEBBBBWWEEEEWEWEEEEEEE".to_string()).unwrap(), RuleSet::default());
 
-        assert_eq!(update_possible_move_count(board, 0b10, 8, false), board);
+        board.update_possible_move_count(0b10, 8, false, RuleSet::default());
     }
 }
@@ -0,0 +1,161 @@
+use std::{error::Error, fmt, str::FromStr};
+use super::action::Action;
+
+/*
+    The positions are named by the coordinates of the usual board diagram
+    with the files a-g from left to right and the ranks 1-7 from bottom to top:
+        a7(7)           d7(0)           g7(1)
+
+             b6(15)     d6(8)     f6(9)
+
+                  c5(23) d5(16) e5(17)
+
+        a4(6) b4(14) c4(22)   e4(18) f4(10) g4(2)
+
+                  c3(21) d3(20) e3(19)
+
+             b2(13)     d2(12)    f2(11)
+
+        a1(5)           d1(4)           g1(3)
+
+    An action is written as the end position for placing ("d7"), start and
+    end position joined by "-" for moving ("d7-g7") and followed by "x" and
+    the position of the removed token if a mill was closed ("d7-g7xa1").
+    An action capturing two tokens has two of them ("d7-g7xa1xd1").
+*/
+pub const COORDINATES: [&str; 24] = [
+    "d7", "g7", "g4", "g1", "d1", "a1", "a4", "a7",
+    "d6", "f6", "f4", "f2", "d2", "b2", "b4", "b6",
+    "d5", "e5", "e4", "e3", "d3", "c3", "c4", "c5"
+];
+
+pub fn position_to_coordinate(position: usize) -> &'static str {
+    COORDINATES[position]
+}
+
+pub fn coordinate_to_position(coordinate: &str) -> Option<usize> {
+    COORDINATES.iter().position(|candidate| candidate.eq_ignore_ascii_case(coordinate))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseActionError {
+    Empty,
+    InvalidCoordinate(String)
+}
+
+impl fmt::Display for ParseActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseActionError::Empty => write!(f, "empty action"),
+            ParseActionError::InvalidCoordinate(coordinate) => write!(f, "invalid coordinate '{}'", coordinate)
+        }
+    }
+}
+
+impl Error for ParseActionError {}
+
+fn parse_coordinate(coordinate: &str) -> Result<usize, ParseActionError> {
+    coordinate_to_position(coordinate)
+        .ok_or_else(|| ParseActionError::InvalidCoordinate(coordinate.to_string()))
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(start_position) = self.start_position {
+            write!(f, "{}-", position_to_coordinate(start_position))?;
+        }
+        write!(f, "{}", position_to_coordinate(self.end_position))?;
+        for beatable_position in self.get_captures() {
+            write!(f, "x{}", position_to_coordinate(beatable_position))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Action {
+    type Err = ParseActionError;
+
+    fn from_str(notation: &str) -> Result<Self, Self::Err> {
+        let notation = notation.trim();
+        if notation.is_empty() {
+            return Err(ParseActionError::Empty);
+        }
+
+        let (movement, beaten) = match notation.split_once(['x', 'X']) {
+            Some((movement, beaten)) => (movement, Some(beaten)),
+            None => (notation, None)
+        };
+        let (start_position, end_position) = match movement.split_once('-') {
+            Some((start, end)) => (Some(parse_coordinate(start)?), parse_coordinate(end)?),
+            None => (None, parse_coordinate(movement)?)
+        };
+
+        let mut action = Action::new(start_position, end_position, None);
+        match beaten.map(|beaten| beaten.split_once(['x', 'X']).ok_or(beaten)) {
+            Some(Ok((first, second))) => action = action.with_capture(parse_coordinate(first)?).with_capture(parse_coordinate(second)?),
+            Some(Err(beaten)) => action = action.with_capture(parse_coordinate(beaten)?),
+            None => ()
+        }
+        Ok(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::{action::{list_actions, Action}, game_state::{Phase, Token}, position::decode_positions, rules::RuleSet};
+    use super::{coordinate_to_position, position_to_coordinate, ParseActionError};
+
+    #[test]
+    fn test_coordinates() {
+        assert_eq!(position_to_coordinate(0), "d7");
+        assert_eq!(position_to_coordinate(5), "a1");
+        assert_eq!(position_to_coordinate(14), "b4");
+        assert_eq!(position_to_coordinate(23), "c5");
+
+        for position in 0..24 {
+            assert_eq!(coordinate_to_position(position_to_coordinate(position)), Some(position));
+        }
+        assert_eq!(coordinate_to_position("G7"), Some(1));
+        assert_eq!(coordinate_to_position("d4"), None);
+        assert_eq!(coordinate_to_position("h1"), None);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Action::new(None, 5, None).to_string(), "a1");
+        assert_eq!(Action::new(Some(12), 20, None).to_string(), "d2-d3");
+        assert_eq!(Action::new(None, 5, Some(1)).to_string(), "a1xg7");
+        assert_eq!(Action::new(Some(4), 3, Some(16)).to_string(), "d1-g1xd5");
+        assert_eq!(Action::new(Some(4), 3, Some(16)).with_capture(7).to_string(), "d1-g1xa7xd5");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("a1".parse(), Ok(Action::new(None, 5, None)));
+        assert_eq!("d2-d3".parse(), Ok(Action::new(Some(12), 20, None)));
+        assert_eq!(" A1XG7 ".parse(), Ok(Action::new(None, 5, Some(1))));
+        assert_eq!("d1-g1xd5".parse(), Ok(Action::new(Some(4), 3, Some(16))));
+        assert_eq!("d1-g1xd5xa7".parse(), Ok(Action::new(Some(4), 3, Some(7)).with_capture(16)));
+
+        assert_eq!("".parse::<Action>(), Err(ParseActionError::Empty));
+        assert_eq!("d4".parse::<Action>(), Err(ParseActionError::InvalidCoordinate("d4".to_string())));
+        assert_eq!("a1-".parse::<Action>(), Err(ParseActionError::InvalidCoordinate("".to_string())));
+        assert_eq!("a1xa1xa1xa1".parse::<Action>(), Err(ParseActionError::InvalidCoordinate("a1xa1".to_string())));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let boards = [
+            (decode_positions("BWWEWBBBBEEWWWEEBEEEEEEE".to_string()).unwrap(), Token::White, Phase::Move),
+            (decode_positions("WBBEBWWWWEEBBBEEWWEEEEEE".to_string()).unwrap(), Token::Black, Phase::Move),
+            (decode_positions("BWWEWBBBBEEWWWEBBEEEEEEE".to_string()).unwrap(), Token::White, Phase::Set),
+            (decode_positions("EWWEBEEBEEEEEEEEWEEEEBEE".to_string()).unwrap(), Token::White, Phase::Move),
+        ];
+
+        for (board, player, phase) in boards {
+            for action in list_actions(board, Token::parse_to_u8(player), phase, None, RuleSet::default()) {
+                assert_eq!(action.to_string().parse(), Ok(action));
+            }
+        }
+    }
+}
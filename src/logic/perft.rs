@@ -0,0 +1,220 @@
+use crate::agent::AiPhase;
+use super::{
+    action::{list_actions, Action},
+    forward_boards::forward_step_boards,
+    game_state::{GameState, Phase, Token},
+    move_token_count::SearchBoard,
+    position::negate_token
+};
+
+/*
+    Counts the leaf nodes of the game tree up to the given depth. Finished
+    games are leaves without successors, so they are only counted at depth 0.
+    The numbers are used to compare the move generation with known values.
+*/
+pub fn perft(game_state: GameState, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if game_state.is_game_over() {
+        return 0;
+    }
+
+    list_actions(
+        game_state.get_board(),
+        Token::parse_to_u8(game_state.get_player_turn()),
+        game_state.get_phase(),
+        None,
+        game_state.get_rules()
+    ).map(|action| {
+        let mut successor = game_state;
+        successor.apply_action(&action);
+        perft(successor, depth - 1)
+    }).sum()
+}
+
+/*
+    Splits the count of perft by the actions of the root position, which
+    helps to find the position where two move generators disagree.
+*/
+pub fn perft_divide(game_state: GameState, depth: usize) -> Vec<(Action, u64)> {
+    if depth == 0 || game_state.is_game_over() {
+        return Vec::new();
+    }
+
+    list_actions(
+        game_state.get_board(),
+        Token::parse_to_u8(game_state.get_player_turn()),
+        game_state.get_phase(),
+        None,
+        game_state.get_rules()
+    ).map(|action| {
+        let mut successor = game_state;
+        successor.apply_action(&action);
+        (action, perft(successor, depth - 1))
+    }).collect()
+}
+
+/*
+    Same as perft but walks the tree like the search does, with
+    forward_step_boards and the counters of the SearchBoard.
+*/
+pub fn perft_search_board(board: SearchBoard, token_type: u8, phase: AiPhase, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let token_count = board.get_token_count(token_type);
+    let move_count = if token_type == 0b11 { board.get_white_move_count() } else { board.get_black_move_count() };
+    if phase.phase == Phase::Move && (token_count < 3 || (!phase.rules.can_fly(token_count as u8) && move_count == 0)) {
+        return 0;
+    }
+    if phase.phase == Phase::Set && phase.is_out_of_tokens(board, token_type) {
+        return 0;
+    }
+
+    forward_step_boards(board, token_type, phase)
+        .map(|forward_board| perft_search_board(forward_board, negate_token(token_type), phase.increased(), depth - 1))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::{agent::AiPhase, logic::{
+        action::list_actions,
+        forward_boards::forward_step_boards,
+        game_state::{GameState, Token},
+        move_token_count::SearchBoard,
+        rules::{RuleSet, Variant}
+    }};
+    use super::{perft, perft_divide, perft_search_board};
+
+    /*
+        Positions of the regression suite with known node counts:
+        start position, set phase shortly before the first mill, move phase
+        with mills to open and close, flying with three tokens on one or both
+        sides, a position where one capture ends the game, the set and move
+        phase of Twelve Men's Morris with the diagonals and of Six Men's
+        Morris and a position of Three Men's Morris with two mills to close.
+    */
+    const POSITIONS: [(&str, [u64; 3]); 11] = [
+        ("EEEEEEEEEEEEEEEEEEEEEEEE w 9 9 0 0", [24, 552, 12144]),
+        ("WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0", [21, 437, 8716]),
+        ("BWWEWBBBBEEWWWEEBEEEEEEE b 0 0 25 3", [5, 39, 350]),
+        ("WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 18 0", [6, 32, 230]),
+        ("WWEWBEEEEEEEEEEEEEEEEBBB w 0 0 30 0", [51, 354, 18708]),
+        ("EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0", [56, 2862, 157782]),
+        ("WBWEEBWEEWEBEEWEBWEBEEEE b 5 6 13 0 twelve", [18, 285, 5010]),
+        ("WWEWBBEBEEEEEEEEWEEEEBEE w 0 0 24 0 twelve", [8, 67, 662]),
+        ("WBWEEEEEBWEEEEEEEEEEEEEE b 3 4 5 0 six", [11, 110, 990]),
+        ("WEWBEEEBBEBEEEWEEEEEEEEE w 0 0 12 0 six", [5, 32, 138]),
+        ("WEEEEEEBWEEEEEEBEBEEEEEW w 0 0 6 0 three", [9, 19, 42])
+    ];
+
+    fn search_board_perft(game_state: GameState, depth: usize) -> u64 {
+        perft_search_board(
+            SearchBoard::new(game_state.get_board(), game_state.get_rules()),
+            Token::parse_to_u8(game_state.get_player_turn()),
+            AiPhase::from_game_state(&game_state),
+            depth
+        )
+    }
+
+    /*
+        Compares the successors of both move generators at every node. The
+        counters of the boards from forward_step_boards are updated
+        incrementally and must equal the counters calculated from scratch.
+    */
+    fn check_successors(game_state: GameState, search_board: SearchBoard, depth: usize) {
+        assert_eq!(SearchBoard::new(game_state.get_board(), game_state.get_rules()), search_board, "counters differ in {}", game_state);
+        if depth == 0 || game_state.is_game_over() {
+            return;
+        }
+
+        let token_type = Token::parse_to_u8(game_state.get_player_turn());
+        let successors = list_actions(game_state.get_board(), token_type, game_state.get_phase(), None, game_state.get_rules())
+            .map(|action| {
+                let mut successor = game_state;
+                successor.apply_action(&action);
+                successor
+            })
+            .collect::<Vec<GameState>>();
+        let forward_boards = forward_step_boards(search_board, token_type, AiPhase::from_game_state(&game_state))
+            .collect::<Vec<SearchBoard>>();
+        assert_eq!(successors.len(), forward_boards.len(), "number of successors differs in {}", game_state);
+
+        for (successor, forward_board) in successors.into_iter().zip(forward_boards) {
+            check_successors(successor, forward_board, depth - 1);
+        }
+    }
+
+    #[test]
+    fn test_perft_start_position() {
+        let game_state = GameState::default();
+        assert_eq!(perft(game_state, 0), 1);
+        assert_eq!(perft(game_state, 1), 24);
+        assert_eq!(perft(game_state, 2), 552);
+        assert_eq!(perft(game_state, 3), 12144);
+        assert_eq!(perft(game_state, 4), 255024);
+
+        assert_eq!(perft(GameState::new(RuleSet::new(Variant::SixMensMorris)), 4), 43680);
+        /* the fifth placement is the third one of white, which can close a mill */
+        assert_eq!(perft(GameState::new(RuleSet::new(Variant::ThreeMensMorris)), 5), 16560);
+    }
+
+    #[test]
+    fn test_perft_positions() {
+        for (position, counts) in POSITIONS {
+            let game_state = position.parse().unwrap();
+            for (depth, count) in counts.into_iter().enumerate() {
+                assert_eq!(perft(game_state, depth + 1), count, "perft {} of {}", depth + 1, position);
+            }
+        }
+    }
+
+    #[test]
+    fn test_perft_search_board() {
+        for (position, counts) in POSITIONS {
+            let game_state = position.parse().unwrap();
+            for (depth, count) in counts.into_iter().enumerate() {
+                assert_eq!(search_board_perft(game_state, depth + 1), count, "perft {} of {}", depth + 1, position);
+            }
+        }
+    }
+
+    #[test]
+    fn test_successors_agree() {
+        for (position, _) in POSITIONS {
+            let game_state: GameState = position.parse().unwrap();
+            check_successors(game_state, SearchBoard::new(game_state.get_board(), game_state.get_rules()), 3);
+        }
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let game_state: GameState = "WWEEEEEEBBEEEEEEEEEEEEEE w 7 7 4 0".parse().unwrap();
+        let divide = perft_divide(game_state, 2);
+
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), perft(game_state, 2));
+        let actions = divide.iter().map(|(action, _)| action.to_string()).collect::<HashSet<String>>();
+        assert_eq!(actions.len(), divide.len());
+        assert!(actions.contains("a7xd6"));
+        assert!(!actions.contains("a7"));
+        assert!(perft_divide(game_state, 0).is_empty());
+    }
+
+    #[test]
+    fn test_perft_game_over() {
+        let game_state = "BWBWBWBWEEEEEEEEEEEEEEEE w 0 0 18 0".parse().unwrap();
+        assert_eq!(perft(game_state, 0), 1);
+        assert_eq!(perft(game_state, 3), 0);
+        assert_eq!(search_board_perft(game_state, 3), 0);
+    }
+
+    #[test]
+    fn test_perft_captures() {
+        let game_state = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0".parse().unwrap();
+        /* white can jump with three tokens to 18 free positions and d5-g1 closes a mill with three possible captures */
+        assert_eq!(perft(game_state, 1), 3 * 18 - 1 + 3);
+    }
+}
@@ -1,10 +1,61 @@
-pub fn get_token_at(board: u64, position: usize) -> u8 {
-    ((board >> (46 - position * 2)) & 0b11) as u8
+use std::{error::Error, fmt};
+use super::rules::{ParseRulesError, RuleSet, Variant};
+
+pub const BOARD_MASK: u64 = 0b111111111111111111111111111111111111111111111111;
+
+/*
+    A plain game board holding only the 48 bits of the 24 positions.
+    Boards with token and move counters in the upper 16 bits are
+    represented by SearchBoard, so the two can't be mixed up.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Board(u64);
+
+impl Board {
+    pub fn from_raw(raw: u64) -> Board {
+        Board(raw & BOARD_MASK)
+    }
+
+    pub fn to_raw(self) -> u64 {
+        self.0
+    }
+
+    pub fn get_token_at(self, position: usize) -> u8 {
+        ((self.0 >> (46 - position * 2)) & 0b11) as u8
+    }
+
+    pub fn set_token_at(&mut self, position: usize, token: u8) {
+        self.0 = (self.0 & !(0b11 << (46 - position * 2))) | ((token as u64) << (46 - position * 2));
+    }
+
+    pub fn get_number_of_tokens(self, token: u8) -> u8 {
+        if token == 0b11 {
+            (self.0 & 0b010101010101010101010101010101010101010101010101).count_ones() as u8
+        } else {
+            ((!self.0 & 0b010101010101010101010101010101010101010101010101).count_ones() + (self.0 & 0b101010101010101010101010101010101010101010101010).count_ones() - 24) as u8
+        }
+    }
+
+    pub fn token_iter(self) -> impl Iterator<Item = u8> {
+        (0..24).rev().map(move |i| (self.0 >> (i*2) & 0b11) as u8)
+    }
+
+    pub fn reverse_tokens(self) -> Board {
+        let mut board = self.0;
+        let mut reversed_board: u64 = 0;
+        for i in 0..24 {
+            let token = negate_token((board & 0b11) as u8) as u64;
+            reversed_board |= token << (i*2);
+            board >>= 2;
+        }
+        Board(reversed_board)
+    }
 }
 
-pub fn set_token_at(board: u64, position: usize, token: u8) -> u64 {
-    let new_board = board & !(0b11 << (46 - position * 2));
-    new_board | ((token as u64) << (46 - position * 2))
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", encode_positions(*self))
+    }
 }
 
 pub fn negate_token(token: u8) -> u8 {
@@ -15,71 +66,129 @@ pub fn negate_token(token: u8) -> u8 {
     }
 }
 
-pub fn reverse_token_of_board(mut board: u64) -> u64 {
-    let mut reversed_board: u64 = 0;
-    for i in 0..24 {
-        let token = negate_token((board & 0b11) as u8) as u64;
-        reversed_board |= token << (i*2);
-        board >>= 2;
+/* columns and rows of the positions in the printed board, like SCREEN_POS of the ui */
+const PRINT_POS: [(usize, usize); 24] = [
+    (13, 0), (26, 0), (26, 6), (26, 12), (13, 12), (0, 12), (0, 6), (0, 0),
+    (13, 2), (22, 2), (22, 6), (22, 10), (13, 10), (4, 10), (4, 6), (4, 2),
+    (13, 4), (18, 4), (18, 6), (18, 8), (13, 8), (8, 8), (8, 6), (8, 4)
+];
+
+/* the grid of Three Men's Morris has the upper sides of the rings as rows */
+fn get_print_position(rules: RuleSet, position: usize) -> (usize, usize) {
+    if rules.get_variant() == Variant::ThreeMensMorris {
+        (6 * ((position + 1) % 8), 3 * (position / 8))
+    } else {
+        PRINT_POS[position]
     }
-    reversed_board
 }
 
-pub fn create_token_iter<'a>(board: u64) -> impl Iterator<Item = u8> + 'a {
-    (0..24).rev().map(move |i| {
-        let result: u8 = (board >> (i*2) & 0b11) as u8;
-        result
-    })
+/*
+    Draws the board of the variant with E, W and B on its positions and
+    the lines between all neighbors, so the smaller boards and the
+    diagonals of Twelve Men's Morris are shown as they are played. A row
+    is about twice as high as a column is wide, so diagonals go two
+    columns per row.
+*/
+pub fn format_board(board: Board, rules: RuleSet) -> String {
+    let geometry = rules.get_geometry();
+    let positions = (0..24).filter(|position| geometry.has_position(*position)).collect::<Vec<usize>>();
+    let width = positions.iter().map(|position| get_print_position(rules, *position).0 + 1).max().unwrap_or(0);
+    let height = positions.iter().map(|position| get_print_position(rules, *position).1 + 1).max().unwrap_or(0);
+    let mut grid = vec![vec![' '; width]; height];
+
+    for position in positions.iter().copied() {
+        let (x1, y1) = get_print_position(rules, position);
+        for neighbor in geometry.get_neighbors(position).iter().copied().filter(|neighbor| *neighbor > position) {
+            let (x2, y2) = get_print_position(rules, neighbor);
+            if y1 == y2 {
+                (x1.min(x2) + 1..x1.max(x2)).for_each(|x| grid[y1][x] = '-');
+            } else if x1 == x2 {
+                (y1.min(y2) + 1..y1.max(y2)).for_each(|y| grid[y][x1] = '|');
+            } else {
+                let line = if (x1 < x2) == (y1 < y2) { '\\' } else { '/' };
+                let steps = y1.abs_diff(y2);
+                for step in 1..steps {
+                    let x = if x1 < x2 { x1 + (x2 - x1) * step / steps } else { x1 - (x1 - x2) * step / steps };
+                    let y = if y1 < y2 { y1 + step } else { y1 - step };
+                    grid[y][x] = line;
+                }
+            }
+        }
+        grid[y1][x1] = match board.get_token_at(position) {
+            0b00 => 'E',
+            0b10 => 'B',
+            _ => 'W'
+        };
+    }
+
+    grid.into_iter().map(|row| row.into_iter().collect::<String>().trim_end().to_string() + "\n").collect()
 }
 
-pub fn get_number_of_tokens(board: u64, token: u8) -> u8 {
-    if token == 0b11 {
-        (board & 0b010101010101010101010101010101010101010101010101).count_ones() as u8
-    } else {
-        ((!board & 0b010101010101010101010101010101010101010101010101).count_ones() + (board & 0b101010101010101010101010101010101010101010101010).count_ones() - 24) as u8
+pub fn print_board(board: Board, rules: RuleSet) {
+    print!("{}", format_board(board, rules));
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PositionError {
+    InvalidLength(usize),
+    InvalidCharacter(char),
+    MissingField(&'static str),
+    InvalidField(&'static str, String),
+    TrailingInput(String),
+    TooManyTokens(String),
+    OutsideBoard(usize),
+    InconsistentField(&'static str, String),
+    InvalidRules(ParseRulesError)
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::InvalidLength(length) => write!(f, "expected 24 positions but got {}", length),
+            PositionError::InvalidCharacter(char) => write!(f, "invalid character '{}' in encoded positions", char),
+            PositionError::MissingField(field) => write!(f, "missing {}", field),
+            PositionError::InvalidField(field, value) => write!(f, "invalid {} '{}'", field, value),
+            PositionError::TrailingInput(input) => write!(f, "unexpected trailing input '{}'", input),
+            PositionError::TooManyTokens(player) => write!(f, "{} has more tokens than the rules allow", player),
+            PositionError::OutsideBoard(position) => write!(f, "token on position {} which is not part of the board of the rules", position),
+            PositionError::InconsistentField(field, value) => write!(f, "{} '{}' does not match the rest of the position", field, value),
+            PositionError::InvalidRules(error) => write!(f, "invalid rules: {}", error)
+        }
     }
 }
 
-#[allow(dead_code)]
-pub fn print_board(board: u64) {
-    let board_vec = create_token_iter(board).map(|token| if token == 0b00 { "E" } else if token == 0b10 { "B" } else { "W" }).collect::<Vec<&str>>();
-    println!("{}------------{}------------{}   ", board_vec[7], board_vec[0], board_vec[1]);
-    println!("|            |            |      ");
-    println!("|   {}--------{}--------{}   |   ", board_vec[15], board_vec[8], board_vec[9]);
-    println!("|   |        |        |   |      ");
-    println!("|   |   {}----{}----{}   |   |   ", board_vec[23], board_vec[16], board_vec[17]);
-    println!("|   |   |         |   |   |      ");
-    println!("{}---{}---{}         {}---{}---{}", board_vec[6], board_vec[14], board_vec[22], board_vec[18], board_vec[10], board_vec[2]);
-    println!("|   |   |         |   |   |      ");
-    println!("|   |   {}----{}----{}   |   |   ", board_vec[21], board_vec[20], board_vec[19]);
-    println!("|   |        |        |   |      ");
-    println!("|   {}--------{}--------{}   |   ", board_vec[13], board_vec[12], board_vec[11]);
-    println!("|            |            |      ");
-    println!("{}------------{}------------{}   ", board_vec[5], board_vec[4], board_vec[3]);
+impl Error for PositionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PositionError::InvalidRules(error) => Some(error),
+            _ => None
+        }
+    }
 }
 
-#[allow(dead_code)]
-pub fn decode_positions(encoded_positions: String) -> u64 {
-    let mut decoded_positions: u64 = 0b0;
+pub fn decode_positions(encoded_positions: String) -> Result<Board, PositionError> {
+    let encoded_positions = encoded_positions.trim();
+    if encoded_positions.chars().count() != 24 {
+        return Err(PositionError::InvalidLength(encoded_positions.chars().count()));
+    }
 
-    for char in encoded_positions.trim().chars() {
+    let mut decoded_positions: u64 = 0b0;
+    for char in encoded_positions.chars() {
+        decoded_positions <<= 2;
         match char {
             'B' => decoded_positions |= 0b10,
             'W' => decoded_positions |= 0b11,
             'E' => (),
-            _ => panic!("Invalid character in encoded positions")
+            _ => return Err(PositionError::InvalidCharacter(char))
         }
-        decoded_positions <<= 2;
     }
-    decoded_positions >>= 2;
-    decoded_positions
+    Ok(Board(decoded_positions))
 }
 
-#[allow(dead_code)]
-pub fn encode_positions(board: u64) -> String {
+pub fn encode_positions(board: Board) -> String {
     let mut encoded_positions = String::new();
     for index in 0..24 {
-        match (board & (0b11 << (46 - index*2))) >> (46 - index*2) {
+        match board.get_token_at(index) {
             0b10 => encoded_positions.push('B'),
             0b11 => encoded_positions.push('W'),
             _ => encoded_positions.push('E')
@@ -90,47 +199,47 @@ pub fn encode_positions(board: u64) -> String {
 
 #[cfg(test)]
 pub mod tests {
-    use crate::logic::position::{decode_positions, encode_positions, get_number_of_tokens, get_token_at, negate_token, reverse_token_of_board};
-    use super::create_token_iter;
+    use crate::logic::{
+        position::{decode_positions, encode_positions, format_board, negate_token, Board, PositionError},
+        rules::{RuleSet, Variant}
+    };
 
     #[test]
     fn test_get_token_at() {
-        let board1: u64 = 0b101000000011110011101110110010110011101100100010; // BBEEEWWE WBWBWEBW EWBWEBEB
-
-        assert_eq!(0b10, get_token_at(board1, 0));
-        assert_eq!(0b10, get_token_at(board1, 1));
-        assert_eq!(0b00, get_token_at(board1, 2));
-        assert_eq!(0b00, get_token_at(board1, 3));
-        assert_eq!(0b00, get_token_at(board1, 4));
-        assert_eq!(0b11, get_token_at(board1, 5));
-        assert_eq!(0b11, get_token_at(board1, 6));
-        assert_eq!(0b00, get_token_at(board1, 7));
-        assert_eq!(0b11, get_token_at(board1, 8));
-        assert_eq!(0b10, get_token_at(board1, 9));
-        assert_eq!(0b11, get_token_at(board1, 10));
-        assert_eq!(0b10, get_token_at(board1, 11));
-        assert_eq!(0b11, get_token_at(board1, 12));
-        assert_eq!(0b00, get_token_at(board1, 13));
-        assert_eq!(0b10, get_token_at(board1, 14));
-        assert_eq!(0b11, get_token_at(board1, 15));
-        assert_eq!(0b00, get_token_at(board1, 16));
-        assert_eq!(0b11, get_token_at(board1, 17));
-        assert_eq!(0b10, get_token_at(board1, 18));
-        assert_eq!(0b11, get_token_at(board1, 19));
-        assert_eq!(0b00, get_token_at(board1, 20));
-        assert_eq!(0b10, get_token_at(board1, 21));
-        assert_eq!(0b00, get_token_at(board1, 22));
-        assert_eq!(0b10, get_token_at(board1, 23));
+        let board1 = Board::from_raw(0b101000000011110011101110110010110011101100100010); // BBEEEWWE WBWBWEBW EWBWEBEB
+
+        assert_eq!(0b10, board1.get_token_at(0));
+        assert_eq!(0b10, board1.get_token_at(1));
+        assert_eq!(0b00, board1.get_token_at(2));
+        assert_eq!(0b00, board1.get_token_at(3));
+        assert_eq!(0b00, board1.get_token_at(4));
+        assert_eq!(0b11, board1.get_token_at(5));
+        assert_eq!(0b11, board1.get_token_at(6));
+        assert_eq!(0b00, board1.get_token_at(7));
+        assert_eq!(0b11, board1.get_token_at(8));
+        assert_eq!(0b10, board1.get_token_at(9));
+        assert_eq!(0b11, board1.get_token_at(10));
+        assert_eq!(0b10, board1.get_token_at(11));
+        assert_eq!(0b11, board1.get_token_at(12));
+        assert_eq!(0b00, board1.get_token_at(13));
+        assert_eq!(0b10, board1.get_token_at(14));
+        assert_eq!(0b11, board1.get_token_at(15));
+        assert_eq!(0b00, board1.get_token_at(16));
+        assert_eq!(0b11, board1.get_token_at(17));
+        assert_eq!(0b10, board1.get_token_at(18));
+        assert_eq!(0b11, board1.get_token_at(19));
+        assert_eq!(0b00, board1.get_token_at(20));
+        assert_eq!(0b10, board1.get_token_at(21));
+        assert_eq!(0b00, board1.get_token_at(22));
+        assert_eq!(0b10, board1.get_token_at(23));
     }
 
     #[test]
     fn test_set_token_at() {
-        use super::set_token_at;
-
-        let mut board = 0b0;
-        board = set_token_at(board, 0, 0b11);
-        board = set_token_at(board, 23, 0b11);
-        assert_eq!(0b110000000000000000000000000000000000000000000011, board);
+        let mut board = Board::default();
+        board.set_token_at(0, 0b11);
+        board.set_token_at(23, 0b11);
+        assert_eq!(0b110000000000000000000000000000000000000000000011, board.to_raw());
     }
 
     #[test]
@@ -143,21 +252,21 @@ pub mod tests {
     }
 
     #[test]
-    fn test_reverse_token_of_board() {
-        let board = 0b101000000011110011101110110010110011101100100010;
-        let reversed_board = 0b111100000010100010111011100011100010111000110011;
+    fn test_reverse_tokens() {
+        let board = Board::from_raw(0b101000000011110011101110110010110011101100100010);
+        let reversed_board = Board::from_raw(0b111100000010100010111011100011100010111000110011);
 
-        assert_eq!(reversed_board, reverse_token_of_board(board));
+        assert_eq!(reversed_board, board.reverse_tokens());
     }
 
     #[test]
-    fn test_create_token_iter() {
-        let board1: u64 = 0b0;
-        for position in create_token_iter(board1) {
+    fn test_token_iter() {
+        let board1 = Board::default();
+        for position in board1.token_iter() {
             assert_eq!(0b00, position);
         }
 
-        let board2: u64 = 0b101000000011110011101110110010110011101100100010;
+        let board2 = Board::from_raw(0b101000000011110011101110110010110011101100100010);
         let expected_positions: Vec<u8> = vec![
             0b10,
             0b00,
@@ -184,22 +293,28 @@ pub mod tests {
             0b10,
             0b10
         ];
-        for (index, position) in create_token_iter(board2).enumerate() {
+        for (index, position) in board2.token_iter().enumerate() {
             assert_eq!(expected_positions[23 - index], position);
         }
     }
 
     #[test]
     fn test_get_number_of_tokens() {
-        let board1 = 0b0;
-        let board2 = 0b101000000011110011101110110010110011101100100010;
-        let board3 = 0b000000000011110011101110110010110011101100100010;
-        let board4 = 0b111100000011110011101110110010110011101100100010;
+        let board1 = Board::default();
+        let board2 = Board::from_raw(0b101000000011110011101110110010110011101100100010);
+        let board3 = Board::from_raw(0b000000000011110011101110110010110011101100100010);
+        let board4 = Board::from_raw(0b111100000011110011101110110010110011101100100010);
+
+        assert_eq!((0, 0), (board1.get_number_of_tokens(0b10), board1.get_number_of_tokens(0b11)));
+        assert_eq!((8, 8), (board2.get_number_of_tokens(0b10), board2.get_number_of_tokens(0b11)));
+        assert_eq!((6, 8), (board3.get_number_of_tokens(0b10), board3.get_number_of_tokens(0b11)));
+        assert_eq!((6, 10), (board4.get_number_of_tokens(0b10), board4.get_number_of_tokens(0b11)));
+    }
 
-        assert_eq!((0, 0), (get_number_of_tokens(board1, 0b10), get_number_of_tokens(board1, 0b11)));
-        assert_eq!((8, 8), (get_number_of_tokens(board2, 0b10), get_number_of_tokens(board2, 0b11)));
-        assert_eq!((6, 8), (get_number_of_tokens(board3, 0b10), get_number_of_tokens(board3, 0b11)));
-        assert_eq!((6, 10), (get_number_of_tokens(board4, 0b10), get_number_of_tokens(board4, 0b11)));
+    #[test]
+    fn test_from_raw() {
+        let board = Board::from_raw(0b1111111111111111101000000011110011101110110010110011101100100010);
+        assert_eq!(0b101000000011110011101110110010110011101100100010, board.to_raw());
     }
 
     #[test]
@@ -208,19 +323,26 @@ pub mod tests {
         let encoded_positions2 = "BBEEEWWEWBWBWEBWEWBWEBEB";
         let encoded_positions3 = "WEEWEWBWBBEEBWEWEEEBEEEE";
 
-        let expected_decoded_positions = 0b0;
-        let expected_decoded_positions2 = 0b101000000011110011101110110010110011101100100010;
-        let expected_positions3 = 0b110000110011101110100000101100110000001000000000;
+        let expected_decoded_positions = Board::default();
+        let expected_decoded_positions2 = Board::from_raw(0b101000000011110011101110110010110011101100100010);
+        let expected_positions3 = Board::from_raw(0b110000110011101110100000101100110000001000000000);
         
-        assert_eq!(expected_decoded_positions, decode_positions(encoded_positions.to_string()));
-        assert_eq!(expected_decoded_positions2, decode_positions(encoded_positions2.to_string()));
-        assert_eq!(expected_positions3, decode_positions(encoded_positions3.to_string()));
+        assert_eq!(expected_decoded_positions, decode_positions(encoded_positions.to_string()).unwrap());
+        assert_eq!(expected_decoded_positions2, decode_positions(encoded_positions2.to_string()).unwrap());
+        assert_eq!(expected_positions3, decode_positions(encoded_positions3.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_decode_positions_errors() {
+        assert_eq!(decode_positions("EEEEEEEEEEEEEEEEEEEEEEE".to_string()), Err(PositionError::InvalidLength(23)));
+        assert_eq!(decode_positions("EEEEEEEEEEEEEEEEEEEEEEEEE".to_string()), Err(PositionError::InvalidLength(25)));
+        assert_eq!(decode_positions("EEEEEEEEEEEEEEEEEEEEEEEX".to_string()), Err(PositionError::InvalidCharacter('X')));
     }
 
     #[test]
     fn test_encode_positions() {
-        let positions = 0b0;
-        let positions2 = 0b101000000011110011101110110010110011101100100010;
+        let positions = Board::default();
+        let positions2 = Board::from_raw(0b101000000011110011101110110010110011101100100010);
 
         let expected_encoded_positions = "EEEEEEEEEEEEEEEEEEEEEEEE";
         let expected_encoded_positions2 = "BBEEEWWEWBWBWEBWEWBWEBEB";
@@ -228,4 +350,22 @@ pub mod tests {
         assert_eq!(expected_encoded_positions, encode_positions(positions));
         assert_eq!(expected_encoded_positions2, encode_positions(positions2))
     }
+
+    #[test]
+    fn test_format_board() {
+        let nine = format_board(decode_positions("WEEEEEEBEEEEEEEEEEEEEEEE".to_string()).unwrap(), RuleSet::default());
+        assert_eq!(nine.lines().count(), 13);
+        assert_eq!(nine.lines().next(), Some("B------------W------------E"));
+        assert_eq!(nine.lines().nth(6), Some("E---E---E         E---E---E"));
+
+        let twelve = format_board(Board::default(), RuleSet::new(Variant::TwelveMensMorris));
+        assert_eq!(twelve.lines().nth(1), Some("| \\          |          / |"));
+
+        let six = format_board(Board::default(), RuleSet::new(Variant::SixMensMorris));
+        assert_eq!(six.lines().nth(6), Some("E---E                 E---E"));
+        assert_eq!(six.matches('E').count(), 16);
+
+        let three = format_board(decode_positions("WEEEEEEWBEEEEEEBEEEEEEEE".to_string()).unwrap(), RuleSet::new(Variant::ThreeMensMorris));
+        assert_eq!(three, "W-----W-----E\n| \\   |   / |\n|   \\ | /   |\nB-----B-----E\n|   / | \\   |\n| /   |   \\ |\nE-----E-----E\n");
+    }
 }
@@ -0,0 +1,617 @@
+use std::{error::Error, fmt, str::FromStr};
+use super::{mill_detection::MILLS, r#move::NEIGHBORS};
+
+const MAX_NEIGHBORS: usize = 8;
+const MAX_MILLS: usize = 20;
+const MAX_POSITION_MILLS: usize = 4;
+
+/*
+    The diagonals of Twelve Men's Morris connect the corners of the three
+    rings, so each of them is an additional line of neighbors and a mill.
+*/
+const DIAGONAL_MILLS: [[usize; 3]; 4] = [
+    [7, 15, 23],
+    [1, 9, 17],
+    [3, 11, 19],
+    [5, 13, 21]
+];
+const TWELVE_MENS_MILLS: [[usize; 3]; 20] = get_twelve_mens_mills();
+
+/*
+    Six Men's Morris is played on the outer and the middle ring, which
+    are connected in the middle of their sides. Only the sides of the
+    rings are mills.
+*/
+const SIX_MENS_MILLS: [[usize; 3]; 8] = [
+    [7, 0, 1],
+    [15, 8, 9],
+    [13, 12, 11],
+    [5, 4, 3],
+    [7, 6, 5],
+    [15, 14, 13],
+    [9, 10, 11],
+    [1, 2, 3]
+];
+
+/*
+    Three Men's Morris is played on a grid of 3 by 3 positions, whose
+    rows, columns and diagonals are the mills. The rows are the upper
+    sides of the three rings, so the center of the grid is position 8.
+*/
+const THREE_MENS_MILLS: [[usize; 3]; 8] = [
+    [7, 0, 1],
+    [15, 8, 9],
+    [23, 16, 17],
+    [7, 15, 23],
+    [0, 8, 16],
+    [1, 9, 17],
+    [7, 8, 17],
+    [1, 8, 23]
+];
+
+static NINE_MENS_MORRIS: Geometry = Geometry::new(get_nine_mens_neighbors(), &MILLS);
+static TWELVE_MENS_MORRIS: Geometry = Geometry::new(add_lines(get_nine_mens_neighbors(), &DIAGONAL_MILLS), &TWELVE_MENS_MILLS);
+static SIX_MENS_MORRIS: Geometry = Geometry::new(get_six_mens_neighbors(), &SIX_MENS_MILLS);
+static THREE_MENS_MORRIS: Geometry = Geometry::new(add_lines([[24; MAX_NEIGHBORS]; 24], &THREE_MENS_MILLS), &THREE_MENS_MILLS);
+
+const fn get_twelve_mens_mills() -> [[usize; 3]; 20] {
+    let mut mills = [[0; 3]; 20];
+    let mut index = 0;
+    while index < 20 {
+        mills[index] = if index < 16 { MILLS[index] } else { DIAGONAL_MILLS[index - 16] };
+        index += 1;
+    }
+    mills
+}
+
+const fn add_neighbor(mut neighbors: [[usize; MAX_NEIGHBORS]; 24], position: usize, neighbor: usize) -> [[usize; MAX_NEIGHBORS]; 24] {
+    let mut index = 0;
+    while neighbors[position][index] != 24 {
+        index += 1;
+    }
+    neighbors[position][index] = neighbor;
+    neighbors
+}
+
+/* connects the neighboring positions of every line of three positions */
+const fn add_lines(mut neighbors: [[usize; MAX_NEIGHBORS]; 24], lines: &[[usize; 3]]) -> [[usize; MAX_NEIGHBORS]; 24] {
+    let mut index = 0;
+    while index < lines.len() {
+        let [first, middle, last] = lines[index];
+        neighbors = add_neighbor(neighbors, first, middle);
+        neighbors = add_neighbor(neighbors, middle, first);
+        neighbors = add_neighbor(neighbors, middle, last);
+        neighbors = add_neighbor(neighbors, last, middle);
+        index += 1;
+    }
+    neighbors
+}
+
+const fn get_nine_mens_neighbors() -> [[usize; MAX_NEIGHBORS]; 24] {
+    let mut neighbors = [[24; MAX_NEIGHBORS]; 24];
+    let mut position = 0;
+    while position < 24 {
+        let mut index = 0;
+        while index < NEIGHBORS[position].len() {
+            neighbors[position][index] = NEIGHBORS[position][index];
+            index += 1;
+        }
+        position += 1;
+    }
+    neighbors
+}
+
+/* the neighbors of Nine Men's Morris without the inner ring */
+const fn get_six_mens_neighbors() -> [[usize; MAX_NEIGHBORS]; 24] {
+    let mut neighbors = [[24; MAX_NEIGHBORS]; 24];
+    let mut position = 0;
+    while position < 16 {
+        let mut index = 0;
+        while index < NEIGHBORS[position].len() {
+            if NEIGHBORS[position][index] < 16 {
+                neighbors = add_neighbor(neighbors, position, NEIGHBORS[position][index]);
+            }
+            index += 1;
+        }
+        position += 1;
+    }
+    neighbors
+}
+
+/*
+    The board of a variant: which positions are neighbors and which lines
+    of three positions are mills. The positions are numbered as in
+    GameState, smaller boards only use some of them, the ones with
+    neighbors. Mills are also stored as masks of the raw board with white
+    tokens on their positions, and every position knows the indices of
+    the mills it is part of.
+*/
+pub struct Geometry {
+    positions: u32,
+    neighbors: [[usize; MAX_NEIGHBORS]; 24],
+    neighbor_counts: [usize; 24],
+    mills: &'static [[usize; 3]],
+    mill_masks: [u64; MAX_MILLS],
+    position_mills: [[usize; MAX_POSITION_MILLS]; 24],
+    position_mill_counts: [usize; 24]
+}
+
+impl Geometry {
+    /* neighbors are filled up with 24 like NEIGHBORS */
+    const fn new(neighbors: [[usize; MAX_NEIGHBORS]; 24], mills: &'static [[usize; 3]]) -> Geometry {
+        let mut positions = 0;
+        let mut neighbor_counts = [0; 24];
+        let mut position = 0;
+        while position < 24 {
+            while neighbor_counts[position] < MAX_NEIGHBORS && neighbors[position][neighbor_counts[position]] != 24 {
+                neighbor_counts[position] += 1;
+            }
+            if neighbor_counts[position] > 0 {
+                positions |= 1 << position;
+            }
+            position += 1;
+        }
+
+        let mut mill_masks = [0; MAX_MILLS];
+        let mut position_mills = [[0; MAX_POSITION_MILLS]; 24];
+        let mut position_mill_counts = [0; 24];
+        let mut index = 0;
+        while index < mills.len() {
+            let mut offset = 0;
+            while offset < 3 {
+                let position = mills[index][offset];
+                mill_masks[index] |= 0b11 << (46 - position * 2);
+                position_mills[position][position_mill_counts[position]] = index;
+                position_mill_counts[position] += 1;
+                offset += 1;
+            }
+            index += 1;
+        }
+
+        Geometry { positions, neighbors, neighbor_counts, mills, mill_masks, position_mills, position_mill_counts }
+    }
+
+    /* whether the position is part of the board */
+    pub fn has_position(&self, position: usize) -> bool {
+        self.positions & (1 << position) != 0
+    }
+
+    pub fn get_position_count(&self) -> usize {
+        self.positions.count_ones() as usize
+    }
+
+    pub fn get_neighbors(&self, position: usize) -> &[usize] {
+        &self.neighbors[position][..self.neighbor_counts[position]]
+    }
+
+    pub fn is_neighbor(&self, position1: usize, position2: usize) -> bool {
+        self.get_neighbors(position1).contains(&position2)
+    }
+
+    pub fn get_mills(&self) -> &'static [[usize; 3]] {
+        self.mills
+    }
+
+    /* the masks of the mills in the order of get_mills */
+    pub fn get_mill_masks(&self) -> &[u64] {
+        &self.mill_masks[..self.mills.len()]
+    }
+
+    /* the indices of the mills the position is part of */
+    pub fn get_position_mills(&self, position: usize) -> &[usize] {
+        &self.position_mills[position][..self.position_mill_counts[position]]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    #[default]
+    NineMensMorris,
+    TwelveMensMorris,
+    SixMensMorris,
+    ThreeMensMorris
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [Variant::NineMensMorris, Variant::TwelveMensMorris, Variant::SixMensMorris, Variant::ThreeMensMorris];
+
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Variant::NineMensMorris => "nine",
+            Variant::TwelveMensMorris => "twelve",
+            Variant::SixMensMorris => "six",
+            Variant::ThreeMensMorris => "three"
+        }
+    }
+
+    pub fn get_title(self) -> &'static str {
+        match self {
+            Variant::NineMensMorris => "Nine Men's Morris",
+            Variant::TwelveMensMorris => "Twelve Men's Morris",
+            Variant::SixMensMorris => "Six Men's Morris",
+            Variant::ThreeMensMorris => "Three Men's Morris"
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseRulesError {
+    UnknownVariant(String),
+    UnknownOption(String),
+    UnreachableDraw(u16, u16)
+}
+
+impl fmt::Display for ParseRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseRulesError::UnknownVariant(name) => write!(
+                f, "unknown variant '{}', expected one of {}", name, Variant::ALL.map(Variant::get_name).join(", ")
+            ),
+            ParseRulesError::UnknownOption(option) => write!(
+                f, "unknown rule option '{}', expected flying, noflying, doublecapture, millcapture, draw<moves> or blockeddraw", option
+            ),
+            ParseRulesError::UnreachableDraw(moves, max_moves) => write!(
+                f, "a draw after {} moves without a capture can't be reached, at most {} moves are possible", moves, max_moves
+            )
+        }
+    }
+}
+
+impl Error for ParseRulesError {}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ParseRulesError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Variant::ALL.into_iter()
+            .find(|variant| variant.get_name() == name)
+            .ok_or_else(|| ParseRulesError::UnknownVariant(name.to_string()))
+    }
+}
+
+/* what happens to a player that can't move at their first move after the set phase */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BlockedOutcome {
+    #[default]
+    Loss,
+    Draw
+}
+
+/*
+    The rules a game is played with. Every function that depends on the
+    board or the number of tokens gets the rules passed explicitly, the
+    default are the rules of Nine Men's Morris.
+
+    Twelve Men's Morris is played with 12 tokens per player on the board
+    of Nine Men's Morris with the diagonals between the corners of the
+    rings as additional lines. A full board after the set phase leaves
+    the player to move without a legal move, who loses the game.
+
+    Six Men's Morris with 6 tokens and Three Men's Morris with 3 tokens
+    are played on the smaller boards of their geometries and without
+    flying, so a player with three tokens that can't move loses. In Three
+    Men's Morris every capture ends the game, so the first mill wins.
+
+    On top of the variant the rules have options for the house rules
+    that differ between players:
+    - flying with three tokens, which the variant decides by default
+    - a second capture when an action closes two mills at once
+    - capturing tokens from mills even if the opponent has other tokens
+    - a draw after the given number of moves of each player without a
+      capture or placement
+    - a draw instead of a loss for a player that is blocked at their
+      first move after the set phase, e.g. on the full board of Twelve
+      Men's Morris
+    The repetition of a position three times is always a draw.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RuleSet {
+    variant: Variant,
+    flying: bool,
+    double_capture: bool,
+    capture_from_mills: bool,
+    no_capture_moves: Option<u16>,
+    blocked_outcome: BlockedOutcome
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::new(Variant::default())
+    }
+}
+
+impl RuleSet {
+    pub fn new(variant: Variant) -> Self {
+        RuleSet {
+            variant,
+            flying: RuleSet::has_flying_by_default(variant),
+            double_capture: false,
+            capture_from_mills: false,
+            no_capture_moves: None,
+            blocked_outcome: BlockedOutcome::Loss
+        }
+    }
+
+    fn has_flying_by_default(variant: Variant) -> bool {
+        matches!(variant, Variant::NineMensMorris | Variant::TwelveMensMorris)
+    }
+
+    pub fn with_flying(mut self, flying: bool) -> Self {
+        self.flying = flying;
+        self
+    }
+
+    pub fn with_double_capture(mut self, double_capture: bool) -> Self {
+        self.double_capture = double_capture;
+        self
+    }
+
+    pub fn with_capture_from_mills(mut self, capture_from_mills: bool) -> Self {
+        self.capture_from_mills = capture_from_mills;
+        self
+    }
+
+    /* the moves are limited to get_max_no_capture_draw */
+    pub fn with_no_capture_draw(mut self, moves: Option<u16>) -> Self {
+        self.no_capture_moves = moves.map(|moves| moves.min(self.get_max_no_capture_draw()));
+        self
+    }
+
+    pub fn with_blocked_outcome(mut self, blocked_outcome: BlockedOutcome) -> Self {
+        self.blocked_outcome = blocked_outcome;
+        self
+    }
+
+    pub fn get_variant(self) -> Variant {
+        self.variant
+    }
+
+    pub fn get_geometry(self) -> &'static Geometry {
+        match self.variant {
+            Variant::NineMensMorris => &NINE_MENS_MORRIS,
+            Variant::TwelveMensMorris => &TWELVE_MENS_MORRIS,
+            Variant::SixMensMorris => &SIX_MENS_MORRIS,
+            Variant::ThreeMensMorris => &THREE_MENS_MORRIS
+        }
+    }
+
+    pub fn get_tokens_per_player(self) -> u8 {
+        match self.variant {
+            Variant::NineMensMorris => 9,
+            Variant::TwelveMensMorris => 12,
+            Variant::SixMensMorris => 6,
+            Variant::ThreeMensMorris => 3
+        }
+    }
+
+    /* the number of tokens with which a player may move to every empty position */
+    pub fn get_flying_tokens(self) -> Option<u8> {
+        if self.flying {
+            Some(3)
+        } else {
+            None
+        }
+    }
+
+    pub fn can_fly(self, number_of_tokens: u8) -> bool {
+        self.get_flying_tokens() == Some(number_of_tokens)
+    }
+
+    pub fn has_double_capture(self) -> bool {
+        self.double_capture
+    }
+
+    pub fn can_capture_from_mills(self) -> bool {
+        self.capture_from_mills
+    }
+
+    /* the number of moves of each player without a capture after which the game is drawn */
+    pub fn get_no_capture_draw(self) -> Option<u16> {
+        self.no_capture_moves
+    }
+
+    /*
+        The step counter of a game has 16 bits, so the draw has to happen
+        before it stops counting, even if nothing was captured after the
+        set phase.
+    */
+    pub fn get_max_no_capture_draw(self) -> u16 {
+        (u16::MAX - self.get_set_plies() as u16) / 2
+    }
+
+    pub fn is_no_capture_draw(self, reversible_plies: u16) -> bool {
+        self.no_capture_moves.is_some_and(|moves| reversible_plies / 2 >= moves)
+    }
+
+    pub fn get_blocked_outcome(self) -> BlockedOutcome {
+        self.blocked_outcome
+    }
+
+    /* whether a blocked player with the step counter draws instead of losing */
+    pub fn is_blocked_draw(self, step_counter: u16) -> bool {
+        self.blocked_outcome == BlockedOutcome::Draw && step_counter < self.get_set_plies() as u16 + 2
+    }
+
+    /* the number of plies of the set phase */
+    pub fn get_set_plies(self) -> u8 {
+        2 * self.get_tokens_per_player()
+    }
+
+    /*
+        The counters of SearchBoard have 3 bits for the tokens and 5 bits
+        for the moves of each player, which fits every board of Nine Men's
+        Morris and the smaller boards, so they can be updated with every
+        action. With more tokens or neighbors they can overflow, so they
+        are counted again on every board and saturate.
+    */
+    pub fn has_incremental_counters(self) -> bool {
+        self.variant != Variant::TwelveMensMorris
+    }
+
+    /*
+        The part of the keys of the transposition table that tells the
+        rules apart. The options take the 7 bits from bit 54 on and the
+        number of moves of the no capture draw, which doesn't fit next to
+        the board, is spread over all bits like a Zobrist key. The default
+        rules have the key 0, so their keys are the same as without rules.
+        The reversible plies of the positions are not part of the key.
+    */
+    pub fn get_key(self) -> u64 {
+        let options = self.variant as u64
+            | ((self.flying != RuleSet::has_flying_by_default(self.variant)) as u64) << 2
+            | (self.double_capture as u64) << 3
+            | (self.capture_from_mills as u64) << 4
+            | (self.no_capture_moves.is_some() as u64) << 5
+            | (self.blocked_outcome as u64) << 6;
+        let draw = self.no_capture_moves.map_or(0, |moves| (moves as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15));
+        (options << 54) ^ draw
+    }
+}
+
+/*
+    The rules are written as the name of the variant followed by the
+    options that differ from the variant, separated by '+', e.g.
+    "nine+doublecapture+draw50".
+*/
+impl fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.variant)?;
+        if self.flying != RuleSet::has_flying_by_default(self.variant) {
+            write!(f, "+{}", if self.flying { "flying" } else { "noflying" })?;
+        }
+        if self.double_capture {
+            write!(f, "+doublecapture")?;
+        }
+        if self.capture_from_mills {
+            write!(f, "+millcapture")?;
+        }
+        if let Some(moves) = self.no_capture_moves {
+            write!(f, "+draw{}", moves)?;
+        }
+        if self.blocked_outcome == BlockedOutcome::Draw {
+            write!(f, "+blockeddraw")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = ParseRulesError;
+
+    fn from_str(rules: &str) -> Result<Self, Self::Err> {
+        let mut fields = rules.split('+');
+        let mut rule_set = RuleSet::new(fields.next().unwrap_or_default().parse()?);
+        for option in fields {
+            rule_set = match option {
+                "flying" => rule_set.with_flying(true),
+                "noflying" => rule_set.with_flying(false),
+                "doublecapture" => rule_set.with_double_capture(true),
+                "millcapture" => rule_set.with_capture_from_mills(true),
+                "blockeddraw" => rule_set.with_blocked_outcome(BlockedOutcome::Draw),
+                _ => match option.strip_prefix("draw").and_then(|moves| moves.parse::<u16>().ok()) {
+                    Some(moves) if moves > rule_set.get_max_no_capture_draw() => {
+                        return Err(ParseRulesError::UnreachableDraw(moves, rule_set.get_max_no_capture_draw()))
+                    },
+                    Some(moves) if moves > 0 => rule_set.with_no_capture_draw(Some(moves)),
+                    _ => return Err(ParseRulesError::UnknownOption(option.to_string()))
+                }
+            };
+        }
+        Ok(rule_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockedOutcome, ParseRulesError, RuleSet, Variant};
+
+    #[test]
+    fn test_geometry() {
+        for variant in Variant::ALL {
+            let geometry = RuleSet::new(variant).get_geometry();
+            for position in 0..24 {
+                for neighbor in geometry.get_neighbors(position) {
+                    assert!(geometry.is_neighbor(*neighbor, position));
+                }
+                for index in geometry.get_position_mills(position) {
+                    assert!(geometry.get_mills()[*index].contains(&position));
+                    assert!(geometry.has_position(position));
+                }
+            }
+            assert_eq!(geometry.get_mill_masks().len(), geometry.get_mills().len());
+            assert!(geometry.get_position_count() >= 2 * RuleSet::new(variant).get_tokens_per_player() as usize);
+        }
+
+        let nine = RuleSet::default().get_geometry();
+        let twelve = RuleSet::new(Variant::TwelveMensMorris).get_geometry();
+        assert_eq!(nine.get_mills().len(), 16);
+        assert_eq!(twelve.get_mills().len(), 20);
+        assert!(!nine.is_neighbor(1, 9));
+        assert!(twelve.is_neighbor(1, 9) && twelve.is_neighbor(9, 17) && !twelve.is_neighbor(1, 17));
+        assert_eq!(twelve.get_neighbors(9).len(), 4);
+        assert_eq!(twelve.get_position_mills(23).len(), 3);
+        assert_eq!(nine.get_mill_masks()[0], 0b111100000000001100000000000000000000000000000000);
+
+        let six = RuleSet::new(Variant::SixMensMorris).get_geometry();
+        assert_eq!((six.get_position_count(), six.get_mills().len()), (16, 8));
+        assert_eq!(nine.get_position_count(), 24);
+        assert!(six.is_neighbor(0, 8) && !six.is_neighbor(8, 16) && !six.has_position(16));
+
+        let three = RuleSet::new(Variant::ThreeMensMorris).get_geometry();
+        assert_eq!((three.get_position_count(), three.get_mills().len()), (9, 8));
+        assert_eq!(three.get_neighbors(8).len(), 8);
+        assert_eq!(three.get_position_mills(8).len(), 4);
+        assert!(three.is_neighbor(7, 15) && three.is_neighbor(17, 8) && !three.is_neighbor(7, 1) && !three.has_position(2));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(RuleSet::default().get_set_plies(), 18);
+        assert_eq!(RuleSet::new(Variant::TwelveMensMorris).get_set_plies(), 24);
+        for variant in Variant::ALL {
+            assert_eq!(RuleSet::new(variant).to_string().parse(), Ok(RuleSet::new(variant)));
+        }
+        assert_eq!("twelve".parse::<RuleSet>().map(RuleSet::get_variant), Ok(Variant::TwelveMensMorris));
+        assert_eq!("three".parse::<RuleSet>().map(RuleSet::get_flying_tokens), Ok(None));
+        assert!(RuleSet::default().can_fly(3) && !RuleSet::default().can_fly(4));
+        assert_eq!("seven".parse::<RuleSet>(), Err(ParseRulesError::UnknownVariant("seven".to_string())));
+        assert_eq!("nine+drawn".parse::<RuleSet>(), Err(ParseRulesError::UnknownOption("drawn".to_string())));
+        assert_eq!("nine+draw0".parse::<RuleSet>(), Err(ParseRulesError::UnknownOption("draw0".to_string())));
+        assert_eq!("nine+draw200".parse::<RuleSet>().map(RuleSet::get_no_capture_draw), Ok(Some(200)));
+        assert_eq!("nine+draw32758".parse::<RuleSet>().map(RuleSet::get_no_capture_draw), Ok(Some(32758)));
+        assert_eq!("nine+draw32759".parse::<RuleSet>(), Err(ParseRulesError::UnreachableDraw(32759, 32758)));
+        assert_eq!("twelve+draw32756".parse::<RuleSet>(), Err(ParseRulesError::UnreachableDraw(32756, 32755)));
+        assert_eq!(RuleSet::default().with_no_capture_draw(Some(u16::MAX)).get_no_capture_draw(), Some(32758));
+    }
+
+    #[test]
+    fn test_rule_options() {
+        let rules = RuleSet::default()
+            .with_flying(false)
+            .with_double_capture(true)
+            .with_capture_from_mills(true)
+            .with_no_capture_draw(Some(50))
+            .with_blocked_outcome(BlockedOutcome::Draw);
+        assert_eq!(rules.to_string(), "nine+noflying+doublecapture+millcapture+draw50+blockeddraw");
+        assert_eq!(rules.to_string().parse(), Ok(rules));
+        assert_eq!("six+flying".parse::<RuleSet>().map(RuleSet::get_flying_tokens), Ok(Some(3)));
+        assert_eq!(RuleSet::new(Variant::SixMensMorris).to_string(), "six");
+        assert!(!rules.can_fly(3) && rules.has_double_capture() && rules.can_capture_from_mills());
+
+        assert!(!rules.is_no_capture_draw(99) && rules.is_no_capture_draw(100));
+        assert!(!RuleSet::default().is_no_capture_draw(u16::MAX));
+        assert!(rules.is_blocked_draw(19) && !rules.is_blocked_draw(20));
+        assert!(!RuleSet::default().is_blocked_draw(18));
+
+        assert_eq!(RuleSet::default().get_key(), 0);
+        assert_ne!(rules.get_key(), RuleSet::default().get_key());
+        assert_ne!(RuleSet::default().with_double_capture(true).get_key(), RuleSet::default().with_capture_from_mills(true).get_key());
+        assert_ne!(RuleSet::default().with_no_capture_draw(Some(50)).get_key(), RuleSet::default().with_no_capture_draw(Some(60)).get_key());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::logic::{action::Action, position::Board, rules::RuleSet};
+
+pub const SYMMETRY_COUNT: usize = 16;
+
+const PERMUTATIONS: [[usize; 24]; SYMMETRY_COUNT] = get_permutations();
+
+/*
+    The board has 16 symmetries that keep all mills and neighbors: the
+    four rotations by 90 degrees, each of them with or without a
+    reflection, and all of them with or without swapping the outer and
+    the inner ring. Every ring is numbered clockwise starting at the
+    middle of its upper side, so a position is its ring times 8 plus its
+    index in the ring and a rotation by 90 degrees adds 2 to the index.
+
+    The symmetry with the number 0 is the identity. Bits 0 and 1 are the
+    rotation, bit 2 the reflection at the vertical axis and bit 3 the
+    swap of the rings. The reflection is applied before the rotation.
+
+    The smaller boards of other rules only keep some of them, e.g. Six
+    Men's Morris has no inner ring to swap the outer ring with.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Symmetry(u8);
+
+const fn transform(symmetry: usize, position: usize) -> usize {
+    let (ring, index) = (position / 8, position % 8);
+    let index = if symmetry & 0b100 != 0 { (8 - index) % 8 } else { index };
+    let index = (index + 2 * (symmetry & 0b11)) % 8;
+    let ring = if symmetry & 0b1000 != 0 { 2 - ring } else { ring };
+    ring * 8 + index
+}
+
+const fn get_permutations() -> [[usize; 24]; SYMMETRY_COUNT] {
+    let mut permutations = [[0; 24]; SYMMETRY_COUNT];
+    let mut symmetry = 0;
+    while symmetry < SYMMETRY_COUNT {
+        let mut position = 0;
+        while position < 24 {
+            permutations[symmetry][position] = transform(symmetry, position);
+            position += 1;
+        }
+        symmetry += 1;
+    }
+    permutations
+}
+
+impl Symmetry {
+    pub const IDENTITY: Symmetry = Symmetry(0);
+
+    pub fn all() -> impl Iterator<Item = Symmetry> {
+        (0..SYMMETRY_COUNT as u8).map(Symmetry)
+    }
+
+    /* the symmetries that keep the positions, neighbors and mills of the rules */
+    pub fn all_of(rules: RuleSet) -> impl Iterator<Item = Symmetry> {
+        Symmetry::all().filter(move |symmetry| symmetry.keeps(rules))
+    }
+
+    fn keeps(self, rules: RuleSet) -> bool {
+        let geometry = rules.get_geometry();
+        let keeps_neighbors = (0..24).all(|position| {
+            let transformed = self.transform_position(position);
+            geometry.has_position(position) == geometry.has_position(transformed)
+                && geometry.get_neighbors(position).iter()
+                    .all(|neighbor| geometry.is_neighbor(transformed, self.transform_position(*neighbor)))
+        });
+        keeps_neighbors && geometry.get_mills().iter().all(|mill| {
+            geometry.get_mills().iter().any(|other| mill.iter().all(|position| other.contains(&self.transform_position(*position))))
+        })
+    }
+
+    pub fn get_index(self) -> usize {
+        self.0 as usize
+    }
+
+    /*
+        A reflection is its own inverse, also after a rotation, and a
+        plain rotation is undone by rotating the rest of the full turn.
+    */
+    pub fn inverse(self) -> Symmetry {
+        if self.0 & 0b100 != 0 {
+            self
+        } else {
+            Symmetry((self.0 & 0b1000) | ((4 - (self.0 & 0b11)) % 4))
+        }
+    }
+
+    pub fn transform_position(self, position: usize) -> usize {
+        PERMUTATIONS[self.0 as usize][position]
+    }
+
+    pub fn transform_board(self, board: Board) -> Board {
+        let mut transformed = Board::default();
+        for (position, token) in board.token_iter().enumerate() {
+            if token != 0b00 {
+                transformed.set_token_at(self.transform_position(position), token);
+            }
+        }
+        transformed
+    }
+
+    pub fn transform_action(self, action: Action) -> Action {
+        action.get_captures().fold(
+            Action::new(action.start_position.map(|position| self.transform_position(position)), self.transform_position(action.end_position), None),
+            |transformed, position| transformed.with_capture(self.transform_position(position))
+        )
+    }
+}
+
+/*
+    Returns the canonical representative of the board, the transformed
+    board with the smallest raw value among the symmetries of the rules,
+    together with the symmetry that maps the board to it. Symmetric boards
+    have the same canonical board, and the inverse of the symmetry maps
+    the canonical board and the actions in it back to the original board.
+*/
+pub fn canonicalize(board: Board, rules: RuleSet) -> (Board, Symmetry) {
+    Symmetry::all_of(rules)
+        .map(|symmetry| (symmetry.transform_board(board), symmetry))
+        .min_by_key(|(transformed, symmetry)| (transformed.to_raw(), symmetry.0))
+        .expect("no symmetries")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::logic::{
+        action::list_actions,
+        game_state::{GameState, Phase},
+        mill_detection::MILLS,
+        position::{decode_positions, Board},
+        r#move::{apply_action, NEIGHBORS},
+        rules::{RuleSet, Variant}
+    };
+    use super::{canonicalize, Symmetry, SYMMETRY_COUNT};
+
+    #[test]
+    fn test_symmetries() {
+        let mills = MILLS.iter()
+            .map(|mill| mill.iter().fold(0u32, |mask, position| mask | 1 << position))
+            .collect::<HashSet<u32>>();
+        let mut boards = HashSet::new();
+        let board = decode_positions("WBEEEEEEEEEWEEEEEEEEEEEE".to_string()).unwrap();
+
+        for symmetry in Symmetry::all() {
+            let transformed_mills = mills.iter()
+                .map(|mask| (0..24).filter(|position| mask & (1 << position) != 0).fold(0u32, |transformed, position| transformed | 1 << symmetry.transform_position(position)))
+                .collect::<HashSet<u32>>();
+            assert_eq!(transformed_mills, mills);
+
+            for position in 0..24 {
+                for neighbor in NEIGHBORS[position].iter().filter(|neighbor| **neighbor != 24) {
+                    assert!(NEIGHBORS[symmetry.transform_position(position)].contains(&symmetry.transform_position(*neighbor)));
+                }
+                assert_eq!(symmetry.inverse().transform_position(symmetry.transform_position(position)), position);
+            }
+            boards.insert(symmetry.transform_board(board));
+        }
+        /* no symmetry maps this board to itself, except the identity */
+        assert_eq!(boards.len(), SYMMETRY_COUNT);
+        assert_eq!(Symmetry::IDENTITY.transform_board(board), board);
+        assert_eq!(Symmetry(1).transform_position(0), 2);
+        assert_eq!(Symmetry(0b100).transform_position(1), 7);
+        assert_eq!(Symmetry(0b1000).transform_position(3), 19);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE w 0 0 24 3".parse().unwrap();
+        let board = game_state.get_board();
+        let (canonical, symmetry) = canonicalize(board, RuleSet::default());
+        assert_eq!(symmetry.transform_board(board), canonical);
+        assert_eq!(symmetry.inverse().transform_board(canonical), board);
+
+        for other in Symmetry::all() {
+            let transformed = other.transform_board(board);
+            assert_eq!(canonicalize(transformed, RuleSet::default()).0, canonical);
+            assert!(canonical.to_raw() <= transformed.to_raw());
+        }
+
+        /* the rings of Six Men's Morris can't be swapped and the grid of Three Men's Morris can only be reflected */
+        let counts = Variant::ALL.map(|variant| Symmetry::all_of(RuleSet::new(variant)).count());
+        assert_eq!(counts, [SYMMETRY_COUNT, SYMMETRY_COUNT, 8, 4]);
+        let board = decode_positions("EEEEEEEWEEEEEEEEEEEEEEEE".to_string()).unwrap();
+        let three = RuleSet::new(Variant::ThreeMensMorris);
+        assert!(Symmetry::all_of(three).all(|symmetry| three.get_geometry().has_position(symmetry.transform_position(7))));
+        assert_eq!(canonicalize(board, three).0, canonicalize(decode_positions("EEEEEEEEEEEEEEEEEWEEEEEE".to_string()).unwrap(), three).0);
+
+        /* the empty board is its own canonical board */
+        assert_eq!(canonicalize(Board::default(), RuleSet::default()), (Board::default(), Symmetry::IDENTITY));
+    }
+
+    #[test]
+    fn test_transform_action() {
+        let game_state: GameState = "BWWEWBBBBEEWWWEEBEEEEEEE w 0 0 24 3".parse().unwrap();
+        let board = game_state.get_board();
+
+        for symmetry in Symmetry::all() {
+            let transformed = symmetry.transform_board(board);
+            let mut expected = list_actions(board, 0b11, Phase::Move, None, RuleSet::default())
+                .map(|action| (symmetry.transform_board(apply_action(board, &action, 0b11)), symmetry.transform_action(action)))
+                .map(|(board, action)| (board.to_raw(), action.start_position, action.end_position, action.beatable_position))
+                .collect::<Vec<_>>();
+            let mut actions = list_actions(transformed, 0b11, Phase::Move, None, RuleSet::default())
+                .map(|action| (apply_action(transformed, &action, 0b11).to_raw(), action.start_position, action.end_position, action.beatable_position))
+                .collect::<Vec<_>>();
+            expected.sort();
+            actions.sort();
+            assert_eq!(actions, expected);
+        }
+    }
+}
@@ -1,10 +1,6 @@
 extern crate good_web_game as ggez;
 use ggez::conf::Conf;
-use ui::MuehleUi;
-
-mod agent;
-mod logic;
-mod ui;
+use muehle::ui::MuehleUi;
 
 fn main() {
     let config = Conf::default()
@@ -0,0 +1,317 @@
+use std::{error::Error, fmt};
+use crate::{
+    agent::{context::SearchLimits, SearchResult},
+    logic::{
+        action::{list_actions, Action},
+        game_state::{GameState, Token},
+        notation::ParseActionError,
+        position::PositionError,
+        rules::RuleSet
+    }
+};
+
+/*
+    Line based engine protocol modelled after UCI, so the engine can be run
+    as a subprocess of other programs. The supported commands are:
+        uci
+        isready
+        ucinewgame
+        setoption name <name> [value <value>]
+        position startpos [moves <action>...]
+        position fen <position string> [moves <action>...]
+        go [depth <plies>] [nodes <count>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [infinite]
+        stop
+        quit
+    Positions use the position string of GameState and actions the
+    coordinate notation, startpos is the empty board of the rules the
+    command is parsed with, e.g.
+        position fen EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0 moves d5-g1xd1
+    The engine answers with info lines after every finished iteration and
+    the best action when the search is finished:
+        info depth 3 score cp 21 nodes 1480 time 12 pv a1 d7 g7
+        bestmove a1
+*/
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Uci,
+    IsReady,
+    NewGame,
+    SetOption(String, Option<String>),
+    Position(GameState),
+    Go(GoOptions),
+    Stop,
+    Quit
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GoOptions {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub infinite: bool
+}
+
+impl GoOptions {
+    /*
+        With a clock the engine uses a 20th of the remaining time and half
+        of the increment. Without any limit it thinks for 3 seconds.
+    */
+    pub fn get_limits(&self, player: Token) -> SearchLimits {
+        let (remaining, increment) = match player {
+            Token::Black => (self.btime, self.binc),
+            _ => (self.wtime, self.winc)
+        };
+        let max_time = match (self.movetime, remaining) {
+            (Some(movetime), _) => Some(movetime as f64 / 1000.0),
+            (None, Some(remaining)) => Some((remaining / 20 + increment.unwrap_or(0) / 2) as f64 / 1000.0),
+            (None, None) if self.depth.is_none() && self.nodes.is_none() => Some(3.0),
+            (None, None) => None
+        };
+
+        SearchLimits {
+            max_depth: self.depth,
+            max_time,
+            max_nodes: self.nodes,
+            infinite: self.infinite
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProtocolError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+    InvalidPosition(PositionError),
+    InvalidAction(String, ParseActionError),
+    IllegalAction(String)
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::UnknownCommand(command) => write!(f, "unknown command '{}'", command),
+            ProtocolError::MissingArgument(argument) => write!(f, "missing {}", argument),
+            ProtocolError::InvalidArgument(argument) => write!(f, "invalid argument '{}'", argument),
+            ProtocolError::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+            ProtocolError::InvalidAction(action, error) => write!(f, "invalid action '{}': {}", action, error),
+            ProtocolError::IllegalAction(action) => write!(f, "illegal action '{}'", action)
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+pub fn parse_command(line: &str, rules: RuleSet) -> Result<Command, ProtocolError> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    match command {
+        "uci" => Ok(Command::Uci),
+        "isready" => Ok(Command::IsReady),
+        "ucinewgame" => Ok(Command::NewGame),
+        "setoption" => parse_option(words.collect()),
+        "position" => parse_position(words.collect(), rules).map(Command::Position),
+        "go" => parse_go(words.collect()).map(Command::Go),
+        "stop" => Ok(Command::Stop),
+        "quit" => Ok(Command::Quit),
+        _ => Err(ProtocolError::UnknownCommand(command.to_string()))
+    }
+}
+
+fn parse_position(words: Vec<&str>, rules: RuleSet) -> Result<GameState, ProtocolError> {
+    let moves_index = words.iter().position(|word| *word == "moves").unwrap_or(words.len());
+    let mut game_state = match words.first() {
+        Some(&"startpos") if moves_index == 1 => GameState::new(rules),
+        Some(&"startpos") => return Err(ProtocolError::InvalidArgument(words[1].to_string())),
+        Some(&"fen") => words[1..moves_index].join(" ").parse().map_err(ProtocolError::InvalidPosition)?,
+        Some(other) => return Err(ProtocolError::InvalidArgument(other.to_string())),
+        None => return Err(ProtocolError::MissingArgument("position"))
+    };
+
+    for word in words.iter().skip(moves_index + 1) {
+        let action = word.parse::<Action>().map_err(|error| ProtocolError::InvalidAction(word.to_string(), error))?;
+        let is_legal = !game_state.is_game_over() && list_actions(
+            game_state.get_board(),
+            Token::parse_to_u8(game_state.get_player_turn()),
+            game_state.get_phase(),
+            None,
+            game_state.get_rules()
+        ).any(|possible_action| possible_action == action);
+        if !is_legal {
+            return Err(ProtocolError::IllegalAction(word.to_string()));
+        }
+        game_state.apply_action(&action);
+    }
+    Ok(game_state)
+}
+
+fn parse_option(words: Vec<&str>) -> Result<Command, ProtocolError> {
+    if words.first() != Some(&"name") {
+        return Err(ProtocolError::MissingArgument("name"));
+    }
+    let value_index = words.iter().position(|word| *word == "value").unwrap_or(words.len());
+    let name = words[1..value_index].join(" ");
+    if name.is_empty() {
+        return Err(ProtocolError::MissingArgument("name"));
+    }
+    let value = if value_index < words.len() { Some(words[value_index + 1..].join(" ")) } else { None };
+    Ok(Command::SetOption(name, value))
+}
+
+fn parse_go(words: Vec<&str>) -> Result<GoOptions, ProtocolError> {
+    let mut options = GoOptions::default();
+    let mut words = words.into_iter();
+
+    while let Some(word) = words.next() {
+        if word == "infinite" {
+            options.infinite = true;
+            continue;
+        }
+
+        let value = words.next().ok_or(ProtocolError::MissingArgument(match word {
+            "depth" => "depth",
+            "nodes" => "nodes",
+            "movetime" => "movetime",
+            "wtime" => "wtime",
+            "btime" => "btime",
+            "winc" => "winc",
+            "binc" => "binc",
+            _ => return Err(ProtocolError::InvalidArgument(word.to_string()))
+        }))?;
+        let number = value.parse::<u64>().map_err(|_| ProtocolError::InvalidArgument(value.to_string()))?;
+        match word {
+            "depth" => options.depth = Some(number as usize),
+            "nodes" => options.nodes = Some(number),
+            "movetime" => options.movetime = Some(number),
+            "wtime" => options.wtime = Some(number),
+            "btime" => options.btime = Some(number),
+            "winc" => options.winc = Some(number),
+            _ => options.binc = Some(number)
+        }
+    }
+    Ok(options)
+}
+
+/*
+    Scores are reported from the view of the player to move, won or lost
+    positions as "mate" with the number of own moves until the end of the
+    game, which is negative if the player to move loses.
+*/
+pub fn format_info(result: &SearchResult) -> String {
+    let mut line = format!("info depth {} score ", result.depth);
+    match result.mate {
+        Some(mate) => line.push_str(&format!("mate {}", mate)),
+        None => line.push_str(&format!("cp {}", result.score))
+    }
+    line.push_str(&format!(" nodes {} time {}", result.nodes, (result.time * 1000.0) as u64));
+    if !result.pv.is_empty() {
+        line.push_str(" pv");
+        for action in result.pv.iter() {
+            line.push_str(&format!(" {}", action));
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{agent::{context::SearchLimits, SearchResult}, logic::{action::Action, game_state::{GameState, Token}, notation::ParseActionError, position::PositionError, rules::{RuleSet, Variant}}};
+    use super::{format_info, parse_command, Command, GoOptions, ProtocolError};
+
+    #[test]
+    fn test_parse_simple_commands() {
+        assert_eq!(parse_command("uci", RuleSet::default()), Ok(Command::Uci));
+        assert_eq!(parse_command(" isready ", RuleSet::default()), Ok(Command::IsReady));
+        assert_eq!(parse_command("ucinewgame", RuleSet::default()), Ok(Command::NewGame));
+        assert_eq!(parse_command("setoption name Hash value 64", RuleSet::default()), Ok(Command::SetOption("Hash".to_string(), Some("64".to_string()))));
+        assert_eq!(parse_command("setoption name Clear Hash", RuleSet::default()), Ok(Command::SetOption("Clear Hash".to_string(), None)));
+        assert_eq!(parse_command("setoption value 3", RuleSet::default()), Err(ProtocolError::MissingArgument("name")));
+        assert_eq!(parse_command("stop", RuleSet::default()), Ok(Command::Stop));
+        assert_eq!(parse_command("quit", RuleSet::default()), Ok(Command::Quit));
+        assert_eq!(parse_command("hello world", RuleSet::default()), Err(ProtocolError::UnknownCommand("hello".to_string())));
+        assert_eq!(parse_command("", RuleSet::default()), Err(ProtocolError::UnknownCommand("".to_string())));
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_command("position startpos", RuleSet::default()), Ok(Command::Position(GameState::default())));
+
+        let mut game_state = GameState::default();
+        game_state.apply_action(&Action::new(None, 0, None));
+        game_state.apply_action(&Action::new(None, 8, None));
+        assert_eq!(parse_command("position startpos moves d7 d6", RuleSet::default()), Ok(Command::Position(game_state)));
+
+        let game_state = "EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0".parse::<GameState>().unwrap();
+        assert_eq!(parse_command("position fen EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0", RuleSet::default()), Ok(Command::Position(game_state)));
+        let Ok(Command::Position(game_state)) = parse_command("position fen EWWEBEEBEEEEEEEEWEEEEBEE w 0 0 18 0 moves d5-g1xd1", RuleSet::default()) else {
+            panic!("expected a position");
+        };
+        assert_eq!(game_state.to_string(), "EWWWEEEBEEEEEEEEEEEEEBEE b 0 0 19 0");
+
+        let twelve = RuleSet::new(Variant::TwelveMensMorris);
+        assert_eq!(parse_command("position startpos", twelve), Ok(Command::Position(GameState::new(twelve))));
+        let Ok(Command::Position(game_state)) = parse_command("position startpos moves d7", twelve) else {
+            panic!("expected a position");
+        };
+        assert_eq!(game_state.to_string(), "WEEEEEEEEEEEEEEEEEEEEEEE b 11 12 1 0 twelve");
+        assert_eq!(parse_command("position fen EEEEEEEEEEEEEEEEEEEEEEEE w 12 12 0 0 twelve", RuleSet::default()), Ok(Command::Position(GameState::new(twelve))));
+    }
+
+    #[test]
+    fn test_parse_position_errors() {
+        assert_eq!(parse_command("position", RuleSet::default()), Err(ProtocolError::MissingArgument("position")));
+        assert_eq!(parse_command("position startpos d7", RuleSet::default()), Err(ProtocolError::InvalidArgument("d7".to_string())));
+        assert_eq!(parse_command("position fen EEE w 9 9 0 0", RuleSet::default()), Err(ProtocolError::InvalidPosition(PositionError::InvalidLength(3))));
+        assert_eq!(parse_command("position startpos moves d4", RuleSet::default()), Err(ProtocolError::InvalidAction("d4".to_string(), ParseActionError::InvalidCoordinate("d4".to_string()))));
+        assert_eq!(parse_command("position startpos moves d7 d7", RuleSet::default()), Err(ProtocolError::IllegalAction("d7".to_string())));
+    }
+
+    #[test]
+    fn test_parse_go() {
+        assert_eq!(parse_command("go", RuleSet::default()), Ok(Command::Go(GoOptions::default())));
+        assert_eq!(parse_command("go depth 5 movetime 200", RuleSet::default()), Ok(Command::Go(GoOptions { depth: Some(5), movetime: Some(200), ..Default::default() })));
+        assert_eq!(parse_command("go nodes 10000", RuleSet::default()), Ok(Command::Go(GoOptions { nodes: Some(10000), ..Default::default() })));
+        assert_eq!(
+            parse_command("go wtime 60000 btime 30000 winc 1000 binc 0", RuleSet::default()),
+            Ok(Command::Go(GoOptions { wtime: Some(60000), btime: Some(30000), winc: Some(1000), binc: Some(0), ..Default::default() }))
+        );
+        assert_eq!(parse_command("go infinite", RuleSet::default()), Ok(Command::Go(GoOptions { infinite: true, ..Default::default() })));
+        assert_eq!(parse_command("go depth", RuleSet::default()), Err(ProtocolError::MissingArgument("depth")));
+        assert_eq!(parse_command("go depth x", RuleSet::default()), Err(ProtocolError::InvalidArgument("x".to_string())));
+        assert_eq!(parse_command("go ponder", RuleSet::default()), Err(ProtocolError::InvalidArgument("ponder".to_string())));
+    }
+
+    #[test]
+    fn test_go_limits() {
+        assert_eq!(GoOptions::default().get_limits(Token::White), SearchLimits::time(3.0));
+        assert_eq!(GoOptions { depth: Some(4), ..Default::default() }.get_limits(Token::White), SearchLimits::depth(4));
+        assert_eq!(GoOptions { nodes: Some(500), ..Default::default() }.get_limits(Token::White), SearchLimits::nodes(500));
+        assert_eq!(GoOptions { movetime: Some(500), ..Default::default() }.get_limits(Token::White), SearchLimits::time(0.5));
+        assert!(GoOptions { infinite: true, ..Default::default() }.get_limits(Token::Black).infinite);
+
+        let clock = GoOptions { wtime: Some(60000), btime: Some(20000), winc: Some(1000), depth: Some(8), ..Default::default() };
+        assert_eq!(clock.get_limits(Token::White), SearchLimits { max_depth: Some(8), ..SearchLimits::time(3.5) });
+        assert_eq!(clock.get_limits(Token::Black), SearchLimits { max_depth: Some(8), ..SearchLimits::time(1.0) });
+    }
+
+    #[test]
+    fn test_format_info() {
+        let mut result = SearchResult {
+            action: Action::new(None, 5, None),
+            score: 3,
+            mate: None,
+            depth: 2,
+            nodes: 120,
+            time: 0.0125,
+            pv: vec![Action::new(None, 5, None), Action::new(None, 0, None)]
+        };
+        assert_eq!(format_info(&result), "info depth 2 score cp 3 nodes 120 time 12 pv a1 d7");
+        result.mate = Some(-2);
+        result.pv.clear();
+        assert_eq!(format_info(&result), "info depth 2 score mate -2 nodes 120 time 12");
+    }
+}
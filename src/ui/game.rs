@@ -1,26 +1,25 @@
-use std::borrow::BorrowMut;
+use std::sync::Arc;
 use ggez::{
     event::MouseButton, 
-    graphics::{self, DrawParam, Font, Image, Text}, 
+    graphics::{self, Color, DrawMode, DrawParam, Font, Image, MeshBuilder, Text}, 
     miniquad::GraphicsContext, 
     timer, 
     Context
 };
 use crate::{
-    agent::{calculate_next_move, AiPhase}, 
+    agent::handle::SearchHandle, 
     logic::{
         action::{list_actions, Action}, 
-        game_state::{Phase, Token}, 
-        r#move::apply_action, 
-        position::{create_token_iter, get_number_of_tokens}
+        game_record::Winner,
+        game_state::Token,
+        rules::{RuleSet, Variant}
     }
 };
 use super::{
     input::InputHandler, 
     Difficulty, 
     GameResources, 
-    MuehleUi, 
-    Winner
+    MuehleUi
 };
 
 pub const SCREEN_POS: [(f32, f32); 24] = [
@@ -53,9 +52,26 @@ pub const SCREEN_POS: [(f32, f32); 24] = [
     (410.0, 410.0)
 ];
 
-fn selected_position(x: f32, y: f32) -> Option<usize> {
-    for (index, position) in SCREEN_POS.iter().enumerate() {
-        let (x1, y1) = position;
+const LINE_COLOR: Color = Color::new(0.243, 0.153, 0.192, 1.0);
+const POINT_COLOR: Color = Color::new(0.902, 0.714, 0.580, 1.0);
+
+/*
+    The grid of Three Men's Morris has the size of the outer ring. Its
+    rows are the upper sides of the rings and its columns start with the
+    left corner of a side.
+*/
+pub fn get_screen_position(rules: RuleSet, position: usize) -> (f32, f32) {
+    if rules.get_variant() == Variant::ThreeMensMorris {
+        let (row, column) = (position / 8, (position + 1) % 8);
+        (90.0 + 470.0 * column as f32, 90.0 + 470.0 * row as f32)
+    } else {
+        SCREEN_POS[position]
+    }
+}
+
+fn selected_position(x: f32, y: f32, rules: RuleSet) -> Option<usize> {
+    for index in (0..24).filter(|position| rules.get_geometry().has_position(*position)) {
+        let (x1, y1) = get_screen_position(rules, index);
         if (x - x1) * (x - x1) + (y - y1) * (y - y1) < 50.0 * 50.0 {
             return Some(index);
         }
@@ -63,10 +79,10 @@ fn selected_position(x: f32, y: f32) -> Option<usize> {
     None
 }
 
-pub fn get_token_draw_params(quad_ctx: &mut GraphicsContext, position: usize, resources: GameResources) -> DrawParam {
+pub fn get_token_draw_params(quad_ctx: &mut GraphicsContext, position: usize, rules: RuleSet, resources: GameResources) -> DrawParam {
     let (scale, x_offset, y_offset) = get_scaling(quad_ctx, resources.game_board);
     
-    let (mut x, mut y) = SCREEN_POS[position];
+    let (mut x, mut y) = get_screen_position(rules, position);
     x = x * scale + x_offset;
     y = y * scale + y_offset;
 
@@ -75,6 +91,49 @@ pub fn get_token_draw_params(quad_ctx: &mut GraphicsContext, position: usize, re
         .dest([x, y])
 }
 
+/*
+    The board image shows the board of Nine Men's Morris. The other lines
+    of a variant on all of its positions, like the diagonals of Twelve
+    Men's Morris, are drawn on top of it in the colors of the image, from
+    the edge of one point to the edge of the other. The smaller boards are
+    drawn completely, their lines together with their points.
+*/
+fn draw_board(ctx: &mut Context, quad_ctx: &mut GraphicsContext, rules: RuleSet, resources: GameResources) {
+    let (scale, x_offset, y_offset) = get_scaling(quad_ctx, resources.game_board.clone());
+    let standard = RuleSet::default().get_geometry();
+    let geometry = rules.get_geometry();
+    let full_board = geometry.get_position_count() == 24;
+    if full_board {
+        let _ = graphics::draw(ctx, quad_ctx, &resources.game_board, DrawParam::default().scale([scale, scale]).dest([x_offset, y_offset]));
+    }
+
+    let point = |x: f32, y: f32| [(x + 80.0) * scale + x_offset, (y + 80.0) * scale + y_offset];
+    let mut builder = MeshBuilder::new();
+    let mut is_empty = true;
+    for position in (0..24).filter(|position| geometry.has_position(*position)) {
+        let (x1, y1) = get_screen_position(rules, position);
+        for neighbor in geometry.get_neighbors(position).iter().copied().filter(|neighbor| *neighbor > position) {
+            if full_board && standard.is_neighbor(position, neighbor) {
+                continue;
+            }
+            let (x2, y2) = get_screen_position(rules, neighbor);
+            let length = ((x2 - x1) * (x2 - x1) + (y2 - y1) * (y2 - y1)).sqrt();
+            let (dx, dy) = ((x2 - x1) / length * 40.0, (y2 - y1) / length * 40.0);
+            let _ = builder.line(&[point(x1 + dx, y1 + dy), point(x2 - dx, y2 - dy)], 20.0 * scale, LINE_COLOR);
+            is_empty = false;
+        }
+        if !full_board {
+            let _ = builder.circle(DrawMode::fill(), point(x1, y1), 40.0 * scale, 0.5, POINT_COLOR);
+            is_empty = false;
+        }
+    }
+    if !is_empty {
+        if let Ok(mesh) = builder.build(ctx, quad_ctx) {
+            let _ = graphics::draw(ctx, quad_ctx, &mesh, DrawParam::default());
+        }
+    }
+}
+
 pub fn get_scaling(quad_ctx: &mut GraphicsContext, image: Image) -> (f32, f32, f32) {
     let (window_width, window_height) = graphics::drawable_size(quad_ctx);
 
@@ -87,60 +146,87 @@ pub fn get_scaling(quad_ctx: &mut GraphicsContext, image: Image) -> (f32, f32, f
 
 impl MuehleUi {
     fn apply_action(&mut self, action: Action) {
-        let game_state = self.game_state.borrow_mut();
-        let successor = apply_action(&game_state.get_board(), &action, Token::parse_to_u8(game_state.get_player_turn()));
-        game_state.set_board(successor);
-        game_state.change_player();
-        game_state.increase_step_counter();
-
-
-        if game_state.get_phase() == Phase::Move {
-            let parsed_player_token = Token::parse_to_u8(game_state.get_player_turn());
-            if get_number_of_tokens(game_state.get_board(), parsed_player_token) == 2 
-                || list_actions(&game_state.get_board(), parsed_player_token, game_state.get_phase(), None).count() == 0 {
-                self.winner = Some(match game_state.get_player_turn() {
-                    Token::White => Winner::Black("".to_string()),
-                    Token::Black => Winner::White("".to_string()),
-                    _ => unreachable!()
-                });
-            } else {
-                let cnt = self.repetition.entry((successor, game_state.get_player_turn())).or_insert(0);
-                *cnt += 1;
-                if *cnt >= 3 {
-                    self.winner = Some(Winner::Draw("Position repeated thrice".to_string()));
-                }
+        self.game_record.apply_action(action);
+    }
+
+    /*
+        Against the engine a single undo would hand the turn straight back
+        to it, so undo and redo always step until it's the human's turn.
+    */
+    pub fn undo(&mut self) {
+        while self.game_record.undo().is_some() {
+            if self.ai != Some(self.game_record.get_game_state().get_player_turn()) {
+                break;
             }
         }
+        self.cancel_search();
+        self.input = None;
+        self.last_search = None;
+    }
+
+    pub fn redo(&mut self) {
+        while self.game_record.redo().is_some() {
+            if self.game_record.get_winner().is_some()
+                || self.ai != Some(self.game_record.get_game_state().get_player_turn()) {
+                break;
+            }
+        }
+        self.cancel_search();
+        self.input = None;
+        self.last_search = None;
+    }
+
+    /* the engine plays the best action it has found so far */
+    pub fn move_now(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            search.stop();
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.cancel();
+        }
     }
 
     pub fn update_game(&mut self) {
-        let player_turn = self.game_state.get_player_turn();
-
-        if self.ai.is_some() && player_turn == self.ai.unwrap() {
-            let board = self.game_state.get_board();
-            let ai_phase = AiPhase::new(self.game_state.get_phase(), self.game_state.get_step_counter());
-            let max_time  = match self.difficulty.as_ref().unwrap() {
-                Difficulty::Easy => 0,
-                Difficulty::Normal => 3,
-                Difficulty::Hard => 15
-            };
-            let action = calculate_next_move(board, player_turn, ai_phase, max_time);
+        let game_state = self.game_record.get_game_state();
+        let player_turn = game_state.get_player_turn();
+
+        if self.ai == Some(player_turn) {
+            let difficulty = self.difficulty.as_ref().unwrap();
+            if self.search.is_none() {
+                let search = SearchHandle::start(game_state, difficulty.get_search_limits(), Arc::clone(&self.table), self.thread_pool.take());
+                self.thread_pool = search.get_thread_pool();
+                self.search = Some(search);
+                self.search_start = timer::time();
+            }
+            if !self.search.as_mut().unwrap().poll() {
+                return;
+            }
+            /* below Hard the engine doesn't answer instantly, so its action can be followed */
+            if *difficulty != Difficulty::Hard && timer::time() - self.search_start < 0.750 {
+                return;
+            }
+
+            let result = self.search.take()
+                .and_then(|mut search| search.take_result())
+                .expect("no legal action");
+            let action = result.action;
+            let board = game_state.get_board();
             let possible_actions = list_actions(
-                &board, 
+                board, 
                 Token::parse_to_u8(player_turn), 
-                self.game_state.get_phase(), 
-                None
+                game_state.get_phase(), 
+                None,
+                game_state.get_rules()
             ).collect::<Vec<Action>>();
 
-            if self.difficulty.is_some() && *self.difficulty.as_ref().unwrap() != Difficulty::Hard {
-                let now = timer::time();
-                while timer::time() - now < 0.750 {}
-            }
-    
             if possible_actions.contains(&action) {
                 self.apply_action(action);
+                self.last_search = Some(result);
             } else {
-                self.winner = Some(match player_turn {
+                self.game_record.set_winner(match player_turn {
                     Token::White => Winner::Black("White attempted illegal move".to_string()),
                     Token::Black => Winner::White("Black attempted illegal move".to_string()),
                     _ => unreachable!()
@@ -152,28 +238,23 @@ impl MuehleUi {
                 self.input = None;
             }
         } else {
-            self.input = Some(InputHandler::new(self.game_state));
+            self.input = Some(InputHandler::new(game_state));
         }
     }
 
     pub fn draw_game(&mut self, ctx: &mut Context, quad_ctx: &mut GraphicsContext) {
-        let (board_scale, x_offset, y_offset) = get_scaling(quad_ctx, self.resources.game_board.clone());
-        
-        let _ = graphics::draw(
-            ctx,
-            quad_ctx,
-            &self.resources.game_board,
-            DrawParam::default().scale([board_scale, board_scale]).dest([x_offset, y_offset])
-        );
+        let (board_scale, _, _) = get_scaling(quad_ctx, self.resources.game_board.clone());
+        let rules = self.game_record.get_game_state().get_rules();
+        draw_board(ctx, quad_ctx, rules, self.resources.clone());
 
         if let Some(input) = self.input.as_ref() {
             input.create_highlight_mesh(ctx, quad_ctx, self.resources.clone());
         }
 
-        create_token_iter(self.game_state.get_board())
+        self.game_record.get_game_state().get_board().token_iter()
             .enumerate()
             .for_each(|(position, token)| {
-                let token_draw_params = get_token_draw_params(quad_ctx, position, self.resources.clone());
+                let token_draw_params = get_token_draw_params(quad_ctx, position, rules, self.resources.clone());
                 let _ = match Token::parse_to_token(token) {
                     Token::White => graphics::draw(ctx, quad_ctx,&self.resources.white_token, token_draw_params),
                     Token::Black => graphics::draw(ctx, quad_ctx,&self.resources.black_token, token_draw_params),
@@ -181,7 +262,7 @@ impl MuehleUi {
                 };
             });
 
-        let (heading, subheading) = if let Some(winner) = self.winner.as_ref() {
+        let (heading, subheading) = if let Some(winner) = self.game_record.get_winner() {
             match winner {
                 Winner::White(s) => { ("White won".to_string(), s.to_string()) }
                 Winner::Black(s) => { ("Black won".to_string(), s.to_string()) }
@@ -190,10 +271,12 @@ impl MuehleUi {
         } else {
             let subheading = if let Some(input) = self.input.as_ref() {
                 input.hint()
+            } else if let Some(result) = self.search.as_ref().and_then(|search| search.get_result()) {
+                format!("Waiting for engine... depth {} (space to move now)", result.depth)
             } else {
                 "Waiting for engine...".to_string()
             };
-            (format!("{}'s turn", self.game_state.get_player_turn()), subheading)
+            (format!("{}'s turn", self.game_record.get_game_state().get_player_turn()), subheading)
         };
         let _ = graphics::draw(
             ctx, 
@@ -207,6 +290,16 @@ impl MuehleUi {
             Text::new(subheading).set_font(Font::default(), (40.0 * board_scale).into()), 
             DrawParam::default().dest([20.0 * board_scale, 70.0 * board_scale])
         );
+
+        /* shows why the engine chose its last action */
+        if let Some(result) = self.last_search.as_ref() {
+            let _ = graphics::draw(
+                ctx, 
+                quad_ctx,
+                Text::new(format!("Engine played {}: {}", result.action, result)).set_font(Font::default(), (25.0 * board_scale).into()), 
+                DrawParam::default().dest([20.0 * board_scale, 115.0 * board_scale])
+            );
+        }
     }
 
     pub fn game_handle_mouse_event(
@@ -222,14 +315,14 @@ impl MuehleUi {
         let adjusted_y = ((y - y_offset) / scale) - 80.0;
 
         if button == MouseButton::Left {
-            if let Some(position) = selected_position(adjusted_x, adjusted_y) {
+            if let Some(position) = selected_position(adjusted_x, adjusted_y, self.game_record.get_game_state().get_rules()) {
                 if let Some(input) = self.input.as_mut() {
                     input.handle_click(position);
                 }
             }
         } else if button == MouseButton::Right {
             if let Some(input) = self.input.as_mut() {
-                *input = InputHandler::new(self.game_state)
+                *input = InputHandler::new(self.game_record.get_game_state())
             }
         }
     }
@@ -1,7 +1,6 @@
 use ggez::{Context, graphics, miniquad::GraphicsContext};
 use crate::logic::action::{list_actions, Action};
 use crate::logic::game_state::{GameState, Phase, Token};
-use crate::logic::position::get_token_at;
 use super::game::get_token_draw_params;
 use super::GameResources;
 
@@ -26,16 +25,15 @@ enum InputHandlerState {
 impl InputHandler {
     pub fn new(game_state: GameState) -> Self {
         let possible_actions = list_actions(
-            &game_state.get_board(), 
+            game_state.get_board(), 
             Token::parse_to_u8(game_state.get_player_turn()), 
             game_state.get_phase(),
-            None
+            None,
+            game_state.get_rules()
         ).collect::<Vec<Action>>();
-        let can_take = possible_actions.iter().flat_map(|action| {
-           if action.beatable_position.is_some() {
-                Some(action.beatable_position.unwrap())
-           } else { None } 
-        }).collect::<Vec<usize>>();
+        let can_take = possible_actions.iter()
+            .flat_map(|action| action.get_captures())
+            .collect::<Vec<usize>>();
         Self {
             game_state,
             possible_actions,
@@ -51,7 +49,7 @@ impl InputHandler {
 
     pub fn can_click(&self, position: usize) -> bool {
         let token_at_position = Token::parse_to_token(
-            get_token_at(self.game_state.get_board(), position)
+            self.game_state.get_board().get_token_at(position)
         );
         match self.state {
             InputHandlerState::PlaceDest => {
@@ -93,15 +91,16 @@ impl InputHandler {
                     self.selected_action = Some(action);
                 }
                 InputHandlerState::Take => {
-                    if let Some(action) = self.selected_action.as_mut() {
-                        action.beatable_position = Some(position);
+                    let action = self.selected_action.map(|action| action.with_capture(position));
+                    self.selected_action = action;
+                    self.can_take = self.get_second_captures(action);
+                    if self.can_take.is_empty() {
+                        self.state = InputHandlerState::Done;
                     }
-                    self.state = InputHandlerState::Done;
                 }
                 InputHandlerState::Done => {}
             }
-            if self.selected_action.is_some() && self.state != InputHandlerState::Done  {
-                let action = self.selected_action.unwrap();
+            if let Some(action) = self.selected_action.filter(|_| self.state != InputHandlerState::Done) {
                 let is_mill_closing = self.possible_actions.iter().find(|possible_action| 
                     action.start_position == possible_action.start_position &&
                     action.end_position == possible_action.end_position
@@ -116,6 +115,28 @@ impl InputHandler {
         }
     }
 
+    /* the positions that can be captured together with the first capture of the action */
+    fn get_second_captures(&self, action: Option<Action>) -> Vec<usize> {
+        let Some(action) = action.filter(|action| action.second_beatable_position.is_none()) else {
+            return Vec::new();
+        };
+        self.possible_actions.iter()
+            .filter(|possible_action| 
+                action.start_position == possible_action.start_position &&
+                action.end_position == possible_action.end_position &&
+                possible_action.second_beatable_position.is_some()
+            )
+            .filter_map(|possible_action| {
+                let mut captures = possible_action.get_captures();
+                match (captures.next(), captures.next()) {
+                    (Some(first), second) if action.beatable_position == Some(first) => second,
+                    (first, Some(second)) if action.beatable_position == Some(second) => first,
+                    _ => None
+                }
+            })
+            .collect()
+    }
+
     pub fn get_action(&self) -> Option<Action> {
         if self.state == InputHandlerState::Done {
             self.selected_action
@@ -127,14 +148,14 @@ impl InputHandler {
     pub fn create_highlight_mesh(&self, ctx: &mut Context, quad_ctx: &mut GraphicsContext, resources: GameResources) {
         if self.state != InputHandlerState::Take {
             if let Some(src) = self.selected_pos {
-                let outline_draw_params = get_token_draw_params(quad_ctx, src, resources.clone());
+                let outline_draw_params = get_token_draw_params(quad_ctx, src, self.game_state.get_rules(), resources.clone());
                 let _ = graphics::draw(ctx, quad_ctx, &resources.token_green_outline, outline_draw_params);
             }
         }
         
         for position in 0..24 {
             if self.can_click(position) {
-                let outline_draw_params = get_token_draw_params(quad_ctx, position, resources.clone());
+                let outline_draw_params = get_token_draw_params(quad_ctx, position, self.game_state.get_rules(), resources.clone());
                 match self.state {
                     InputHandlerState::PlaceDest => {
                         let _ = graphics::draw(ctx, quad_ctx, &resources.empty_token_outline, outline_draw_params);
@@ -155,7 +176,7 @@ impl InputHandler {
     }
     
     pub fn hint(&self) -> String {
-        let tokens_left_to_place = 9 - (self.game_state.get_step_counter() / 2);
+        let tokens_left_to_place = self.game_state.get_tokens_in_hand(self.game_state.get_player_turn());
         match self.state {
             InputHandlerState::PlaceDest => {
                 format!("Place a new piece. Remaining: {}", tokens_left_to_place)
@@ -1,23 +1,35 @@
-use std::collections::HashMap;
+use std::{fmt, str::FromStr, sync::Arc};
+use rayon::ThreadPool;
 use ggez::{
-    event::{EventHandler, MouseButton}, 
+    event::{self, EventHandler, KeyCode, KeyMods, MouseButton}, 
     graphics::{self, Color, Image}, 
     miniquad::GraphicsContext, 
     Context, 
     GameResult
 };
-use crate::logic::game_state::{GameState, Token};
+use crate::{
+    agent::{
+        context::SearchLimits,
+        handle::SearchHandle,
+        transposition_table::{TranspositionTable, DEFAULT_HASH_SIZE},
+        SearchResult
+    },
+    logic::{
+        game_record::GameRecord,
+        game_state::{GameState, Token},
+        rules::{BlockedOutcome, RuleSet, Variant}
+    }
+};
 use crate::ui::input::InputHandler;
 
 pub mod input;
 pub mod setup;
 pub mod game;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod save;
 
-enum Winner {
-    White(String),
-    Black(String),
-    Draw(String)
-}
+/* the moves without a capture after which the game is drawn, if the rule is switched on */
+const NO_CAPTURE_DRAW_MOVES: u16 = 50;
 
 enum Mode {
     SinglePlayer,
@@ -31,6 +43,43 @@ enum Difficulty {
     Hard,
 }
 
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard")
+        }
+    }
+}
+
+/*
+    The difficulty limits the depth of the search on Easy and Normal, while
+    Hard uses the full time the engine is given to think.
+*/
+impl Difficulty {
+    fn get_search_limits(&self) -> SearchLimits {
+        match self {
+            Difficulty::Easy => SearchLimits::depth(1),
+            Difficulty::Normal => SearchLimits::default(),
+            Difficulty::Hard => SearchLimits::time(10.0)
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    fn from_str(difficulty: &str) -> Result<Self, Self::Err> {
+        match difficulty {
+            "Easy" => Ok(Difficulty::Easy),
+            "Normal" => Ok(Difficulty::Normal),
+            "Hard" => Ok(Difficulty::Hard),
+            _ => Err(())
+        }
+    }
+}
+
 enum State {
     Mode,
     Difficulty,
@@ -69,13 +118,17 @@ impl GameResources {
 
 pub struct MuehleUi {
     resources: GameResources,
-    game_state: GameState,
+    game_record: GameRecord,
     input: Option<InputHandler>,
-    winner: Option<Winner>,
-    repetition: HashMap<(u64, Token), u8>,
     ai: Option<Token>,
     mode: Option<Mode>,
     difficulty: Option<Difficulty>,
+    last_search: Option<SearchResult>,
+    search: Option<SearchHandle>,
+    search_start: f64,
+    table: Arc<TranspositionTable>,
+    thread_pool: Option<Arc<ThreadPool>>,
+    rules: RuleSet,
     state: State
 }
 
@@ -83,16 +136,59 @@ impl MuehleUi {
     pub fn new(ctx: &mut Context, quad_ctx: &mut GraphicsContext) -> MuehleUi {
         MuehleUi {
             resources: GameResources::new(ctx, quad_ctx),
-            game_state: GameState::default(),
+            game_record: GameRecord::default(),
             input: None,
-            winner: None,
-            repetition: HashMap::new(),
             ai: None,
             mode: None,
             difficulty: None,
+            last_search: None,
+            search: None,
+            search_start: 0.0,
+            table: Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+            thread_pool: None,
+            rules: RuleSet::default(),
             state: State::Mode
         }
     }
+
+    /*
+        The rules can be changed until the game starts. The table doesn't
+        know the reversible plies, so its scores of an earlier game are
+        dropped with every new game.
+    */
+    fn change_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+        self.game_record = GameRecord::new(GameState::new(self.rules));
+        self.table.clear();
+    }
+
+    /* switches to the next variant, which keeps the options except flying */
+    fn change_variant(&mut self) {
+        let index = Variant::ALL.iter().position(|variant| *variant == self.rules.get_variant()).unwrap_or_default();
+        self.change_rules(RuleSet::new(Variant::ALL[(index + 1) % Variant::ALL.len()])
+            .with_double_capture(self.rules.has_double_capture())
+            .with_capture_from_mills(self.rules.can_capture_from_mills())
+            .with_no_capture_draw(self.rules.get_no_capture_draw())
+            .with_blocked_outcome(self.rules.get_blocked_outcome()));
+    }
+
+    fn change_rule_option(&mut self, keycode: KeyCode) {
+        let rules = self.rules;
+        self.change_rules(match keycode {
+            KeyCode::F => rules.with_flying(rules.get_flying_tokens().is_none()),
+            KeyCode::D => rules.with_double_capture(!rules.has_double_capture()),
+            KeyCode::M => rules.with_capture_from_mills(!rules.can_capture_from_mills()),
+            KeyCode::N => rules.with_no_capture_draw(match rules.get_no_capture_draw() {
+                Some(_) => None,
+                None => Some(NO_CAPTURE_DRAW_MOVES)
+            }),
+            KeyCode::B => rules.with_blocked_outcome(match rules.get_blocked_outcome() {
+                BlockedOutcome::Loss => BlockedOutcome::Draw,
+                BlockedOutcome::Draw => BlockedOutcome::Loss
+            }),
+            _ => rules
+        });
+    }
 }
 
 impl EventHandler for MuehleUi {
@@ -100,7 +196,7 @@ impl EventHandler for MuehleUi {
         match self.state {
             State::Mode | State::Difficulty | State::Player => {},
             State::Game => {
-                if self.winner.is_some() {
+                if self.game_record.get_winner().is_some() {
                     return Ok(());
                 }
 
@@ -143,6 +239,52 @@ impl EventHandler for MuehleUi {
         }
     }
 
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        _quad_ctx: &mut GraphicsContext,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        match keycode {
+            KeyCode::Escape => {
+                self.cancel_search();
+                event::quit(ctx);
+            },
+            KeyCode::Space => self.move_now(),
+            KeyCode::Left | KeyCode::U => {
+                if let State::Game = self.state {
+                    self.undo();
+                }
+            },
+            KeyCode::Right | KeyCode::R => {
+                if let State::Game = self.state {
+                    self.redo();
+                }
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::S => {
+                if let State::Game = self.state {
+                    self.save_game();
+                }
+            },
+            KeyCode::V => {
+                if let State::Mode | State::Difficulty | State::Player = self.state {
+                    self.change_variant();
+                }
+            },
+            KeyCode::F | KeyCode::D | KeyCode::M | KeyCode::N | KeyCode::B => {
+                if let State::Mode | State::Difficulty | State::Player = self.state {
+                    self.change_rule_option(keycode);
+                }
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::L => self.load_game(),
+            _ => {}
+        }
+    }
+
     fn resize_event(
         &mut self,
         ctx: &mut Context,
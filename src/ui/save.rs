@@ -0,0 +1,77 @@
+use std::{fs, time::{SystemTime, UNIX_EPOCH}};
+use crate::logic::{
+    game_file::{read_game, write_game, GameFile},
+    game_state::Token
+};
+use super::{Difficulty, Mode, MuehleUi, State};
+
+pub const SAVE_FILE: &str = "muehle_save.txt";
+
+const HUMAN: &str = "Human";
+const ENGINE: &str = "Engine";
+
+fn current_date() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86400)
+        .unwrap_or_default() as i64;
+
+    // civil date from days since 1970-01-01, see https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+impl MuehleUi {
+    pub fn save_game(&self) {
+        let player_name = |token: Token| if self.ai == Some(token) { ENGINE } else { HUMAN };
+        let game = GameFile {
+            white: player_name(Token::White).to_string(),
+            black: player_name(Token::Black).to_string(),
+            difficulty: self.difficulty.as_ref().map(|difficulty| difficulty.to_string()),
+            date: current_date(),
+            record: self.game_record.clone()
+        };
+
+        if let Err(error) = fs::write(SAVE_FILE, write_game(&game)) {
+            eprintln!("Could not save the game to {}: {}", SAVE_FILE, error);
+        }
+    }
+
+    pub fn load_game(&mut self) {
+        let game = match fs::read_to_string(SAVE_FILE).map_err(|error| error.to_string())
+            .and_then(|content| read_game(&content).map_err(|error| error.to_string())) {
+            Ok(game) => game,
+            Err(error) => {
+                eprintln!("Could not load the game from {}: {}", SAVE_FILE, error);
+                return;
+            }
+        };
+
+        self.ai = if game.white == ENGINE {
+            Some(Token::White)
+        } else if game.black == ENGINE {
+            Some(Token::Black)
+        } else {
+            None
+        };
+        self.mode = Some(if self.ai.is_some() { Mode::MultiPlayer } else { Mode::SinglePlayer });
+        self.difficulty = game.difficulty.and_then(|difficulty| difficulty.parse().ok())
+            .or(self.ai.map(|_| Difficulty::Normal));
+        self.cancel_search();
+        self.table.clear();
+        self.rules = game.record.get_game_state().get_rules();
+        self.game_record = game.record;
+        self.last_search = None;
+        self.input = None;
+        self.state = State::Game;
+    }
+}
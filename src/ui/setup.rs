@@ -1,10 +1,10 @@
 use ggez::{
     event::MouseButton, 
-    graphics::{self, DrawParam}, 
+    graphics::{self, DrawParam, Font, Text}, 
     miniquad::GraphicsContext, 
     Context
 };
-use crate::logic::game_state::Token;
+use crate::logic::{game_state::Token, rules::BlockedOutcome};
 use super::{
     game::get_scaling, 
     Difficulty, 
@@ -70,6 +70,31 @@ impl MuehleUi {
             }
             State::Game => {}
         }
+
+        let _ = graphics::draw(
+            ctx,
+            quad_ctx,
+            Text::new(format!("{} (V to change)", self.rules.get_variant().get_title())).set_font(Font::default(), (40.0 * scale).into()),
+            DrawParam::default().dest([x_offset + 20.0 * scale, y_offset + 1160.0 * scale])
+        );
+
+        let rules = self.rules;
+        let options = [
+            ("F", "Flying", rules.get_flying_tokens().is_some()),
+            ("D", "Double capture", rules.has_double_capture()),
+            ("M", "Capture from mills", rules.can_capture_from_mills()),
+            ("N", "Draw without captures", rules.get_no_capture_draw().is_some()),
+            ("B", "Blocked draws", rules.get_blocked_outcome() == BlockedOutcome::Draw)
+        ];
+        let options = options
+            .map(|(key, name, enabled)| format!("{} {} ({})", if enabled { "[x]" } else { "[ ]" }, name, key))
+            .join("   ");
+        let _ = graphics::draw(
+            ctx,
+            quad_ctx,
+            Text::new(options).set_font(Font::default(), (30.0 * scale).into()),
+            DrawParam::default().dest([x_offset + 20.0 * scale, y_offset + 1220.0 * scale])
+        );
     }
 
     pub fn setup_handle_mouse_event(